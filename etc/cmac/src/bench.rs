@@ -0,0 +1,43 @@
+use test::Bencher;
+use aes::Aes128;
+use crypto_mac::Mac;
+use super::Cmac;
+
+#[bench]
+pub fn cmac_aes128_10(bh: & mut Bencher) {
+    let mut mac = [0u8; 16];
+    let key     = Cmac::new(Aes128::new(&[0u8; 16]));
+    let bytes   = [1u8; 10];
+    bh.iter( || {
+        let mut cmac = key;
+        cmac.input(&bytes);
+        cmac.raw_result(&mut mac);
+    });
+    bh.bytes = bytes.len() as u64;
+}
+
+#[bench]
+pub fn cmac_aes128_1k(bh: & mut Bencher) {
+    let mut mac = [0u8; 16];
+    let key     = Cmac::new(Aes128::new(&[0u8; 16]));
+    let bytes   = [1u8; 1024];
+    bh.iter( || {
+        let mut cmac = key;
+        cmac.input(&bytes);
+        cmac.raw_result(&mut mac);
+    });
+    bh.bytes = bytes.len() as u64;
+}
+
+#[bench]
+pub fn cmac_aes128_64k(bh: & mut Bencher) {
+    let mut mac = [0u8; 16];
+    let key     = Cmac::new(Aes128::new(&[0u8; 16]));
+    let bytes   = [1u8; 65536];
+    bh.iter( || {
+        let mut cmac = key;
+        cmac.input(&bytes);
+        cmac.raw_result(&mut mac);
+    });
+    bh.bytes = bytes.len() as u64;
+}
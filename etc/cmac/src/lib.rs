@@ -0,0 +1,148 @@
+//! This is an implementation of CMAC (OMAC1), the block cipher based MAC
+//! specified in NIST SP 800-38B [1] and, for AES, in RFC 4493 [2].
+//!
+//! CMAC is a CBC-MAC whose final block is XORed with one of two subkeys
+//! derived from `L = E(K, 0^128)`: `K1 = dbl(L)` when the message ends on a
+//! complete block, and `K2 = dbl(K1)` after the last block has been padded
+//! with a single one bit followed by zeros. Unlike plain CBC-MAC it is secure
+//! for messages of varying length.
+//!
+//! Only ciphers with a 128-bit block size are supported.
+//!
+//! [1] - "Recommendation for Block Cipher Modes of Operation: The CMAC Mode
+//!       for Authentication"
+//!       <https://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-38b.pdf>
+//! [2] - "The AES-CMAC Algorithm" <https://tools.ietf.org/html/rfc4493>
+
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate generic_array;
+extern crate crypto_mac;
+extern crate crypto_symmetric;
+#[cfg(test)]
+extern crate aes;
+
+use generic_array::GenericArray;
+use generic_array::typenum::U16;
+use crypto_mac::{Mac, MacResult128};
+use crypto_symmetric::BlockCipher;
+
+/// Doubling in GF(2^128) with the big-endian convention, that is a left
+/// shift by one bit and a conditional XOR of the reduction polynomial
+/// `x^128 + x^7 + x^2 + x + 1` into the last byte. It is computed without
+/// branches on the secret input.
+pub fn dbl(block: &[u8; 16]) -> [u8; 16] {
+    let mut out = [0u8; 16];
+    let mut carry = 0;
+    for i in (0..16).rev() {
+        out[i] = block[i] << 1 | carry;
+        carry = block[i] >> 7;
+    }
+    out[15] ^= 0x87 & 0u8.wrapping_sub(carry);
+    out
+}
+
+/// A structure representing the state of a CMAC computation. Cloning a
+/// keyed state which has not processed any data yet avoids recomputing
+/// the subkeys.
+#[derive(Clone, Copy)]
+pub struct Cmac<C> {
+    cipher: C,
+    k1: [u8; 16],
+    k2: [u8; 16],
+    state: [u8; 16],
+    /// The last block is only processed by `raw_result`, which needs to
+    /// know whether it is complete
+    block: [u8; 16],
+    pos: usize,
+}
+
+impl<C> Cmac<C> where C: BlockCipher<BlockSize = U16> {
+    /// Creates a new CMAC state from an already keyed `cipher`
+    pub fn new(cipher: C) -> Cmac<C> {
+        let mut cmac = Cmac {
+            cipher: cipher,
+            k1: [0u8; 16],
+            k2: [0u8; 16],
+            state: [0u8; 16],
+            block: [0u8; 16],
+            pos: 0,
+        };
+        cmac.k1 = dbl(&cmac.encrypt(&[0u8; 16]));
+        cmac.k2 = dbl(&cmac.k1);
+        cmac
+    }
+
+    fn encrypt(&self, block: &[u8; 16]) -> [u8; 16] {
+        let mut input = GenericArray::new();
+        let mut output = GenericArray::new();
+        input.copy_from_slice(block);
+        self.cipher.encrypt_block(&input, &mut output);
+        let mut out = [0u8; 16];
+        out.copy_from_slice(&output);
+        out
+    }
+
+    fn xor_state(&mut self, other: &[u8; 16]) {
+        for (s, o) in self.state.iter_mut().zip(other.iter()) {
+            *s ^= *o;
+        }
+    }
+}
+
+impl<C> Mac<MacResult128> for Cmac<C> where C: BlockCipher<BlockSize = U16> {
+    fn input(&mut self, mut data: &[u8]) {
+        while data.len() > 0 {
+            if self.pos == 16 {
+                let block = self.block;
+                self.xor_state(&block);
+                self.state = self.encrypt(&self.state);
+                self.pos = 0;
+            }
+            let n = core::cmp::min(16 - self.pos, data.len());
+            self.block[self.pos..self.pos + n].copy_from_slice(&data[..n]);
+            self.pos += n;
+            data = &data[n..];
+        }
+    }
+
+    fn reset(&mut self) {
+        self.state = [0u8; 16];
+        self.pos = 0;
+    }
+
+    fn result(&mut self) -> MacResult128 {
+        let mut mac = [0u8; 16];
+        self.raw_result(&mut mac[..]);
+        MacResult128::new(mac)
+    }
+
+    /// Writes the MAC of the data fed so far, the state is left unchanged
+    fn raw_result(&mut self, output: &mut [u8]) {
+        assert!(output.len() >= 16);
+        let mut last = self.block;
+        let subkey = if self.pos == 16 {
+            self.k1
+        } else {
+            last[self.pos] = 0x80;
+            for b in last[self.pos + 1..].iter_mut() {
+                *b = 0;
+            }
+            self.k2
+        };
+        let mut x = self.state;
+        for ((x, l), k) in x.iter_mut().zip(last.iter()).zip(subkey.iter()) {
+            *x ^= *l ^ *k;
+        }
+        output[..16].copy_from_slice(&self.encrypt(&x));
+    }
+
+    fn output_bytes(&self) -> usize { 16 }
+}
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod bench;
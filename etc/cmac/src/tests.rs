@@ -0,0 +1,89 @@
+use generic_array::typenum::U16;
+use aes::{Aes128, Aes192, Aes256};
+use crypto_mac::Mac;
+use crypto_symmetric::BlockCipher;
+use super::{Cmac, dbl};
+
+struct Test {
+    pub name: &'static str,
+    pub key: &'static [u8],
+    pub input: &'static [u8],
+    pub out: &'static [u8],
+}
+
+macro_rules! get_tests {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            Test{
+                name: $name,
+                key: include_bytes!(concat!("data/", $name, ".key.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                out: include_bytes!(concat!("data/", $name, ".out.bin")),
+            },
+        )*]
+    };
+}
+
+// The AES-128, AES-192 and AES-256 examples of NIST SP 800-38B, appendix D
+// (the AES-128 ones are also in RFC 4493), with 0, 16, 40 and 64-byte
+// messages.
+const AES128_TESTS: [Test; 4] = get_tests!("1", "2", "3", "4");
+const AES192_TESTS: [Test; 4] = get_tests!("5", "6", "7", "8");
+const AES256_TESTS: [Test; 4] = get_tests!("9", "10", "11", "12");
+
+fn check<C>(cmac: Cmac<C>, t: &Test)
+    where C: BlockCipher<BlockSize = U16> + Copy
+{
+    // Every split point exercises a different buffering of the last block
+    for i in 0..t.input.len() + 1 {
+        let (d1, d2) = t.input.split_at(i);
+        let mut cmac = cmac;
+        cmac.input(d1);
+        cmac.input(d2);
+        let mut out = [0u8; 16];
+        cmac.raw_result(&mut out);
+        assert_eq!(&out[..], t.out);
+    }
+}
+
+#[test]
+fn cmac_aes128() {
+    for t in AES128_TESTS.iter() {
+        check(Cmac::new(Aes128::new(t.key)), t);
+    }
+}
+
+#[test]
+fn cmac_aes192() {
+    for t in AES192_TESTS.iter() {
+        check(Cmac::new(Aes192::new(t.key)), t);
+    }
+}
+
+#[test]
+fn cmac_aes256() {
+    for t in AES256_TESTS.iter() {
+        check(Cmac::new(Aes256::new(t.key)), t);
+    }
+}
+
+#[test]
+fn cmac_reset() {
+    let t = &AES128_TESTS[3];
+    let mut cmac = Cmac::new(Aes128::new(t.key));
+    cmac.input(AES128_TESTS[2].input);
+    assert!(cmac.result().code() == AES128_TESTS[2].out);
+    cmac.reset();
+    cmac.input(t.input);
+    assert!(cmac.result().code() == t.out);
+}
+
+#[test]
+fn cmac_dbl() {
+    let mut block = [0u8; 16];
+    block[0] = 0x80;
+    block[15] = 0x01;
+    let mut expected = [0u8; 16];
+    expected[15] = 0x87 ^ 0x02;
+    assert_eq!(dbl(&block), expected);
+}
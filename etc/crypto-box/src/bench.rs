@@ -0,0 +1,25 @@
+use test::Bencher;
+use super::{keypair, seal, open, CryptoBox, TAG_SIZE};
+
+#[bench]
+pub fn crypto_box_precompute(bh: & mut Bencher) {
+    let (_, pk) = keypair(&[1; 32]).unwrap();
+    let (sk, _) = keypair(&[2; 32]).unwrap();
+    bh.iter( || {
+        CryptoBox::new(&pk, &sk).unwrap()
+    });
+}
+
+#[bench]
+pub fn crypto_box_1k(bh: & mut Bencher) {
+    let (alice_sk, alice_pk) = keypair(&[1; 32]).unwrap();
+    let (bob_sk, bob_pk) = keypair(&[2; 32]).unwrap();
+    let input = [1u8; 1024];
+    let mut sealed = [0u8; 1024 + TAG_SIZE];
+    let mut output = [0u8; 1024];
+    bh.iter( || {
+        seal(&bob_pk, &alice_sk, &[0; 24], &input, &mut sealed).unwrap();
+        open(&alice_pk, &bob_sk, &[0; 24], &sealed, &mut output).unwrap();
+    });
+    bh.bytes = input.len() as u64;
+}
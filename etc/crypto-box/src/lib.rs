@@ -0,0 +1,190 @@
+//! This is an implementation of NaCl's `crypto_box` public-key authenticated
+//! encryption, compatible with libsodium.
+//!
+//! A shared key is computed from the X25519 Diffie-Hellman output of the
+//! sender's secret key and the recipient's public key, passed through
+//! HSalsa20 with an all-zero nonce (`crypto_box_beforenm`). Messages are then
+//! protected with XSalsa20-Poly1305 (see the `secretbox` crate) under that
+//! key, with the tag prepended to the ciphertext.
+//!
+//! Sealed boxes (`crypto_box_seal`) allow anonymous senders: a fresh
+//! ephemeral keypair is used for every message, its public half is prepended
+//! to the box and the nonce is derived as BLAKE2b-192(ephemeral_pk || pk).
+
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate crypto_aead;
+extern crate crypto_digest;
+extern crate curve25519;
+extern crate salsa20;
+extern crate secretbox;
+extern crate sha2;
+extern crate blake2;
+
+use crypto_digest::Digest;
+use curve25519::{curve25519, curve25519_base};
+use salsa20::hsalsa20;
+use sha2::Sha512;
+use blake2::Blake2b;
+
+pub use crypto_aead::AeadError;
+pub use secretbox::TAG_SIZE;
+
+/// Number of bytes added by `seal_anonymous`: the ephemeral public key
+/// followed by the authentication tag
+pub const SEAL_OVERHEAD: usize = 32 + TAG_SIZE;
+
+/// Derives a keypair from a 32 byte seed, returning `(secret, public)`.
+/// Equivalent to `crypto_box_seed_keypair`. The seed must be uniformly
+/// random and kept secret.
+pub fn keypair(seed: &[u8]) -> Result<([u8; 32], [u8; 32]), AeadError> {
+    if seed.len() != 32 {
+        return Err(AeadError::InvalidKeyLength);
+    }
+    let mut hash = [0u8; 64];
+    let mut hasher = Sha512::new();
+    hasher.input(seed);
+    hasher.result(&mut hash);
+
+    let mut secret = [0u8; 32];
+    secret.copy_from_slice(&hash[..32]);
+    let public = public_key(&secret)?;
+    Ok((secret, public))
+}
+
+/// Computes the public key corresponding to a 32 byte secret key.
+/// Equivalent to `crypto_scalarmult_base`.
+pub fn public_key(secret_key: &[u8]) -> Result<[u8; 32], AeadError> {
+    if secret_key.len() != 32 {
+        return Err(AeadError::InvalidKeyLength);
+    }
+    Ok(curve25519_base(secret_key))
+}
+
+/// Computes the shared key used to encrypt messages between the owners of
+/// `public_key` and `secret_key`. Equivalent to `crypto_box_beforenm`.
+///
+/// Fails with `InvalidKey` if `public_key` is of small order, as the shared
+/// secret would then be zero whatever the secret key.
+pub fn precompute(public_key: &[u8], secret_key: &[u8])
+                  -> Result<[u8; 32], AeadError> {
+    if public_key.len() != 32 || secret_key.len() != 32 {
+        return Err(AeadError::InvalidKeyLength);
+    }
+    let shared = curve25519(secret_key, public_key);
+    // Same check as libsodium, without branching on the secret bytes
+    if shared.iter().fold(0, |acc, &b| acc | b) == 0 {
+        return Err(AeadError::InvalidKey);
+    }
+    let mut key = [0u8; 32];
+    hsalsa20(&shared, &[0u8; 16], &mut key);
+    Ok(key)
+}
+
+/// A box with a precomputed shared key, which avoids repeating the
+/// Diffie-Hellman computation for every message between the same peers
+#[derive(Clone, Copy)]
+pub struct CryptoBox {
+    key: [u8; 32],
+}
+
+impl CryptoBox {
+    pub fn new(public_key: &[u8], secret_key: &[u8])
+               -> Result<CryptoBox, AeadError> {
+        Ok(CryptoBox { key: precompute(public_key, secret_key)? })
+    }
+
+    /// Creates a box from a key previously obtained with `precompute`
+    pub fn from_shared_key(key: &[u8]) -> Result<CryptoBox, AeadError> {
+        if key.len() != 32 {
+            return Err(AeadError::InvalidKeyLength);
+        }
+        let mut k = [0u8; 32];
+        k.copy_from_slice(key);
+        Ok(CryptoBox { key: k })
+    }
+
+    /// Equivalent to `crypto_box_easy_afternm`, `output` must be `TAG_SIZE`
+    /// bytes longer than `input`
+    pub fn seal(&self, nonce: &[u8], input: &[u8], output: &mut [u8])
+                -> Result<(), AeadError> {
+        secretbox::seal(&self.key, nonce, input, output)
+    }
+
+    /// Equivalent to `crypto_box_open_easy_afternm`, `output` must be
+    /// `TAG_SIZE` bytes shorter than `input`. If authentication fails
+    /// `output` is zeroed.
+    pub fn open(&self, nonce: &[u8], input: &[u8], output: &mut [u8])
+                -> Result<(), AeadError> {
+        secretbox::open(&self.key, nonce, input, output)
+    }
+}
+
+/// Encrypts `input` from the owner of `secret_key` to the owner of
+/// `public_key`. Equivalent to `crypto_box_easy`.
+pub fn seal(public_key: &[u8], secret_key: &[u8], nonce: &[u8], input: &[u8],
+            output: &mut [u8]) -> Result<(), AeadError> {
+    CryptoBox::new(public_key, secret_key)?.seal(nonce, input, output)
+}
+
+/// Decrypts a box sent by the owner of `public_key` to the owner of
+/// `secret_key`. Equivalent to `crypto_box_open_easy`.
+pub fn open(public_key: &[u8], secret_key: &[u8], nonce: &[u8], input: &[u8],
+            output: &mut [u8]) -> Result<(), AeadError> {
+    CryptoBox::new(public_key, secret_key)?.open(nonce, input, output)
+}
+
+fn sealed_box_nonce(ephemeral_pk: &[u8], public_key: &[u8]) -> [u8; 24] {
+    let mut nonce = [0u8; 24];
+    let mut hasher = Blake2b::new(24);
+    hasher.input(ephemeral_pk);
+    hasher.input(public_key);
+    hasher.result(&mut nonce);
+    nonce
+}
+
+/// Encrypts `input` for the owner of `public_key` without revealing the
+/// sender. Equivalent to `crypto_box_seal`, `output` must be
+/// `SEAL_OVERHEAD` bytes longer than `input`.
+///
+/// `ephemeral_secret` must be 32 fresh random bytes which are never reused
+/// and should be wiped right after the call.
+pub fn seal_anonymous(public_key: &[u8], ephemeral_secret: &[u8],
+                      input: &[u8], output: &mut [u8])
+                      -> Result<(), AeadError> {
+    if public_key.len() != 32 || ephemeral_secret.len() != 32 {
+        return Err(AeadError::InvalidKeyLength);
+    }
+    if output.len() != input.len() + SEAL_OVERHEAD {
+        return Err(AeadError::InvalidLength);
+    }
+    // Reject a small order public key before anything is written to output
+    let crypto_box = CryptoBox::new(public_key, ephemeral_secret)?;
+    let ephemeral_pk = self::public_key(ephemeral_secret)?;
+    let nonce = sealed_box_nonce(&ephemeral_pk, public_key);
+
+    let (epk, sealed) = output.split_at_mut(32);
+    epk.copy_from_slice(&ephemeral_pk);
+    crypto_box.seal(&nonce, input, sealed)
+}
+
+/// Decrypts a box created by `seal_anonymous` for the keypair
+/// `(public_key, secret_key)`. Equivalent to `crypto_box_seal_open`,
+/// `output` must be `SEAL_OVERHEAD` bytes shorter than `input`.
+pub fn open_anonymous(public_key: &[u8], secret_key: &[u8], input: &[u8],
+                      output: &mut [u8]) -> Result<(), AeadError> {
+    if input.len() < SEAL_OVERHEAD ||
+       output.len() != input.len() - SEAL_OVERHEAD {
+        return Err(AeadError::InvalidLength);
+    }
+    let (ephemeral_pk, sealed) = input.split_at(32);
+    let nonce = sealed_box_nonce(ephemeral_pk, public_key);
+    open(ephemeral_pk, secret_key, &nonce, sealed, output)
+}
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod bench;
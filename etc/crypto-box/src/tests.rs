@@ -0,0 +1,194 @@
+use super::{keypair, public_key, precompute, seal, open, seal_anonymous,
+            open_anonymous, AeadError, CryptoBox, TAG_SIZE, SEAL_OVERHEAD};
+
+struct TestVector {
+    pub name: &'static str,
+    pub nonce: &'static [u8],
+    pub input: &'static [u8],
+    pub output: &'static [u8],
+}
+
+macro_rules! get_vectors {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            TestVector {
+                name: $name,
+                nonce: include_bytes!(concat!("data/", $name, ".nonce.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                output: include_bytes!(concat!("data/", $name, ".output.bin")),
+            },
+        )*]
+    };
+}
+
+struct SealedVector {
+    pub name: &'static str,
+    pub esk: &'static [u8],
+    pub input: &'static [u8],
+    pub output: &'static [u8],
+}
+
+macro_rules! get_sealed_vectors {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            SealedVector {
+                name: $name,
+                esk: include_bytes!(concat!("data/", $name, ".esk.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                output: include_bytes!(concat!("data/", $name, ".output.bin")),
+            },
+        )*]
+    };
+}
+
+const MAX_MSG_LEN: usize = 600;
+
+// All vectors were generated with libsodium 1.0.18. Keypairs come from
+// `crypto_box_seed_keypair`, boxes are sent from Alice to Bob.
+const ALICE_SEED: &'static [u8] = include_bytes!("data/alice.seed.bin");
+const ALICE_SK: &'static [u8] = include_bytes!("data/alice.sk.bin");
+const ALICE_PK: &'static [u8] = include_bytes!("data/alice.pk.bin");
+const BOB_SEED: &'static [u8] = include_bytes!("data/bob.seed.bin");
+const BOB_SK: &'static [u8] = include_bytes!("data/bob.sk.bin");
+const BOB_PK: &'static [u8] = include_bytes!("data/bob.pk.bin");
+const SHARED: &'static [u8] = include_bytes!("data/alice_bob.shared.bin");
+
+const TEST_VECTORS: [TestVector; 4] = get_vectors!("1", "2", "3", "4");
+
+// Sealed boxes for Bob built with fixed ephemeral keys, checked with
+// `crypto_box_seal_open`
+const SEALED_VECTORS: [SealedVector; 3] = get_sealed_vectors!(
+    "sealed_1", "sealed_2", "sealed_3");
+
+#[test]
+fn crypto_box_keypair() {
+    let (sk, pk) = keypair(ALICE_SEED).unwrap();
+    assert_eq!(&sk[..], ALICE_SK);
+    assert_eq!(&pk[..], ALICE_PK);
+    let (sk, pk) = keypair(BOB_SEED).unwrap();
+    assert_eq!(&sk[..], BOB_SK);
+    assert_eq!(&pk[..], BOB_PK);
+    assert_eq!(&public_key(BOB_SK).unwrap()[..], BOB_PK);
+}
+
+#[test]
+fn crypto_box_precompute() {
+    assert_eq!(&precompute(BOB_PK, ALICE_SK).unwrap()[..], SHARED);
+    assert_eq!(&precompute(ALICE_PK, BOB_SK).unwrap()[..], SHARED);
+    assert_eq!(precompute(&BOB_PK[..31], ALICE_SK),
+               Err(AeadError::InvalidKeyLength));
+}
+
+#[test]
+fn crypto_box_seal() {
+    let mut output = [0u8; MAX_MSG_LEN + TAG_SIZE];
+    for tv in TEST_VECTORS.iter() {
+        let out_len = tv.input.len() + TAG_SIZE;
+        seal(BOB_PK, ALICE_SK, tv.nonce, tv.input, &mut output[..out_len])
+            .unwrap();
+        assert_eq!(&output[..out_len], tv.output);
+
+        let precomputed = CryptoBox::from_shared_key(SHARED).unwrap();
+        precomputed.seal(tv.nonce, tv.input, &mut output[..out_len]).unwrap();
+        assert_eq!(&output[..out_len], tv.output);
+    }
+}
+
+#[test]
+fn crypto_box_open() {
+    let mut output = [0u8; MAX_MSG_LEN];
+    for tv in TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        assert_eq!(open(ALICE_PK, BOB_SK, tv.nonce, tv.output,
+                        &mut output[..msg_len]), Ok(()));
+        assert_eq!(&output[..msg_len], tv.input);
+
+        // Only the intended recipient can open the box
+        assert_eq!(open(ALICE_PK, ALICE_SK, tv.nonce, tv.output,
+                        &mut output[..msg_len]),
+                   Err(AeadError::AuthenticationFailed));
+        assert!(output[..msg_len].iter().all(|&b| b == 0));
+    }
+}
+
+#[test]
+fn crypto_box_seal_anonymous() {
+    let mut output = [0u8; MAX_MSG_LEN + SEAL_OVERHEAD];
+    for tv in SEALED_VECTORS.iter() {
+        let out_len = tv.input.len() + SEAL_OVERHEAD;
+        seal_anonymous(BOB_PK, tv.esk, tv.input, &mut output[..out_len])
+            .unwrap();
+        assert_eq!(&output[..out_len], tv.output);
+    }
+}
+
+#[test]
+fn crypto_box_open_anonymous() {
+    let mut output = [0u8; MAX_MSG_LEN];
+    for tv in SEALED_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        assert_eq!(open_anonymous(BOB_PK, BOB_SK, tv.output,
+                                  &mut output[..msg_len]), Ok(()));
+        assert_eq!(&output[..msg_len], tv.input);
+        assert_eq!(open_anonymous(ALICE_PK, ALICE_SK, tv.output,
+                                  &mut output[..msg_len]),
+                   Err(AeadError::AuthenticationFailed));
+    }
+
+    // Produced by `crypto_box_seal` with a random ephemeral key
+    let input = include_bytes!("data/sealed_sodium.input.bin");
+    let sealed = include_bytes!("data/sealed_sodium.output.bin");
+    let msg_len = input.len();
+    assert_eq!(open_anonymous(BOB_PK, BOB_SK, sealed, &mut output[..msg_len]),
+               Ok(()));
+    assert_eq!(&output[..msg_len], &input[..]);
+}
+
+// Points of small order on Curve25519, for which X25519 returns zero
+const SMALL_ORDER_POINTS: [[u8; 32]; 3] = [
+    [0; 32],
+    [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0xe0, 0xeb, 0x7a, 0x7c, 0x3b, 0x41, 0xb8, 0xae,
+     0x16, 0x56, 0xe3, 0xfa, 0xf1, 0x9f, 0xc4, 0x6a,
+     0xda, 0x09, 0x8d, 0xeb, 0x9c, 0x32, 0xb1, 0xfd,
+     0x86, 0x62, 0x05, 0x16, 0x5f, 0x49, 0xb8, 0x00],
+];
+
+#[test]
+fn crypto_box_small_order_keys() {
+    let mut output = [0u8; 4 + SEAL_OVERHEAD];
+    for pk in SMALL_ORDER_POINTS.iter() {
+        assert_eq!(precompute(pk, ALICE_SK), Err(AeadError::InvalidKey));
+        assert_eq!(seal(pk, ALICE_SK, &[0; 24], &[0; 4],
+                        &mut output[..4 + TAG_SIZE]),
+                   Err(AeadError::InvalidKey));
+        output = [0xff; 4 + SEAL_OVERHEAD];
+        assert_eq!(seal_anonymous(pk, ALICE_SK, &[0; 4], &mut output),
+                   Err(AeadError::InvalidKey));
+        assert!(output.iter().all(|&b| b == 0xff));
+
+        // A sealed box whose ephemeral key is of small order
+        let mut sealed = [0u8; 4 + SEAL_OVERHEAD];
+        sealed[..32].copy_from_slice(pk);
+        assert_eq!(open_anonymous(BOB_PK, BOB_SK, &sealed, &mut output[..4]),
+                   Err(AeadError::InvalidKey));
+    }
+}
+
+#[test]
+fn crypto_box_invalid_lengths() {
+    let mut output = [0u8; MAX_MSG_LEN + SEAL_OVERHEAD];
+    assert_eq!(seal(BOB_PK, ALICE_SK, &[0; 24], &[0; 4], &mut output[..4]),
+               Err(AeadError::InvalidLength));
+    assert_eq!(seal(BOB_PK, ALICE_SK, &[0; 12], &[0; 4],
+                    &mut output[..4 + TAG_SIZE]),
+               Err(AeadError::InvalidNonceLength));
+    assert_eq!(open_anonymous(BOB_PK, BOB_SK, &[0; SEAL_OVERHEAD - 1],
+                              &mut output[..0]),
+               Err(AeadError::InvalidLength));
+    assert_eq!(CryptoBox::from_shared_key(&SHARED[..16]).err(),
+               Some(AeadError::InvalidKeyLength));
+    assert_eq!(keypair(&ALICE_SEED[..31]), Err(AeadError::InvalidKeyLength));
+    assert_eq!(public_key(&BOB_SK[..16]), Err(AeadError::InvalidKeyLength));
+}
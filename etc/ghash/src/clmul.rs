@@ -0,0 +1,148 @@
+//! Multiplication in GF(2^128) with the PCLMULQDQ instruction, following
+//! the Intel white paper by Gueron and Kounavis.
+//!
+//! Elements are loaded as 128-bit big-endian integers, which reverses the
+//! bit order with respect to GHASH's polynomial representation. The
+//! product of two such values is therefore shifted left by one bit before
+//! being reduced.
+
+use core::arch::x86_64::*;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use super::Gf128;
+
+static SUPPORTED: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns `true` if the processor supports PCLMULQDQ
+pub fn is_supported() -> bool {
+    // CPUID can be slow (it traps into the hypervisor on virtual machines),
+    // so the result is cached: 0 means not checked yet, 1 unsupported and 2
+    // supported
+    match SUPPORTED.load(Ordering::Relaxed) {
+        1 => false,
+        2 => true,
+        _ => {
+            // CPUID leaf 1, ECX bit 1
+            let info = unsafe { __cpuid(1) };
+            let supported = info.ecx & (1 << 1) != 0;
+            SUPPORTED.store(if supported { 2 } else { 1 }, Ordering::Relaxed);
+            supported
+        }
+    }
+}
+
+#[inline]
+unsafe fn load(x: Gf128) -> __m128i {
+    _mm_set_epi64x(x.hi as i64, x.lo as i64)
+}
+
+#[inline]
+unsafe fn store(x: __m128i) -> Gf128 {
+    let mut d = [0u64; 2];
+    _mm_storeu_si128(d.as_mut_ptr() as *mut __m128i, x);
+    Gf128 { hi: d[1], lo: d[0] }
+}
+
+/// 256-bit carry-less product of `a` and `b`, as (low, high) halves
+#[inline]
+#[target_feature(enable = "pclmulqdq")]
+unsafe fn mul_unreduced(a: __m128i, b: __m128i) -> (__m128i, __m128i) {
+    let lo = _mm_clmulepi64_si128(a, b, 0x00);
+    let hi = _mm_clmulepi64_si128(a, b, 0x11);
+    let mid = _mm_xor_si128(_mm_clmulepi64_si128(a, b, 0x10),
+                            _mm_clmulepi64_si128(a, b, 0x01));
+    (_mm_xor_si128(lo, _mm_slli_si128(mid, 8)),
+     _mm_xor_si128(hi, _mm_srli_si128(mid, 8)))
+}
+
+/// Shifts a product left by one bit and reduces it modulo
+/// x^128 + x^7 + x^2 + x + 1
+#[inline]
+#[target_feature(enable = "pclmulqdq")]
+unsafe fn reduce(lo: __m128i, hi: __m128i) -> __m128i {
+    // Shift the 256-bit value (hi:lo) left by one bit
+    let lo_carry = _mm_srli_epi32(lo, 31);
+    let hi_carry = _mm_srli_epi32(hi, 31);
+    let mut lo = _mm_slli_epi32(lo, 1);
+    let mut hi = _mm_slli_epi32(hi, 1);
+    let cross = _mm_srli_si128(lo_carry, 12);
+    hi = _mm_or_si128(hi, _mm_slli_si128(hi_carry, 4));
+    hi = _mm_or_si128(hi, cross);
+    lo = _mm_or_si128(lo, _mm_slli_si128(lo_carry, 4));
+
+    // First phase of the reduction
+    let t = _mm_xor_si128(_mm_xor_si128(_mm_slli_epi32(lo, 31),
+                                        _mm_slli_epi32(lo, 30)),
+                          _mm_slli_epi32(lo, 25));
+    let t_hi = _mm_srli_si128(t, 4);
+    lo = _mm_xor_si128(lo, _mm_slli_si128(t, 12));
+
+    // Second phase of the reduction
+    let mut u = _mm_xor_si128(_mm_xor_si128(_mm_srli_epi32(lo, 1),
+                                            _mm_srli_epi32(lo, 2)),
+                              _mm_srli_epi32(lo, 7));
+    u = _mm_xor_si128(u, t_hi);
+    lo = _mm_xor_si128(lo, u);
+    _mm_xor_si128(hi, lo)
+}
+
+#[inline]
+#[target_feature(enable = "pclmulqdq")]
+unsafe fn mul(a: __m128i, b: __m128i) -> __m128i {
+    let (lo, hi) = mul_unreduced(a, b);
+    reduce(lo, hi)
+}
+
+/// Powers H^1 to H^4 of the hash key
+#[derive(Clone, Copy)]
+pub struct Key {
+    powers: [__m128i; 4],
+}
+
+impl Key {
+    /// Must only be called if `is_supported` returned `true`
+    pub fn new(h: Gf128) -> Key {
+        unsafe {
+            let h1 = load(h);
+            let h2 = mul(h1, h1);
+            let h3 = mul(h2, h1);
+            let h4 = mul(h3, h1);
+            Key { powers: [h1, h2, h3, h4] }
+        }
+    }
+
+    pub fn update(&self, y: &mut Gf128, blocks: &[u8]) {
+        unsafe { self.update_impl(y, blocks) }
+    }
+
+    #[target_feature(enable = "pclmulqdq")]
+    unsafe fn update_impl(&self, y: &mut Gf128, blocks: &[u8]) {
+        let p = &self.powers;
+        let n = blocks.len() - blocks.len() % 64;
+        let mut acc = load(*y);
+
+        // (((y + X1)H + X2)H + X3)H + X4)H
+        //     = (y + X1)H^4 + X2 H^3 + X3 H^2 + X4 H
+        for chunk in blocks[..n].chunks(64) {
+            let x0 = _mm_xor_si128(acc, load(Gf128::from_bytes(&chunk[..16])));
+            let x1 = load(Gf128::from_bytes(&chunk[16..32]));
+            let x2 = load(Gf128::from_bytes(&chunk[32..48]));
+            let x3 = load(Gf128::from_bytes(&chunk[48..]));
+
+            let (mut lo, mut hi) = mul_unreduced(x0, p[3]);
+            for &(x, h) in [(x1, p[2]), (x2, p[1]), (x3, p[0])].iter() {
+                let (l, h) = mul_unreduced(x, h);
+                lo = _mm_xor_si128(lo, l);
+                hi = _mm_xor_si128(hi, h);
+            }
+            acc = reduce(lo, hi);
+        }
+
+        for block in blocks[n..].chunks(16) {
+            let x = _mm_xor_si128(acc, load(Gf128::from_bytes(block)));
+            acc = mul(x, p[0]);
+        }
+
+        *y = store(acc);
+    }
+}
@@ -3,107 +3,136 @@
 //! data, and C is the ciphertext. GHASH can be used as a keyed MAC, if C is
 //! left empty.
 //!
-//! In order to ensure constant time computation it uses the approach described
-//! in [2] section 5.2.
+//! Two constant time backends are provided for the multiplication in
+//! GF(2^128), selected at runtime when the key is set up:
+//!
+//! - on x86_64 processors supporting it, the carry-less multiplication
+//!   instruction PCLMULQDQ is used, following [2];
+//! - otherwise integer multiplications with "holes" compute 64x64-bit
+//!   carry-less products, as done in BearSSL's `ghash_ctmul64` [3].
+//!
+//! Both aggregate the reduction over four blocks, using precomputed powers
+//! H^1 to H^4 of the key.
 //!
 //! [1] - "The Galois/Counter Mode of Operation (GCM)" - David A. McGrew and John Viega
 //!       <http://csrc.nist.gov/groups/ST/toolkit/BCM/documents/proposedmodes/gcm/gcm-spec.pdf>
-//! [2] - "Faster and Timing-Attack Resistant AES-GCM" - Emilia Käsper and Peter Schwabe
-//!       <http://cryptojedi.org/papers/aesbs-20090616.pdf>
+//! [2] - "Intel Carry-Less Multiplication Instruction and its Usage for
+//!       Computing the GCM Mode" - Shay Gueron and Michael E. Kounavis
+//! [3] - "Constant-Time Mul" - Thomas Pornin
+//!       <https://www.bearssl.org/constanttime.html#ghash-for-gcm>
 
 #![no_std]
 #![feature(test)]
 extern crate test;
 extern crate crypto_bytes;
 extern crate crypto_mac;
-extern crate simd;
 
 use core::ops::BitXor;
-use core::mem;
 
-use crypto_bytes::{read_u32_be, write_u32_be, copy_memory};
+use crypto_bytes::{read_u64v_be, write_u64_be, copy_memory};
 use crypto_mac::{Mac, MacResult128};
 
+mod soft;
+#[cfg(target_arch = "x86_64")]
+mod clmul;
+
 /// A struct representing an element in GF(2^128)
-/// x^0 is the msb, while x^127 is the lsb
-#[derive(Clone, Copy)]
-struct Gf128 { d: simd::u32x4 }
+/// x^0 is the msb of `hi`, while x^127 is the lsb of `lo`
+///
+/// It is public so that other universal hashes over the same field (e.g.
+/// POLYVAL) can reuse the constant time multiplication.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gf128 { hi: u64, lo: u64 }
 
 impl Gf128 {
-    fn new(a: u32, b: u32, c: u32, d: u32) -> Gf128 {
-        Gf128 { d: simd::u32x4(a, b, c, d) }
+    pub fn zero() -> Gf128 {
+        Gf128 { hi: 0, lo: 0 }
     }
 
-    fn from_bytes(bytes: &[u8]) -> Gf128 {
+    pub fn from_bytes(bytes: &[u8]) -> Gf128 {
         assert!(bytes.len() == 16);
-        let d = read_u32_be(&bytes[0..4]);
-        let c = read_u32_be(&bytes[4..8]);
-        let b = read_u32_be(&bytes[8..12]);
-        let a = read_u32_be(&bytes[12..16]);
-        Gf128::new(a, b, c, d)
+        let mut d = [0u64; 2];
+        read_u64v_be(&mut d, bytes);
+        Gf128 { hi: d[0], lo: d[1] }
     }
 
-    fn to_bytes(&self) -> [u8; 16] {
-        let simd::u32x4(a, b, c, d) = self.d;
-        let mut result: [u8; 16] = unsafe { mem::uninitialized() };
-
-        write_u32_be(&mut result[0..4], d);
-        write_u32_be(&mut result[4..8], c);
-        write_u32_be(&mut result[8..12], b);
-        write_u32_be(&mut result[12..16], a);
-
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut result = [0u8; 16];
+        write_u64_be(&mut result[..8], self.hi);
+        write_u64_be(&mut result[8..], self.lo);
         result
     }
 
-    /// Multiply the element by x modulo x^128
-    /// This is equivalent to a rightshift in the bit representation
-    fn times_x(self) -> Gf128 {
-        let simd::u32x4(a, b, c, d) = self.d;
-        Gf128::new(a >> 1 | b << 31, b >> 1 | c << 31, c >> 1 |  d << 31, d >> 1)
-    }
-
     /// Multiply the element by x modulo x^128 + x^7 + x^2 + x + 1
     /// This is equivalent to a rightshift, followed by an XOR iff the lsb was
     /// set, in the bit representation
-    fn times_x_reduce(self) -> Gf128 {
-        let r = Gf128::new(0, 0, 0, 0b1110_0001 << 24);
-        self.cond_xor(r, self.times_x())
+    pub fn times_x_reduce(self) -> Gf128 {
+        let mask = 0u64.wrapping_sub(self.lo & 1);
+        Gf128 {
+            hi: (self.hi >> 1) ^ (0xe1 << 56 & mask),
+            lo: (self.lo >> 1) | (self.hi << 63),
+        }
     }
+}
+
+impl BitXor for Gf128 {
+    type Output = Gf128;
 
-    /// Adds y, and multiplies with h using a precomputed array of the values
-    /// h * x^0 to h * x^127
-    fn add_and_mul(&mut self, y: Gf128, hs: &[Gf128; 128]) {
-        *self = *self ^ y;
-        let mut x = mem::replace(self, Gf128::new(0, 0, 0, 0));
+    fn bitxor(self, rhs: Gf128) -> Gf128 {
+        Gf128 { hi: self.hi ^ rhs.hi, lo: self.lo ^ rhs.lo }
+    }
+}
 
-        for &y in hs.iter().rev() {
-            *self = x.cond_xor(y, *self);
-            x = x.times_x();
+#[derive(Clone, Copy)]
+enum Backend {
+    Soft(soft::Key),
+    #[cfg(target_arch = "x86_64")]
+    Clmul(clmul::Key),
+}
+
+/// Multiplication by a fixed hash key H, with the powers of H precomputed
+/// for the fastest backend available on the running processor
+#[derive(Clone, Copy)]
+pub struct Gf128Key {
+    backend: Backend,
+}
+
+impl Gf128Key {
+    pub fn new(h: Gf128) -> Gf128Key {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if clmul::is_supported() {
+                return Gf128Key { backend: Backend::Clmul(clmul::Key::new(h)) };
+            }
         }
+        Gf128Key::new_soft(h)
     }
 
-    /// This XORs the value of y with x if the LSB of self is set, otherwise
-    /// y is returned
-    fn cond_xor(self, x: Gf128, y: Gf128) -> Gf128 {
-        let lsb = simd::u32x4(1, 0, 0, 0);
-        let m = if (self.d & lsb) == lsb {0xffffffff} else {0};
-        let mask = simd::u32x4(m, m, m, m);
-        Gf128 { d: (x.d & mask) ^ y.d }
+    fn new_soft(h: Gf128) -> Gf128Key {
+        Gf128Key { backend: Backend::Soft(soft::Key::new(h)) }
     }
-}
 
-impl BitXor for Gf128 {
-    type Output = Gf128;
+    /// Computes `y = (y ^ x) * H`
+    pub fn add_and_mul(&self, y: &mut Gf128, x: Gf128) {
+        self.update(y, &x.to_bytes());
+    }
 
-    fn bitxor(self, rhs: Gf128) -> Gf128 {
-        Gf128 { d: self.d ^ rhs.d }
+    /// Computes `y = (y ^ X_1) * H` for every block `X_i` in turn, the
+    /// length of `blocks` must be a multiple of 16
+    pub fn update(&self, y: &mut Gf128, blocks: &[u8]) {
+        assert!(blocks.len() % 16 == 0);
+        match self.backend {
+            Backend::Soft(ref key) => key.update(y, blocks),
+            #[cfg(target_arch = "x86_64")]
+            Backend::Clmul(ref key) => key.update(y, blocks),
+        }
     }
 }
 
 /// A structure representing the state of a GHASH computation
 #[derive(Copy)]
 pub struct Ghash {
-    hs: [Gf128; 128],
+    key: Gf128Key,
     state: Gf128,
     a_len: usize,
     rest: Option<[u8; 16]>,
@@ -116,7 +145,7 @@ impl Clone for Ghash { fn clone(&self) -> Ghash { *self } }
 /// C was provided
 #[derive(Copy)]
 pub struct GhashWithC {
-    hs: [Gf128; 128],
+    key: Gf128Key,
     state: Gf128,
     a_len: usize,
     c_len: usize,
@@ -126,7 +155,7 @@ pub struct GhashWithC {
 impl Clone for GhashWithC { fn clone(&self) -> GhashWithC { *self } }
 
 fn update(state: &mut Gf128, len: &mut usize, data: &[u8], srest: &mut Option<[u8; 16]>,
-          hs: &[Gf128; 128]) {
+          key: &Gf128Key) {
     let rest_len = *len % 16;
     let data_len = data.len();
     *len += data_len;
@@ -142,17 +171,14 @@ fn update(state: &mut Gf128, len: &mut usize, data: &[u8], srest: &mut Option<[u
 
             let (fill, data) = data.split_at(16 - rest_len);
             copy_memory(fill, &mut rest[rest_len..]);
-            state.add_and_mul(Gf128::from_bytes(&rest), hs);
+            key.update(state, &rest);
             data
         }
     };
 
     let (data, rest) = data.split_at(data_len - data_len % 16);
 
-    for chunk in data.chunks(16) {
-        let x = Gf128::from_bytes(chunk);
-        state.add_and_mul(x, hs);
-    }
+    key.update(state, data);
 
     if rest.len() != 0 {
         let mut tmp = [0; 16];
@@ -166,18 +192,9 @@ impl Ghash {
     #[inline]
     pub fn new(h: &[u8]) -> Ghash {
         assert!(h.len() == 16);
-        let mut table: [Gf128; 128] = unsafe { mem::uninitialized() };
-
-        // Precompute values for h * x^0 to h * x^127
-        let mut h = Gf128::from_bytes(h);
-        for poly in table.iter_mut() {
-            *poly = h;
-            h = h.times_x_reduce();
-        }
-
         Ghash {
-            hs: table,
-            state: Gf128::new(0, 0, 0, 0),
+            key: Gf128Key::new(Gf128::from_bytes(h)),
+            state: Gf128::zero(),
             a_len: 0,
             rest: None,
             finished: false
@@ -186,7 +203,7 @@ impl Ghash {
 
     fn flush(&mut self) {
         for rest in self.rest.take().iter() {
-            self.state.add_and_mul(Gf128::from_bytes(rest), &self.hs);
+            self.key.update(&mut self.state, rest);
         }
     }
 
@@ -194,7 +211,7 @@ impl Ghash {
     #[inline]
     pub fn input_a(mut self, a: &[u8]) -> Ghash {
         assert!(!self.finished);
-        update(&mut self.state, &mut self.a_len, a, &mut self.rest, &self.hs);
+        update(&mut self.state, &mut self.a_len, a, &mut self.rest, &self.key);
         self
     }
 
@@ -205,11 +222,11 @@ impl Ghash {
         self.flush();
 
         let mut c_len = 0;
-        update(&mut self.state, &mut c_len, c, &mut self.rest, &self.hs);
+        update(&mut self.state, &mut c_len, c, &mut self.rest, &self.key);
 
-        let Ghash { hs, state, a_len, rest, .. } = self;
+        let Ghash { key, state, a_len, rest, .. } = self;
         GhashWithC {
-            hs: hs,
+            key: key,
             state: state,
             a_len: a_len,
             c_len: c_len,
@@ -224,8 +241,8 @@ impl Ghash {
             self.flush();
 
             let a_len = self.a_len as u64 * 8;
-            let lens = Gf128::new(0, 0, a_len as u32, (a_len >> 32) as u32);
-            self.state.add_and_mul(lens, &self.hs);
+            let lens = Gf128 { hi: a_len, lo: 0 };
+            self.key.add_and_mul(&mut self.state, lens);
 
             self.finished = true;
         }
@@ -238,7 +255,7 @@ impl GhashWithC {
     /// Feeds data for GHASH's C input
     #[inline]
     pub fn input_c(mut self, c: &[u8]) -> GhashWithC {
-        update(&mut self.state, &mut self.c_len, c, &mut self.rest, &self.hs);
+        update(&mut self.state, &mut self.c_len, c, &mut self.rest, &self.key);
         self
     }
 
@@ -246,14 +263,13 @@ impl GhashWithC {
     #[inline]
     pub fn result(mut self) -> [u8; 16] {
         for rest in self.rest.take().iter() {
-            self.state.add_and_mul(Gf128::from_bytes(rest), &self.hs);
+            self.key.update(&mut self.state, rest);
         }
 
         let a_len = self.a_len as u64 * 8;
         let c_len = self.c_len as u64 * 8;
-        let lens = Gf128::new(c_len as u32, (c_len >> 32) as u32,
-                              a_len as u32, (a_len >> 32) as u32);
-        self.state.add_and_mul(lens, &self.hs);
+        let lens = Gf128 { hi: a_len, lo: c_len };
+        self.key.add_and_mul(&mut self.state, lens);
 
         self.state.to_bytes()
     }
@@ -262,11 +278,11 @@ impl GhashWithC {
 impl Mac<MacResult128> for Ghash {
     fn input(&mut self, data: &[u8]) {
         assert!(!self.finished);
-        update(&mut self.state, &mut self.a_len, data, &mut self.rest, &self.hs);
+        update(&mut self.state, &mut self.a_len, data, &mut self.rest, &self.key);
     }
 
     fn reset(&mut self) {
-        self.state = Gf128::new(0, 0, 0, 0);
+        self.state = Gf128::zero();
         self.a_len = 0;
         self.rest = None;
         self.finished = false;
@@ -284,8 +300,8 @@ impl Mac<MacResult128> for Ghash {
             self.flush();
 
             let a_len = self.a_len as u64 * 8;
-            let lens = Gf128::new(0, 0, a_len as u32, (a_len >> 32) as u32);
-            self.state.add_and_mul(lens, &self.hs);
+            let lens = Gf128 { hi: a_len, lo: 0 };
+            self.key.add_and_mul(&mut self.state, lens);
 
             self.finished = true;
         }
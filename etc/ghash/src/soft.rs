@@ -0,0 +1,158 @@
+//! Portable constant time multiplication in GF(2^128).
+//!
+//! 64x64-bit carry-less products are computed with regular integer
+//! multiplications on operands in which only every fourth bit is kept, so
+//! that carries land in the "holes" and are masked out. GHASH's reversed
+//! bit order is handled by computing the high half of each product from
+//! bit-reversed operands. This is the `ghash_ctmul64` approach of BearSSL.
+
+use super::Gf128;
+
+/// Low 64 bits of the carry-less product of `x` and `y`
+fn bmul64(x: u64, y: u64) -> u64 {
+    let x0 = x & 0x1111111111111111;
+    let x1 = x & 0x2222222222222222;
+    let x2 = x & 0x4444444444444444;
+    let x3 = x & 0x8888888888888888;
+    let y0 = y & 0x1111111111111111;
+    let y1 = y & 0x2222222222222222;
+    let y2 = y & 0x4444444444444444;
+    let y3 = y & 0x8888888888888888;
+
+    let mut z0 = x0.wrapping_mul(y0) ^ x1.wrapping_mul(y3) ^
+                 x2.wrapping_mul(y2) ^ x3.wrapping_mul(y1);
+    let mut z1 = x0.wrapping_mul(y1) ^ x1.wrapping_mul(y0) ^
+                 x2.wrapping_mul(y3) ^ x3.wrapping_mul(y2);
+    let mut z2 = x0.wrapping_mul(y2) ^ x1.wrapping_mul(y1) ^
+                 x2.wrapping_mul(y0) ^ x3.wrapping_mul(y3);
+    let mut z3 = x0.wrapping_mul(y3) ^ x1.wrapping_mul(y2) ^
+                 x2.wrapping_mul(y1) ^ x3.wrapping_mul(y0);
+    z0 &= 0x1111111111111111;
+    z1 &= 0x2222222222222222;
+    z2 &= 0x4444444444444444;
+    z3 &= 0x8888888888888888;
+    z0 | z1 | z2 | z3
+}
+
+fn rev64(mut x: u64) -> u64 {
+    x = ((x & 0x5555555555555555) << 1) | ((x >> 1) & 0x5555555555555555);
+    x = ((x & 0x3333333333333333) << 2) | ((x >> 2) & 0x3333333333333333);
+    x = ((x & 0x0F0F0F0F0F0F0F0F) << 4) | ((x >> 4) & 0x0F0F0F0F0F0F0F0F);
+    x = ((x & 0x00FF00FF00FF00FF) << 8) | ((x >> 8) & 0x00FF00FF00FF00FF);
+    x = ((x & 0x0000FFFF0000FFFF) << 16) | ((x >> 16) & 0x0000FFFF0000FFFF);
+    (x << 32) | (x >> 32)
+}
+
+/// A factor prepared for Karatsuba multiplication: its halves, their sum,
+/// and the bit-reversed versions of all three
+#[derive(Clone, Copy)]
+struct Factor {
+    h0: u64,
+    h1: u64,
+    h2: u64,
+    h0r: u64,
+    h1r: u64,
+    h2r: u64,
+}
+
+impl Factor {
+    fn new(h: Gf128) -> Factor {
+        let h0r = rev64(h.lo);
+        let h1r = rev64(h.hi);
+        Factor {
+            h0: h.lo,
+            h1: h.hi,
+            h2: h.lo ^ h.hi,
+            h0r: h0r,
+            h1r: h1r,
+            h2r: h0r ^ h1r,
+        }
+    }
+}
+
+/// 256-bit product of `y` and `h`, shifted to account for the reversed bit
+/// order but not yet reduced
+fn mul_unreduced(y: Gf128, h: &Factor) -> [u64; 4] {
+    let y0 = y.lo;
+    let y1 = y.hi;
+    let y2 = y0 ^ y1;
+    let y0r = rev64(y0);
+    let y1r = rev64(y1);
+    let y2r = y0r ^ y1r;
+
+    let z0 = bmul64(y0, h.h0);
+    let z1 = bmul64(y1, h.h1);
+    let mut z2 = bmul64(y2, h.h2);
+    let mut z0h = bmul64(y0r, h.h0r);
+    let mut z1h = bmul64(y1r, h.h1r);
+    let mut z2h = bmul64(y2r, h.h2r);
+    z2 ^= z0 ^ z1;
+    z2h ^= z0h ^ z1h;
+    z0h = rev64(z0h) >> 1;
+    z1h = rev64(z1h) >> 1;
+    z2h = rev64(z2h) >> 1;
+
+    let v0 = z0;
+    let v1 = z0h ^ z2;
+    let v2 = z1 ^ z2h;
+    let v3 = z1h;
+    [v0 << 1,
+     (v1 << 1) | (v0 >> 63),
+     (v2 << 1) | (v1 >> 63),
+     (v3 << 1) | (v2 >> 63)]
+}
+
+/// Reduces a product modulo x^128 + x^7 + x^2 + x + 1
+fn reduce(v: [u64; 4]) -> Gf128 {
+    let v0 = v[0];
+    let mut v1 = v[1];
+    let mut v2 = v[2];
+    let mut v3 = v[3];
+    v2 ^= v0 ^ (v0 >> 1) ^ (v0 >> 2) ^ (v0 >> 7);
+    v1 ^= (v0 << 63) ^ (v0 << 62) ^ (v0 << 57);
+    v3 ^= v1 ^ (v1 >> 1) ^ (v1 >> 2) ^ (v1 >> 7);
+    v2 ^= (v1 << 63) ^ (v1 << 62) ^ (v1 << 57);
+    Gf128 { hi: v3, lo: v2 }
+}
+
+fn xor4(a: &mut [u64; 4], b: [u64; 4]) {
+    for (a, b) in a.iter_mut().zip(b.iter()) {
+        *a ^= *b;
+    }
+}
+
+/// Powers H^1 to H^4 of the hash key
+#[derive(Clone, Copy)]
+pub struct Key {
+    powers: [Factor; 4],
+}
+
+impl Key {
+    pub fn new(h: Gf128) -> Key {
+        let f1 = Factor::new(h);
+        let h2 = reduce(mul_unreduced(h, &f1));
+        let h3 = reduce(mul_unreduced(h2, &f1));
+        let h4 = reduce(mul_unreduced(h3, &f1));
+        Key { powers: [f1, Factor::new(h2), Factor::new(h3), Factor::new(h4)] }
+    }
+
+    pub fn update(&self, y: &mut Gf128, blocks: &[u8]) {
+        let p = &self.powers;
+        let n = blocks.len() - blocks.len() % 64;
+
+        // (((y + X1)H + X2)H + X3)H + X4)H
+        //     = (y + X1)H^4 + X2 H^3 + X3 H^2 + X4 H
+        for chunk in blocks[..n].chunks(64) {
+            let x0 = *y ^ Gf128::from_bytes(&chunk[..16]);
+            let mut v = mul_unreduced(x0, &p[3]);
+            xor4(&mut v, mul_unreduced(Gf128::from_bytes(&chunk[16..32]), &p[2]));
+            xor4(&mut v, mul_unreduced(Gf128::from_bytes(&chunk[32..48]), &p[1]));
+            xor4(&mut v, mul_unreduced(Gf128::from_bytes(&chunk[48..]), &p[0]));
+            *y = reduce(v);
+        }
+
+        for block in blocks[n..].chunks(16) {
+            *y = reduce(mul_unreduced(*y ^ Gf128::from_bytes(block), &p[0]));
+        }
+    }
+}
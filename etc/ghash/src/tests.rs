@@ -1,4 +1,4 @@
-use super::Ghash;
+use super::{Ghash, Gf128, Gf128Key};
 
 struct Test {
     pub name: &'static str,
@@ -45,4 +45,26 @@ fn ghash_split() {
         let res = ghash.input_a(a1).input_a(a2).input_c(c1).input_c(c2).result();
         assert_eq!(&res[..], t.out);
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn ghash_backends() {
+    // Compare the selected backend with the portable one, for every number
+    // of blocks around the 4-block aggregation boundaries
+    let mut data = [0u8; 16 * 11];
+    for (i, b) in data.iter_mut().enumerate() {
+        *b = (i * 37 + 11) as u8;
+    }
+    for t in TESTS.iter() {
+        let h = Gf128::from_bytes(t.h);
+        let key = Gf128Key::new(h);
+        let soft = Gf128Key::new_soft(h);
+        for n in 0..12 {
+            let mut y1 = Gf128::from_bytes(t.out);
+            let mut y2 = y1;
+            key.update(&mut y1, &data[..16 * n]);
+            soft.update(&mut y2, &data[..16 * n]);
+            assert_eq!(y1, y2);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use test::Bencher;
+use crypto_mac::Mac;
+use super::Polyval;
+
+#[bench]
+pub fn polyval_10(bh: & mut Bencher) {
+    let mut mac = [0u8; 16];
+    let key     = [0u8; 16];
+    let bytes   = [1u8; 10];
+    bh.iter( || {
+        let mut polyval = Polyval::new(&key);
+        polyval.input(&bytes);
+        polyval.raw_result(&mut mac);
+    });
+    bh.bytes = bytes.len() as u64;
+}
+
+#[bench]
+pub fn polyval_1k(bh: & mut Bencher) {
+    let mut mac = [0u8; 16];
+    let key     = [0u8; 16];
+    let bytes   = [1u8; 1024];
+    bh.iter( || {
+        let mut polyval = Polyval::new(&key);
+        polyval.input(&bytes);
+        polyval.raw_result(&mut mac);
+    });
+    bh.bytes = bytes.len() as u64;
+}
+
+#[bench]
+pub fn polyval_64k(bh: & mut Bencher) {
+    let mut mac = [0u8; 16];
+    let key     = [0u8; 16];
+    let bytes   = [1u8; 65536];
+    bh.iter( || {
+        let mut polyval = Polyval::new(&key);
+        polyval.input(&bytes);
+        polyval.raw_result(&mut mac);
+    });
+    bh.bytes = bytes.len() as u64;
+}
@@ -0,0 +1,142 @@
+//! This is an implementation of POLYVAL, the universal hash used by
+//! AES-GCM-SIV [1].
+//!
+//! POLYVAL works in the same field as GHASH, but with the little-endian
+//! convention and a different reduction. As shown in [1] appendix A it can
+//! be expressed with GHASH's arithmetic:
+//!
+//! POLYVAL(H, X_1, ..., X_n) = ByteReverse(GHASH(mulX_GHASH(ByteReverse(H)),
+//!                             ByteReverse(X_1), ..., ByteReverse(X_n)))
+//!
+//! so the constant time `Gf128Key` multiplication from the `ghash` crate is
+//! reused, including its PCLMULQDQ backend.
+//!
+//! [1] - "AES-GCM-SIV: Nonce Misuse-Resistant Authenticated Encryption",
+//!       RFC 8452 <https://tools.ietf.org/html/rfc8452>
+
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate crypto_bytes;
+extern crate crypto_mac;
+extern crate ghash;
+
+use crypto_bytes::copy_memory;
+use crypto_mac::{Mac, MacResult128};
+use ghash::{Gf128, Gf128Key};
+
+fn from_bytes_rev(bytes: &[u8]) -> Gf128 {
+    let mut rev = [0u8; 16];
+    for (r, b) in rev.iter_mut().zip(bytes.iter().rev()) {
+        *r = *b;
+    }
+    Gf128::from_bytes(&rev)
+}
+
+/// A structure representing the state of a POLYVAL computation
+#[derive(Copy)]
+pub struct Polyval {
+    key: Gf128Key,
+    state: Gf128,
+    rest: [u8; 16],
+    rest_len: usize,
+}
+
+impl Clone for Polyval { fn clone(&self) -> Polyval { *self } }
+
+impl Polyval {
+    /// Creates a new POLYVAL state, with `h` as the key
+    pub fn new(h: &[u8]) -> Polyval {
+        assert!(h.len() == 16);
+        let h = from_bytes_rev(h).times_x_reduce();
+        Polyval {
+            key: Gf128Key::new(h),
+            state: Gf128::zero(),
+            rest: [0u8; 16],
+            rest_len: 0,
+        }
+    }
+
+    /// Processes the buffered partial block, if any, padded with zeros
+    fn flush(&mut self) {
+        if self.rest_len != 0 {
+            for b in self.rest[self.rest_len..].iter_mut() {
+                *b = 0;
+            }
+            let x = from_bytes_rev(&self.rest);
+            self.key.add_and_mul(&mut self.state, x);
+            self.rest_len = 0;
+        }
+    }
+
+    /// Feeds `data` and pads it with zeros up to a multiple of 16 bytes,
+    /// as done for the AAD and the plaintext in AES-GCM-SIV
+    pub fn input_padded(&mut self, data: &[u8]) {
+        self.input(data);
+        self.flush();
+    }
+}
+
+impl Mac<MacResult128> for Polyval {
+    fn input(&mut self, mut data: &[u8]) {
+        if self.rest_len != 0 {
+            let n = 16 - self.rest_len;
+            if data.len() < n {
+                copy_memory(data, &mut self.rest[self.rest_len..]);
+                self.rest_len += data.len();
+                return;
+            }
+            copy_memory(&data[..n], &mut self.rest[self.rest_len..]);
+            self.rest_len = 16;
+            self.flush();
+            data = &data[n..];
+        }
+
+        // Byte reverse up to four blocks at a time, so that the key can
+        // aggregate their reduction
+        let full = data.len() - data.len() % 16;
+        let mut buf = [0u8; 64];
+        for chunk in data[..full].chunks(64) {
+            for (b, block) in buf.chunks_mut(16).zip(chunk.chunks(16)) {
+                for (b, x) in b.iter_mut().zip(block.iter().rev()) {
+                    *b = *x;
+                }
+            }
+            self.key.update(&mut self.state, &buf[..chunk.len()]);
+        }
+
+        let rest = &data[full..];
+        copy_memory(rest, &mut self.rest);
+        self.rest_len = rest.len();
+    }
+
+    fn reset(&mut self) {
+        self.state = Gf128::zero();
+        self.rest_len = 0;
+    }
+
+    fn result(&mut self) -> MacResult128 {
+        let mut mac = [0u8; 16];
+        self.raw_result(&mut mac[..]);
+        MacResult128::new(mac)
+    }
+
+    /// Writes the hash of the data fed so far, zero padding a trailing
+    /// partial block
+    fn raw_result(&mut self, output: &mut [u8]) {
+        assert!(output.len() >= 16);
+        self.flush();
+        let bytes = self.state.to_bytes();
+        for (o, b) in output.iter_mut().zip(bytes.iter().rev()) {
+            *o = *b;
+        }
+    }
+
+    fn output_bytes(&self) -> usize { 16 }
+}
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod bench;
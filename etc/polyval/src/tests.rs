@@ -0,0 +1,65 @@
+use crypto_mac::Mac;
+use super::Polyval;
+
+struct Test {
+    pub name: &'static str,
+    pub h: &'static [u8],
+    pub input: &'static [u8],
+    pub out: &'static [u8],
+}
+
+macro_rules! get_tests {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            Test{
+                name: $name,
+                h: include_bytes!(concat!("data/", $name, ".h.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                out: include_bytes!(concat!("data/", $name, ".out.bin")),
+            },
+        )*]
+    };
+}
+
+// The first test case is from RFC 8452, appendix A. The others were
+// computed with a direct implementation of the POLYVAL field arithmetic,
+// inputs which are not a multiple of 16 bytes are zero padded.
+const TESTS: [Test; 5] = get_tests!("1", "2", "3", "4", "5");
+
+#[test]
+fn polyval() {
+    for t in TESTS.iter() {
+        let mut polyval = Polyval::new(t.h);
+        polyval.input(t.input);
+        let mut out = [0u8; 16];
+        polyval.raw_result(&mut out);
+        assert_eq!(&out[..], t.out);
+    }
+}
+
+#[test]
+fn polyval_split() {
+    for t in TESTS.iter() {
+        for i in 0..t.input.len() {
+            let (d1, d2) = t.input.split_at(i);
+            let mut polyval = Polyval::new(t.h);
+            polyval.input(d1);
+            polyval.input(d2);
+            let mut out = [0u8; 16];
+            polyval.raw_result(&mut out);
+            assert_eq!(&out[..], t.out);
+        }
+    }
+}
+
+#[test]
+fn polyval_input_padded() {
+    let t = &TESTS[3];
+    let (d1, d2) = t.input.split_at(48);
+    let mut polyval = Polyval::new(t.h);
+    polyval.input_padded(d1);
+    polyval.input_padded(d2);
+    let mut out = [0u8; 16];
+    polyval.raw_result(&mut out);
+    assert_eq!(&out[..], t.out);
+}
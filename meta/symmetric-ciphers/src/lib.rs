@@ -1,10 +1,22 @@
 #![no_std]
+pub extern crate aegis;
+pub extern crate aes;
+pub extern crate aes_gcm;
+pub extern crate aes_gcm_siv;
+pub extern crate aes_siv;
+pub extern crate ascon;
 pub extern crate blowfish;
+pub extern crate ccm;
 pub extern crate chacha20;
 pub extern crate chacha20poly1305;
+pub extern crate deoxys;
+pub extern crate eax;
 pub extern crate hc128;
+pub extern crate ocb3;
 pub extern crate rc4;
 pub extern crate salsa20;
+pub extern crate secretbox;
+pub extern crate secretstream;
 pub extern crate sosemanuk;
 
 pub extern crate crypto_symmetric;
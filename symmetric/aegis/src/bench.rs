@@ -0,0 +1,42 @@
+use test::Bencher;
+use crypto_aead::{Aead, NewAead};
+use super::{Aegis128L, Aegis256};
+
+#[bench]
+pub fn aegis128l_10(bh: & mut Bencher) {
+    let input = [1u8; 10];
+    let aad = [3u8; 10];
+    let c: Aegis128L = Aegis128L::new(&[0; 16]).unwrap();
+    bh.iter( || {
+        let mut output = [0u8; 10];
+        let mut tag = [0u8; 16];
+        c.encrypt(&[0; 16], &aad, &input, &mut output, &mut tag).unwrap();
+    });
+    bh.bytes = 10u64;
+}
+
+#[bench]
+pub fn aegis128l_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let aad = [3u8; 1024];
+    let c: Aegis128L = Aegis128L::new(&[0; 16]).unwrap();
+    bh.iter( || {
+        let mut output = [0u8; 1024];
+        let mut tag = [0u8; 16];
+        c.encrypt(&[0; 16], &aad, &input, &mut output, &mut tag).unwrap();
+    });
+    bh.bytes = 1024u64;
+}
+
+#[bench]
+pub fn aegis256_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let aad = [3u8; 1024];
+    let c: Aegis256 = Aegis256::new(&[0; 32]).unwrap();
+    bh.iter( || {
+        let mut output = [0u8; 1024];
+        let mut tag = [0u8; 16];
+        c.encrypt(&[0; 32], &aad, &input, &mut output, &mut tag).unwrap();
+    });
+    bh.bytes = 1024u64;
+}
@@ -0,0 +1,310 @@
+//! This is an implementation of the AEGIS-128L and AEGIS-256 authenticated
+//! ciphers, as specified in draft-irtf-cfrg-aegis-aead [1].
+//!
+//! AEGIS keeps a large state of 128-bit blocks, eight for AEGIS-128L and
+//! six for AEGIS-256. Every update applies one AES round to each block,
+//! keyed by its neighbour, and injects message blocks, while the keystream
+//! is a nonlinear combination of the state blocks. Since the rounds on the
+//! different blocks are independent, they map well to the pipelined
+//! `aesenc` instruction: `aes::aes_round` uses AES-NI when the processor
+//! supports it, and the bitsliced AES core otherwise.
+//!
+//! AEGIS-128L takes a 128-bit key and nonce and processes 32 bytes per
+//! update, AEGIS-256 a 256-bit key and nonce and 16 bytes per update. Both
+//! produce 128 or 256-bit tags, selected with the `TagSize` parameter. Nonces
+//! can be chosen at random but must never be reused with the same key.
+//!
+//! [1] - "The AEGIS Family of Authenticated Encryption Algorithms"
+//!       <https://datatracker.ietf.org/doc/draft-irtf-cfrg-aegis-aead/>
+
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate generic_array;
+extern crate aes;
+extern crate crypto_aead;
+extern crate crypto_bytes;
+extern crate crypto_ops;
+
+use core::marker::PhantomData;
+use generic_array::{GenericArray, ArrayLength};
+use generic_array::typenum::{U16, U32};
+use aes::aes_round;
+use crypto_aead::{Aead, NewAead, AeadError, Tag};
+use crypto_bytes::write_u64_le;
+use crypto_ops::fixed_time_eq;
+
+/// Tag sizes allowed by AEGIS, 16 or 32 bytes
+pub trait AegisTagSize: ArrayLength<u8> {}
+
+impl AegisTagSize for U16 {}
+impl AegisTagSize for U32 {}
+
+const C0: [u8; 16] = [0x00, 0x01, 0x01, 0x02, 0x03, 0x05, 0x08, 0x0d,
+                      0x15, 0x22, 0x37, 0x59, 0x90, 0xe9, 0x79, 0x62];
+const C1: [u8; 16] = [0xdb, 0x3d, 0x18, 0x55, 0x6d, 0xc2, 0x2f, 0xf1,
+                      0x20, 0x11, 0x31, 0x42, 0x73, 0xb5, 0x28, 0xdd];
+
+fn xor(a: &mut [u8], b: &[u8]) {
+    for (a, b) in a.iter_mut().zip(b.iter()) {
+        *a ^= *b;
+    }
+}
+
+/// The state of either variant, `blocks` 16 byte blocks stored one after
+/// the other
+#[derive(Clone, Copy)]
+struct State {
+    s: [u8; 128],
+    blocks: usize,
+}
+
+impl State {
+    /// Number of message bytes absorbed per update
+    fn rate(&self) -> usize {
+        if self.blocks == 8 { 32 } else { 16 }
+    }
+
+    fn block(&self, i: usize) -> &[u8] {
+        &self.s[16 * i..16 * i + 16]
+    }
+
+    /// `S'_i = AESRound(S_{i-1}, S_i)` for all blocks, then the message is
+    /// XORed into block 0 and, for AEGIS-128L, block 4
+    fn update(&mut self, msg: &[u8]) {
+        let len = 16 * self.blocks;
+        let mut next = [0u8; 128];
+        next[..16].copy_from_slice(&self.s[len - 16..len]);
+        next[16..len].copy_from_slice(&self.s[..len - 16]);
+        aes_round(&mut next[..len], &self.s[..len]);
+        xor(&mut next[..16], &msg[..16]);
+        if self.blocks == 8 {
+            xor(&mut next[64..80], &msg[16..]);
+        }
+        self.s = next;
+    }
+
+    /// Writes the keystream for the next `rate` bytes into `z`
+    fn keystream(&self, z: &mut [u8]) {
+        let s = |i| self.block(i);
+        let (z0, z1) = z.split_at_mut(16);
+        if self.blocks == 8 {
+            for i in 0..16 {
+                z0[i] = s(6)[i] ^ s(1)[i] ^ (s(2)[i] & s(3)[i]);
+                z1[i] = s(2)[i] ^ s(5)[i] ^ (s(6)[i] & s(7)[i]);
+            }
+        } else {
+            for (i, z) in z0.iter_mut().enumerate() {
+                *z = s(1)[i] ^ s(4)[i] ^ s(5)[i] ^ (s(2)[i] & s(3)[i]);
+            }
+        }
+    }
+
+    fn absorb(&mut self, aad: &[u8]) {
+        let rate = self.rate();
+        for chunk in aad.chunks(rate) {
+            let mut block = [0u8; 32];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.update(&block[..rate]);
+        }
+    }
+
+    fn encrypt(&mut self, buf: &mut [u8]) {
+        let rate = self.rate();
+        for chunk in buf.chunks_mut(rate) {
+            let mut block = [0u8; 32];
+            let mut z = [0u8; 32];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.keystream(&mut z[..rate]);
+            self.update(&block[..rate]);
+            xor(chunk, &z);
+        }
+    }
+
+    fn decrypt(&mut self, buf: &mut [u8]) {
+        let rate = self.rate();
+        for chunk in buf.chunks_mut(rate) {
+            // The plaintext of a partial block is zero padded before being
+            // absorbed
+            let mut block = [0u8; 32];
+            self.keystream(&mut block[..rate]);
+            xor(chunk, &block);
+            for b in block.iter_mut() {
+                *b = 0;
+            }
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.update(&block[..rate]);
+        }
+    }
+
+    /// Absorbs the lengths in bits and writes a 16 or 32-byte tag
+    fn finalize(mut self, aad_len: usize, msg_len: usize, tag: &mut [u8]) {
+        let mut t = [0u8; 32];
+        write_u64_le(&mut t[..8], 8 * aad_len as u64);
+        write_u64_le(&mut t[8..16], 8 * msg_len as u64);
+        xor(&mut t[..16], self.block(if self.blocks == 8 { 2 } else { 3 }));
+        let (lo, hi) = t.split_at_mut(16);
+        hi.copy_from_slice(lo);
+        for _ in 0..7 {
+            self.update(&t);
+        }
+
+        for b in tag.iter_mut() {
+            *b = 0;
+        }
+        if tag.len() == 16 {
+            // AEGIS-128L leaves the last block out
+            for i in 0..core::cmp::min(self.blocks, 7) {
+                xor(tag, self.block(i));
+            }
+        } else {
+            let half = self.blocks / 2;
+            for i in 0..half {
+                xor(&mut tag[..16], self.block(i));
+                xor(&mut tag[16..], self.block(half + i));
+            }
+        }
+    }
+}
+
+/// AEGIS-128L, with 128-bit keys and nonces and `TagSize` byte tags
+#[derive(Clone, Copy)]
+pub struct Aegis128L<TagSize = U16> {
+    key: [u8; 16],
+    tag_size: PhantomData<TagSize>,
+}
+
+/// AEGIS-256, with 256-bit keys and nonces and `TagSize` byte tags
+#[derive(Clone, Copy)]
+pub struct Aegis256<TagSize = U16> {
+    key: [u8; 32],
+    tag_size: PhantomData<TagSize>,
+}
+
+impl<TagSize: AegisTagSize> Aegis128L<TagSize> {
+    fn init(&self, nonce: &[u8]) -> Result<State, AeadError> {
+        if nonce.len() != 16 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+        let mut state = State { s: [0u8; 128], blocks: 8 };
+        for (i, block) in state.s.chunks_mut(16).enumerate() {
+            match i {
+                0 | 4 => {
+                    block.copy_from_slice(&self.key);
+                    xor(block, nonce);
+                }
+                1 | 3 => block.copy_from_slice(&C1),
+                2 => block.copy_from_slice(&C0),
+                5 | 7 => {
+                    block.copy_from_slice(&self.key);
+                    xor(block, &C0);
+                }
+                _ => {
+                    block.copy_from_slice(&self.key);
+                    xor(block, &C1);
+                }
+            }
+        }
+        let mut msg = [0u8; 32];
+        msg[..16].copy_from_slice(nonce);
+        msg[16..].copy_from_slice(&self.key);
+        for _ in 0..10 {
+            state.update(&msg);
+        }
+        Ok(state)
+    }
+}
+
+impl<TagSize: AegisTagSize> Aegis256<TagSize> {
+    fn init(&self, nonce: &[u8]) -> Result<State, AeadError> {
+        if nonce.len() != 32 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+        let (k0, k1) = self.key.split_at(16);
+        let (n0, n1) = nonce.split_at(16);
+        let mut kn0 = [0u8; 16];
+        let mut kn1 = [0u8; 16];
+        kn0.copy_from_slice(k0);
+        xor(&mut kn0, n0);
+        kn1.copy_from_slice(k1);
+        xor(&mut kn1, n1);
+
+        let mut state = State { s: [0u8; 128], blocks: 6 };
+        state.s[..16].copy_from_slice(&kn0);
+        state.s[16..32].copy_from_slice(&kn1);
+        state.s[32..48].copy_from_slice(&C1);
+        state.s[48..64].copy_from_slice(&C0);
+        state.s[64..80].copy_from_slice(k0);
+        xor(&mut state.s[64..80], &C0);
+        state.s[80..96].copy_from_slice(k1);
+        xor(&mut state.s[80..96], &C1);
+        for _ in 0..4 {
+            state.update(k0);
+            state.update(k1);
+            state.update(&kn0);
+            state.update(&kn1);
+        }
+        Ok(state)
+    }
+}
+
+macro_rules! impl_aead {
+    ($name:ident, $key_size:expr) => {
+        impl<TagSize: AegisTagSize> NewAead for $name<TagSize> {
+            fn new(key: &[u8]) -> Result<$name<TagSize>, AeadError> {
+                if key.len() != $key_size {
+                    return Err(AeadError::InvalidKeyLength);
+                }
+                let mut k = [0u8; $key_size];
+                k.copy_from_slice(key);
+                Ok($name { key: k, tag_size: PhantomData })
+            }
+        }
+
+        impl<TagSize: AegisTagSize> Aead for $name<TagSize> {
+            type TagSize = TagSize;
+
+            fn encrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                         buf: &mut [u8])
+                                         -> Result<Tag<TagSize>, AeadError> {
+                let mut state = self.init(nonce)?;
+                state.absorb(aad);
+                state.encrypt(buf);
+                let mut tag = GenericArray::new();
+                state.finalize(aad.len(), buf.len(), &mut tag);
+                Ok(tag)
+            }
+
+            fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                         buf: &mut [u8], tag: &[u8])
+                                         -> Result<(), AeadError> {
+                if tag.len() != TagSize::to_usize() {
+                    return Err(AeadError::InvalidLength);
+                }
+                let mut state = self.init(nonce)?;
+                state.absorb(aad);
+                state.decrypt(buf);
+                let mut calc_tag = [0u8; 32];
+                state.finalize(aad.len(), buf.len(),
+                               &mut calc_tag[..tag.len()]);
+                if fixed_time_eq(&calc_tag[..tag.len()], tag) {
+                    Ok(())
+                } else {
+                    for b in buf.iter_mut() {
+                        *b = 0;
+                    }
+                    Err(AeadError::AuthenticationFailed)
+                }
+            }
+        }
+    }
+}
+
+impl_aead!(Aegis128L, 16);
+impl_aead!(Aegis256, 32);
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod bench;
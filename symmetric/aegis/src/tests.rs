@@ -0,0 +1,137 @@
+use generic_array::typenum::{U16, U32};
+use crypto_aead::{Aead, NewAead, AeadError};
+use super::{Aegis128L, Aegis256};
+
+struct TestVector {
+    pub name: &'static str,
+    pub key: &'static [u8],
+    pub nonce: &'static [u8],
+    pub input: &'static [u8],
+    pub output: &'static [u8],
+    pub aad: &'static [u8],
+    pub tag128: &'static [u8],
+    pub tag256: &'static [u8],
+}
+
+macro_rules! get_vectors {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            TestVector {
+                name: $name,
+                key: include_bytes!(concat!("data/", $name, ".key.bin")),
+                nonce: include_bytes!(concat!("data/", $name, ".nonce.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                output: include_bytes!(concat!("data/", $name, ".output.bin")),
+                aad: include_bytes!(concat!("data/", $name, ".aad.bin")),
+                tag128: include_bytes!(concat!("data/", $name, ".tag128.bin")),
+                tag256: include_bytes!(concat!("data/", $name, ".tag256.bin")),
+            },
+        )*]
+    };
+}
+
+macro_rules! check_vector {
+    ($aegis:ty, $tv:expr, $tag:expr) => {{
+        let tv = $tv;
+        let msg_len = tv.input.len();
+        let c = <$aegis>::new(tv.key).unwrap();
+        let mut buf = [0u8; MAX_MSG_LEN];
+        buf[..msg_len].copy_from_slice(tv.input);
+        let tag = c.encrypt_in_place_detached(tv.nonce, tv.aad,
+                                              &mut buf[..msg_len]).unwrap();
+        assert_eq!(&buf[..msg_len], tv.output);
+        assert_eq!(&tag[..], $tag);
+
+        assert_eq!(c.decrypt_in_place_detached(tv.nonce, tv.aad,
+                                               &mut buf[..msg_len], $tag),
+                   Ok(()));
+        assert_eq!(&buf[..msg_len], tv.input);
+
+        let mut bad_tag = [0u8; 32];
+        bad_tag[..$tag.len()].copy_from_slice($tag);
+        bad_tag[1] ^= 0x10;
+        buf[..msg_len].copy_from_slice(tv.output);
+        assert_eq!(c.decrypt_in_place_detached(tv.nonce, tv.aad,
+                                               &mut buf[..msg_len],
+                                               &bad_tag[..$tag.len()]),
+                   Err(AeadError::AuthenticationFailed));
+        assert!(buf[..msg_len].iter().all(|&b| b == 0));
+    }}
+}
+
+const MAX_MSG_LEN: usize = 1000;
+
+// Based on the test vectors of draft-irtf-cfrg-aegis-aead, appendix A. The
+// last vector of each variant was generated with a reference implementation
+// and spans many updates.
+const AEGIS128L_VECTORS: [TestVector; 6] = get_vectors!("128l_1", "128l_2",
+    "128l_3", "128l_4", "128l_5", "128l_gen");
+const AEGIS256_VECTORS: [TestVector; 6] = get_vectors!("256_1", "256_2",
+    "256_3", "256_4", "256_5", "256_gen");
+
+#[test]
+fn aegis128l() {
+    for tv in AEGIS128L_VECTORS.iter() {
+        check_vector!(Aegis128L<U16>, tv, tv.tag128);
+        check_vector!(Aegis128L<U32>, tv, tv.tag256);
+    }
+}
+
+#[test]
+fn aegis256() {
+    for tv in AEGIS256_VECTORS.iter() {
+        check_vector!(Aegis256<U16>, tv, tv.tag128);
+        check_vector!(Aegis256<U32>, tv, tv.tag256);
+    }
+}
+
+#[test]
+fn aegis_modified_inputs() {
+    // Changing the nonce, the AAD or the ciphertext fails authentication
+    let tv = &AEGIS128L_VECTORS[2];
+    let c = Aegis128L::<U16>::new(tv.key).unwrap();
+    let mut buf = [0u8; 32];
+    let mut nonce = [0u8; 16];
+    nonce.copy_from_slice(tv.nonce);
+    nonce[15] ^= 1;
+    buf.copy_from_slice(tv.output);
+    assert_eq!(c.decrypt_in_place_detached(&nonce, tv.aad, &mut buf,
+                                           tv.tag128),
+               Err(AeadError::AuthenticationFailed));
+    buf.copy_from_slice(tv.output);
+    assert_eq!(c.decrypt_in_place_detached(tv.nonce, &tv.aad[1..], &mut buf,
+                                           tv.tag128),
+               Err(AeadError::AuthenticationFailed));
+    buf.copy_from_slice(tv.output);
+    buf[31] ^= 1;
+    assert_eq!(c.decrypt_in_place_detached(tv.nonce, tv.aad, &mut buf,
+                                           tv.tag128),
+               Err(AeadError::AuthenticationFailed));
+}
+
+#[test]
+fn aegis_invalid_lengths() {
+    let mut buf = [0u8; 4];
+    assert_eq!(Aegis128L::<U16>::new(&[0; 32]).err(),
+               Some(AeadError::InvalidKeyLength));
+    assert_eq!(Aegis256::<U16>::new(&[0; 16]).err(),
+               Some(AeadError::InvalidKeyLength));
+
+    let c = Aegis128L::<U16>::new(&[0; 16]).unwrap();
+    assert_eq!(c.encrypt_in_place_detached(&[0; 32], &[], &mut buf).err(),
+               Some(AeadError::InvalidNonceLength));
+    assert_eq!(c.decrypt_in_place_detached(&[0; 16], &[], &mut buf, &[0; 32]),
+               Err(AeadError::InvalidLength));
+    let c = Aegis256::<U32>::new(&[0; 32]).unwrap();
+    assert_eq!(c.encrypt_in_place_detached(&[0; 16], &[], &mut buf).err(),
+               Some(AeadError::InvalidNonceLength));
+    assert_eq!(c.decrypt_in_place_detached(&[0; 32], &[], &mut buf, &[0; 16]),
+               Err(AeadError::InvalidLength));
+}
+
+#[test]
+fn aegis_sync() {
+    fn assert_sync<T: Sync>() {}
+    assert_sync::<Aegis128L>();
+    assert_sync::<Aegis256>();
+}
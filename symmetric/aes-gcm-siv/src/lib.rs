@@ -0,0 +1,226 @@
+//! This is an implementation of AES-GCM-SIV, the nonce misuse-resistant
+//! AEAD specified in RFC 8452 [1].
+//!
+//! A message authentication key and a message encryption key are derived
+//! from the key-generating key and the nonce for every message. The tag is
+//! computed with POLYVAL over the AAD and the plaintext, and then used as
+//! the initial counter block for encrypting the plaintext in CTR mode (with
+//! a 32-bit little-endian counter).
+//!
+//! Because the tag depends on the whole plaintext, reusing a nonce only
+//! reveals whether the same message was encrypted twice under that nonce.
+//! Unique nonces should still be used whenever possible.
+//!
+//! [1] - "AES-GCM-SIV: Nonce Misuse-Resistant Authenticated Encryption"
+//!       <https://tools.ietf.org/html/rfc8452>
+
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate generic_array;
+extern crate aes;
+extern crate polyval;
+extern crate crypto_aead;
+extern crate crypto_bytes;
+extern crate crypto_mac;
+extern crate crypto_ops;
+extern crate crypto_symmetric;
+
+use generic_array::GenericArray;
+use generic_array::typenum::U16;
+use aes::{Aes128, Aes256};
+use polyval::Polyval;
+use crypto_aead::{Aead, NewAead, AeadError, Tag};
+use crypto_bytes::{read_u32_le, write_u32_le, write_u64_le};
+use crypto_mac::Mac;
+use crypto_ops::fixed_time_eq;
+use crypto_symmetric::{BlockCipher, BlockEncryptorX8};
+
+/// AES-GCM-SIV with either a 128 or a 256-bit key, use the `Aes128GcmSiv`
+/// and `Aes256GcmSiv` aliases
+#[derive(Clone, Copy)]
+pub struct AesGcmSiv<C> {
+    /// Key-generating key
+    kgk: C,
+    /// Length of the message encryption keys
+    key_len: usize,
+    new_cipher: fn(&[u8]) -> C,
+}
+
+pub type Aes128GcmSiv = AesGcmSiv<Aes128>;
+pub type Aes256GcmSiv = AesGcmSiv<Aes256>;
+
+/// The keys derived for a single nonce, with the AAD already hashed
+struct Message<C> {
+    cipher: C,
+    polyval: Polyval,
+    nonce: [u8; 12],
+    aad_len: u64,
+}
+
+/// Plaintexts and AAD are limited to 2^36 bytes
+fn check_length(buf: &[u8]) -> Result<(), AeadError> {
+    if (buf.len() as u64) > 1 << 36 {
+        Err(AeadError::InvalidLength)
+    } else {
+        Ok(())
+    }
+}
+
+impl<C> AesGcmSiv<C> where C: BlockCipher<BlockSize = U16> + BlockEncryptorX8 {
+    /// Derives the per-nonce keys (RFC 8452, section 4) from the
+    /// key-generating key
+    fn derive(&self, nonce: &[u8], aad: &[u8])
+              -> Result<Message<C>, AeadError> {
+        if nonce.len() != 12 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+        check_length(aad)?;
+
+        // The first 8 bytes of E(kgk, le32(i) || nonce) for i = 0, 1 form
+        // the authentication key, the following ones the encryption key
+        let mut keys = [0u8; 48];
+        let mut input = GenericArray::new();
+        let mut output = GenericArray::new();
+        input[4..].copy_from_slice(nonce);
+        for (i, chunk) in keys[..16 + self.key_len].chunks_mut(8).enumerate() {
+            write_u32_le(&mut input[..4], i as u32);
+            self.kgk.encrypt_block(&input, &mut output);
+            chunk.copy_from_slice(&output[..8]);
+        }
+
+        let mut polyval = Polyval::new(&keys[..16]);
+        polyval.input_padded(aad);
+
+        let mut n = [0u8; 12];
+        n.copy_from_slice(nonce);
+        Ok(Message {
+            cipher: (self.new_cipher)(&keys[16..16 + self.key_len]),
+            polyval: polyval,
+            nonce: n,
+            aad_len: aad.len() as u64,
+        })
+    }
+}
+
+impl<C> Message<C> where C: BlockCipher<BlockSize = U16> + BlockEncryptorX8 {
+    fn compute_tag(&self, plaintext: &[u8]) -> [u8; 16] {
+        let mut polyval = self.polyval;
+        polyval.input_padded(plaintext);
+        let mut len_block = [0u8; 16];
+        write_u64_le(&mut len_block[..8], self.aad_len * 8);
+        write_u64_le(&mut len_block[8..], plaintext.len() as u64 * 8);
+        polyval.input(&len_block);
+
+        let mut s = GenericArray::new();
+        polyval.raw_result(&mut s[..]);
+        for (s, n) in s.iter_mut().zip(self.nonce.iter()) {
+            *s ^= *n;
+        }
+        s[15] &= 0x7f;
+
+        let mut tag = GenericArray::new();
+        self.cipher.encrypt_block(&s, &mut tag);
+        let mut result = [0u8; 16];
+        result.copy_from_slice(&tag);
+        result
+    }
+
+    /// XORs `buf` with the keystream for the initial counter block derived
+    /// from `tag`, the first 32 bits are incremented as a little-endian
+    /// integer modulo 2^32
+    fn ctr(&self, tag: &[u8], buf: &mut [u8]) {
+        let mut counter = [0u8; 16];
+        counter.copy_from_slice(tag);
+        counter[15] |= 0x80;
+        let mut ctr = read_u32_le(&counter[..4]);
+
+        let mut counters = [0u8; 128];
+        let mut keystream = [0u8; 128];
+        for block in counters.chunks_mut(16) {
+            block.copy_from_slice(&counter);
+        }
+        for chunk in buf.chunks_mut(128) {
+            for block in counters.chunks_mut(16) {
+                write_u32_le(&mut block[..4], ctr);
+                ctr = ctr.wrapping_add(1);
+            }
+            self.cipher.encrypt_block_x8(&counters, &mut keystream);
+            for (b, k) in chunk.iter_mut().zip(keystream.iter()) {
+                *b ^= *k;
+            }
+        }
+    }
+}
+
+impl NewAead for AesGcmSiv<Aes128> {
+    fn new(key: &[u8]) -> Result<Aes128GcmSiv, AeadError> {
+        if key.len() != 16 {
+            return Err(AeadError::InvalidKeyLength);
+        }
+        Ok(AesGcmSiv {
+            kgk: Aes128::new(key),
+            key_len: 16,
+            new_cipher: Aes128::new,
+        })
+    }
+}
+
+impl NewAead for AesGcmSiv<Aes256> {
+    fn new(key: &[u8]) -> Result<Aes256GcmSiv, AeadError> {
+        if key.len() != 32 {
+            return Err(AeadError::InvalidKeyLength);
+        }
+        Ok(AesGcmSiv {
+            kgk: Aes256::new(key),
+            key_len: 32,
+            new_cipher: Aes256::new,
+        })
+    }
+}
+
+impl<C> Aead for AesGcmSiv<C>
+    where C: BlockCipher<BlockSize = U16> + BlockEncryptorX8
+{
+    type TagSize = U16;
+
+    fn encrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8])
+                                 -> Result<Tag<U16>, AeadError> {
+        check_length(buf)?;
+        let msg = self.derive(nonce, aad)?;
+
+        let mut tag = GenericArray::new();
+        tag.copy_from_slice(&msg.compute_tag(buf));
+        msg.ctr(&tag, buf);
+        Ok(tag)
+    }
+
+    /// The plaintext has to be recovered before the tag can be checked, it
+    /// is wiped from `buf` if authentication fails
+    fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8], tag: &[u8])
+                                 -> Result<(), AeadError> {
+        check_length(buf)?;
+        if tag.len() != 16 {
+            return Err(AeadError::InvalidLength);
+        }
+        let msg = self.derive(nonce, aad)?;
+
+        msg.ctr(tag, buf);
+        if fixed_time_eq(&msg.compute_tag(buf), tag) {
+            Ok(())
+        } else {
+            for b in buf.iter_mut() {
+                *b = 0;
+            }
+            Err(AeadError::AuthenticationFailed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod bench;
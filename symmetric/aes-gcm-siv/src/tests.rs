@@ -0,0 +1,154 @@
+use super::{Aes128GcmSiv, Aes256GcmSiv};
+use crypto_aead::{Aead, NewAead, AeadError};
+
+struct TestVector {
+    pub name: &'static str,
+    pub key: &'static [u8],
+    pub nonce: &'static [u8],
+    pub input: &'static [u8],
+    pub output: &'static [u8],
+    pub aad: &'static [u8],
+    pub tag: &'static [u8],
+}
+
+macro_rules! get_vectors {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            TestVector {
+                name: $name,
+                key: include_bytes!(concat!("data/", $name, ".key.bin")),
+                nonce: include_bytes!(concat!("data/", $name, ".nonce.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                output: include_bytes!(concat!("data/", $name, ".output.bin")),
+                aad: include_bytes!(concat!("data/", $name, ".aad.bin")),
+                tag: include_bytes!(concat!("data/", $name, ".tag.bin")),
+            },
+        )*]
+    };
+}
+
+macro_rules! check_vectors {
+    ($siv:ident, $vectors:expr) => {
+        for tv in $vectors.iter() {
+            let msg_len = tv.input.len();
+            let mut output = [0u8; MAX_MSG_LEN];
+            let mut tag = [0u8; 16];
+            let c = $siv::new(tv.key).unwrap();
+            c.encrypt(tv.nonce, tv.aad, tv.input, &mut output[..msg_len],
+                      &mut tag).unwrap();
+            assert_eq!(&output[..msg_len], tv.output);
+            assert_eq!(&tag[..], tv.tag);
+
+            assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output,
+                                 &mut output[..msg_len], tv.tag),
+                       Ok(()));
+            assert_eq!(&output[..msg_len], tv.input);
+
+            tag[0] ^= 1;
+            assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output,
+                                 &mut output[..msg_len], &tag),
+                       Err(AeadError::AuthenticationFailed));
+            assert!(output[..msg_len].iter().all(|&b| b == 0));
+        }
+    }
+}
+
+const MAX_MSG_LEN: usize = 64;
+
+// RFC 8452, appendix C.1
+const AES128_VECTORS: [TestVector; 16] = get_vectors!(
+    "c1_1", "c1_2", "c1_3", "c1_4", "c1_5", "c1_6", "c1_7", "c1_8", "c1_9",
+    "c1_10", "c1_11", "c1_12", "c1_13", "c1_14", "c1_15", "c1_16");
+
+// RFC 8452, appendix C.2
+const AES256_VECTORS: [TestVector; 16] = get_vectors!(
+    "c2_1", "c2_2", "c2_3", "c2_4", "c2_5", "c2_6", "c2_7", "c2_8", "c2_9",
+    "c2_10", "c2_11", "c2_12", "c2_13", "c2_14", "c2_15", "c2_16");
+
+// RFC 8452, appendix C.3: the 32-bit counter wraps around
+const WRAP_VECTORS: [TestVector; 2] = get_vectors!("c3_1", "c3_2");
+
+#[test]
+fn aes128_gcm_siv() {
+    check_vectors!(Aes128GcmSiv, AES128_VECTORS);
+}
+
+#[test]
+fn aes256_gcm_siv() {
+    check_vectors!(Aes256GcmSiv, AES256_VECTORS);
+}
+
+#[test]
+fn aes_gcm_siv_counter_wrap() {
+    check_vectors!(Aes256GcmSiv, WRAP_VECTORS);
+}
+
+#[test]
+fn aes_gcm_siv_in_place_detached() {
+    for tv in AES128_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let mut buf = [0u8; MAX_MSG_LEN];
+        buf[..msg_len].copy_from_slice(tv.input);
+        let c = Aes128GcmSiv::new(tv.key).unwrap();
+        let tag = c.encrypt_in_place_detached(tv.nonce, tv.aad,
+                                              &mut buf[..msg_len]).unwrap();
+        assert_eq!(&buf[..msg_len], tv.output);
+        assert_eq!(&tag[..], tv.tag);
+
+        let result = c.decrypt_in_place_detached(tv.nonce, tv.aad,
+                                                 &mut buf[..msg_len], tv.tag);
+        assert_eq!(result, Ok(()));
+        assert_eq!(&buf[..msg_len], tv.input);
+    }
+}
+
+fn assert_sync<T: Sync>() {}
+
+#[test]
+fn aes_gcm_siv_key_reuse() {
+    assert_sync::<Aes128GcmSiv>();
+    assert_sync::<Aes256GcmSiv>();
+
+    // All the vectors of appendix C.2 use the same key and nonce
+    let c = Aes256GcmSiv::new(AES256_VECTORS[0].key).unwrap();
+    let mut output = [0u8; MAX_MSG_LEN];
+    let mut tag = [0u8; 16];
+    for tv in AES256_VECTORS.iter().rev() {
+        let msg_len = tv.input.len();
+        c.encrypt(tv.nonce, tv.aad, tv.input, &mut output[..msg_len], &mut tag)
+            .unwrap();
+        assert_eq!(&output[..msg_len], tv.output);
+        assert_eq!(&tag[..], tv.tag);
+    }
+}
+
+#[test]
+fn aes_gcm_siv_modified_aad() {
+    let tv = &AES128_VECTORS[15];
+    let msg_len = tv.input.len();
+    let mut output = [0u8; MAX_MSG_LEN];
+    let c = Aes128GcmSiv::new(tv.key).unwrap();
+    assert_eq!(c.decrypt(tv.nonce, &tv.aad[1..], tv.output,
+                         &mut output[..msg_len], tv.tag),
+               Err(AeadError::AuthenticationFailed));
+}
+
+#[test]
+fn aes_gcm_siv_invalid_lengths() {
+    let tv = &AES128_VECTORS[15];
+    let msg_len = tv.input.len();
+    let mut output = [0u8; MAX_MSG_LEN];
+    assert_eq!(Aes256GcmSiv::new(tv.key).err(),
+               Some(AeadError::InvalidKeyLength));
+
+    let c = Aes128GcmSiv::new(tv.key).unwrap();
+    assert_eq!(c.encrypt(&tv.nonce[..8], tv.aad, tv.input,
+                         &mut output[..msg_len], &mut [0u8; 16]),
+               Err(AeadError::InvalidNonceLength));
+    assert_eq!(c.encrypt(tv.nonce, tv.aad, tv.input, &mut output[..msg_len],
+                         &mut [0u8; 12]),
+               Err(AeadError::InvalidLength));
+    assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output, &mut output[..msg_len],
+                         &tv.tag[..12]),
+               Err(AeadError::InvalidLength));
+}
@@ -0,0 +1,215 @@
+//! This is an implementation of the Galois/Counter Mode (GCM) of operation
+//! for AES, as specified in NIST SP 800-38D [1].
+//!
+//! The plaintext is encrypted in CTR mode with a 32-bit big-endian counter,
+//! starting from the block following the pre-counter block J0. The
+//! authentication tag is GHASH (see the `ghash` crate) of the additional
+//! data and the ciphertext, keyed with `H = E(K, 0^128)` and masked with
+//! `E(K, J0)`.
+//!
+//! 96-bit IVs are used directly as the first 12 bytes of J0, IVs of any
+//! other length are hashed into J0 with GHASH. 96-bit IVs should be
+//! preferred, in both cases an IV must never be reused with the same key.
+//!
+//! The tag size is a type parameter, `U16` by default. Tags truncated to 12
+//! to 15 bytes are available as e.g. `AesGcm<Aes128, U12>`, and only tags
+//! of exactly that size are accepted.
+//!
+//! [1] - "Recommendation for Block Cipher Modes of Operation:
+//!       Galois/Counter Mode (GCM) and GMAC"
+//!       <https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38d.pdf>
+
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate generic_array;
+extern crate aes;
+extern crate ghash;
+extern crate crypto_aead;
+extern crate crypto_bytes;
+extern crate crypto_ops;
+extern crate crypto_symmetric;
+
+use core::marker::PhantomData;
+use generic_array::{GenericArray, ArrayLength};
+use generic_array::typenum::{U12, U13, U14, U15, U16};
+use aes::{Aes128, Aes192, Aes256};
+use ghash::Ghash;
+use crypto_aead::{Aead, NewAead, AeadError, Tag};
+use crypto_bytes::{read_u32_be, write_u32_be, xor_keystream};
+use crypto_ops::fixed_time_eq;
+use crypto_symmetric::{BlockCipher, BlockEncryptorX8};
+
+/// Tag sizes allowed by GCM, truncated to no less than 96 bits
+pub trait GcmTagSize: ArrayLength<u8> {}
+
+impl GcmTagSize for U12 {}
+impl GcmTagSize for U13 {}
+impl GcmTagSize for U14 {}
+impl GcmTagSize for U15 {}
+impl GcmTagSize for U16 {}
+
+/// GCM over a block cipher with a 128-bit block size and `TagSize` byte
+/// tags. Use one of the `Aes128Gcm`, `Aes192Gcm` or `Aes256Gcm` aliases, or
+/// `from_cipher` to supply an already keyed cipher.
+#[derive(Clone, Copy)]
+pub struct AesGcm<C, TagSize = U16> {
+    cipher: C,
+    /// GHASH keyed with `H`, copied for every message
+    ghash: Ghash,
+    tag_size: PhantomData<TagSize>,
+}
+
+pub type Aes128Gcm = AesGcm<Aes128>;
+pub type Aes192Gcm = AesGcm<Aes192>;
+pub type Aes256Gcm = AesGcm<Aes256>;
+
+/// Longest plaintext, 2^32 - 2 blocks: beyond it the 32-bit counter would
+/// wrap back to J0, which masks the tag
+const MAX_PLAINTEXT_LEN: u64 = (1 << 36) - 32;
+/// Longest additional data, 2^64 - 1 bits
+const MAX_AAD_LEN: u64 = (1 << 61) - 1;
+
+/// Checks the lengths of the additional data and the message against the
+/// limits of SP 800-38D, section 5.2.1.1
+fn check_lengths(aad_len: u64, msg_len: u64) -> Result<(), AeadError> {
+    if aad_len > MAX_AAD_LEN || msg_len > MAX_PLAINTEXT_LEN {
+        Err(AeadError::InvalidLength)
+    } else {
+        Ok(())
+    }
+}
+
+/// Increments the rightmost 32 bits of `block` modulo 2^32
+fn inc32(block: &mut [u8; 16]) {
+    let ctr = read_u32_be(&block[12..]).wrapping_add(1);
+    write_u32_be(&mut block[12..], ctr);
+}
+
+impl<C, TagSize> AesGcm<C, TagSize>
+    where C: BlockCipher<BlockSize = U16> + BlockEncryptorX8,
+          TagSize: GcmTagSize
+{
+    /// Sets up GCM with an already keyed `cipher`
+    pub fn from_cipher(cipher: C) -> AesGcm<C, TagSize> {
+        let mut h = GenericArray::new();
+        cipher.encrypt_block(&GenericArray::new(), &mut h);
+        AesGcm {
+            cipher: cipher,
+            ghash: Ghash::new(&h),
+            tag_size: PhantomData,
+        }
+    }
+
+    /// Computes the pre-counter block J0, the IV must not be empty
+    fn j0(&self, iv: &[u8]) -> Result<[u8; 16], AeadError> {
+        if iv.len() == 0 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+
+        if iv.len() == 12 {
+            let mut j0 = [0u8; 16];
+            j0[..12].copy_from_slice(iv);
+            j0[15] = 1;
+            Ok(j0)
+        } else {
+            // GHASH(H, {}, IV) pads the IV and appends its length in bits
+            // after 64 zero bits, exactly as required for J0
+            Ok(self.ghash.input_c(iv).result())
+        }
+    }
+
+    /// XORs `buf` with the CTR keystream starting at `inc32(J0)`
+    fn ctr(&self, j0: &[u8; 16], buf: &mut [u8]) {
+        let mut counter = *j0;
+        let mut counters = [0u8; 128];
+        let mut keystream = [0u8; 128];
+        for chunk in buf.chunks_mut(128) {
+            for block in counters.chunks_mut(16) {
+                inc32(&mut counter);
+                block.copy_from_slice(&counter);
+            }
+            self.cipher.encrypt_block_x8(&counters, &mut keystream);
+            for (b, k) in chunk.iter_mut().zip(keystream.iter()) {
+                *b ^= *k;
+            }
+        }
+    }
+
+    /// Writes the tag truncated to the length of `tag`
+    fn compute_tag(&self, j0: &[u8; 16], aad: &[u8], ciphertext: &[u8],
+                   tag: &mut [u8]) {
+        let s = self.ghash.input_a(aad).input_c(ciphertext).result();
+
+        let mut block = GenericArray::new();
+        let mut mask = GenericArray::new();
+        block.copy_from_slice(j0);
+        self.cipher.encrypt_block(&block, &mut mask);
+        xor_keystream(tag, &s[..tag.len()], &mask);
+    }
+}
+
+macro_rules! impl_new_aead {
+    ($cipher:ident, $key_size:expr) => {
+        impl<TagSize> NewAead for AesGcm<$cipher, TagSize>
+            where TagSize: GcmTagSize
+        {
+            fn new(key: &[u8]) -> Result<AesGcm<$cipher, TagSize>, AeadError> {
+                if key.len() != $key_size {
+                    return Err(AeadError::InvalidKeyLength);
+                }
+                Ok(AesGcm::from_cipher($cipher::new(key)))
+            }
+        }
+    }
+}
+
+impl_new_aead!(Aes128, 16);
+impl_new_aead!(Aes192, 24);
+impl_new_aead!(Aes256, 32);
+
+impl<C, TagSize> Aead for AesGcm<C, TagSize>
+    where C: BlockCipher<BlockSize = U16> + BlockEncryptorX8,
+          TagSize: GcmTagSize
+{
+    type TagSize = TagSize;
+
+    fn encrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8])
+                                 -> Result<Tag<TagSize>, AeadError> {
+        check_lengths(aad.len() as u64, buf.len() as u64)?;
+        let j0 = self.j0(nonce)?;
+        self.ctr(&j0, buf);
+        let mut tag = GenericArray::new();
+        self.compute_tag(&j0, aad, buf, &mut tag);
+        Ok(tag)
+    }
+
+    fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8], tag: &[u8])
+                                 -> Result<(), AeadError> {
+        if tag.len() != TagSize::to_usize() {
+            return Err(AeadError::InvalidLength);
+        }
+        check_lengths(aad.len() as u64, buf.len() as u64)?;
+        let j0 = self.j0(nonce)?;
+
+        let mut calc_tag = [0u8; 16];
+        self.compute_tag(&j0, aad, buf, &mut calc_tag[..tag.len()]);
+        if fixed_time_eq(&calc_tag[..tag.len()], tag) {
+            self.ctr(&j0, buf);
+            Ok(())
+        } else {
+            for b in buf.iter_mut() {
+                *b = 0;
+            }
+            Err(AeadError::AuthenticationFailed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod bench;
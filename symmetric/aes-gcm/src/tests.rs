@@ -0,0 +1,370 @@
+use super::{AesGcm, Aes128Gcm, Aes192Gcm, Aes256Gcm, check_lengths,
+            MAX_AAD_LEN, MAX_PLAINTEXT_LEN};
+use generic_array::typenum::{U12, U148};
+use aes::Aes128;
+use crypto_aead::{Aead, NewAead, AeadError, Buffer, ArrayBuffer};
+use crypto_aead::commit::{KeyCommitting, COMMITMENT_LEN};
+
+struct TestVector {
+    pub name: &'static str,
+    pub key: &'static [u8],
+    pub nonce: &'static [u8],
+    pub input: &'static [u8],
+    pub output: &'static [u8],
+    pub aad: &'static [u8],
+    pub tag: &'static [u8],
+}
+
+macro_rules! get_vectors {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            TestVector {
+                name: $name,
+                key: include_bytes!(concat!("data/", $name, ".key.bin")),
+                nonce: include_bytes!(concat!("data/", $name, ".nonce.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                output: include_bytes!(concat!("data/", $name, ".output.bin")),
+                aad: include_bytes!(concat!("data/", $name, ".aad.bin")),
+                tag: include_bytes!(concat!("data/", $name, ".tag.bin")),
+            },
+        )*]
+    };
+}
+
+macro_rules! check_encrypt {
+    ($gcm:ident, $tv:expr) => {{
+        let msg_len = $tv.input.len();
+        let c = $gcm::new($tv.key).unwrap();
+        let mut output = [0u8; MAX_MSG_LEN];
+        let mut tag = [0u8; 16];
+        c.encrypt($tv.nonce, $tv.aad, $tv.input, &mut output[..msg_len],
+                  &mut tag).unwrap();
+        assert_eq!(&output[..msg_len], $tv.output);
+        assert_eq!(&tag[..], $tv.tag);
+    }}
+}
+
+macro_rules! check_decrypt {
+    ($gcm:ident, $tv:expr) => {{
+        let msg_len = $tv.input.len();
+        let c = $gcm::new($tv.key).unwrap();
+        let mut output = [0u8; MAX_MSG_LEN];
+        assert_eq!(c.decrypt($tv.nonce, $tv.aad, $tv.output,
+                             &mut output[..msg_len], $tv.tag),
+                   Ok(()));
+        assert_eq!(&output[..msg_len], $tv.input);
+
+        let mut tag = [0u8; 16];
+        tag.copy_from_slice($tv.tag);
+        tag[15] ^= 0x80;
+        assert_eq!(c.decrypt($tv.nonce, $tv.aad, $tv.output,
+                             &mut output[..msg_len], &tag),
+                   Err(AeadError::AuthenticationFailed));
+        assert!(output[..msg_len].iter().all(|&b| b == 0));
+    }}
+}
+
+const MAX_MSG_LEN: usize = 100;
+
+// Test cases 1-18 from "The Galois/Counter Mode of Operation (GCM)",
+// appendix B. Cases 5 and 6 (and their 192/256-bit counterparts) use
+// 64-bit and 480-bit IVs.
+// <http://csrc.nist.gov/groups/ST/toolkit/BCM/documents/proposedmodes/gcm/gcm-spec.pdf>
+const AES128_VECTORS: [TestVector; 6] = get_vectors!(
+    "1", "2", "3", "4", "5", "6");
+const AES192_VECTORS: [TestVector; 6] = get_vectors!(
+    "7", "8", "9", "10", "11", "12");
+const AES256_VECTORS: [TestVector; 6] = get_vectors!(
+    "13", "14", "15", "16", "17", "18");
+
+// 256-bit IVs chosen so that the counter part of J0 is 0xfffffffe and
+// 0xffffffff, the 32-bit counter must wrap without carrying into the rest
+// of the block. Checked with OpenSSL.
+const WRAP_VECTORS: [TestVector; 2] = get_vectors!("wrap_1", "wrap_2");
+
+// Cases from Wycheproof's aes_gcm_test.json, with 96-bit IVs
+// <https://github.com/google/wycheproof>
+const WYCHEPROOF_VECTORS: [TestVector; 3] = get_vectors!(
+    "wycheproof_1", "wycheproof_2", "wycheproof_3");
+
+// The Wycheproof vector used for its "invalid" modified tag cases, and the
+// modified tags: single bits and pairs of bits flipped, all bits flipped,
+// all zeros, all ones, and the most and least significant bit of every byte
+// flipped
+const WYCHEPROOF_TAG_VECTOR: [TestVector; 1] = get_vectors!("wycheproof_tag");
+const WYCHEPROOF_INVALID_TAGS: &'static [u8] =
+    include_bytes!("data/wycheproof_tag.invalid.bin");
+
+// 64, 128 and 512-bit IVs, which are hashed into J0. Computed with OpenSSL.
+const IV_VECTORS: [TestVector; 3] = get_vectors!("iv_1", "iv_2", "iv_3");
+
+#[test]
+fn aes_gcm_wycheproof() {
+    for tv in WYCHEPROOF_VECTORS.iter().chain(WYCHEPROOF_TAG_VECTOR.iter()) {
+        check_encrypt!(Aes128Gcm, tv);
+        check_decrypt!(Aes128Gcm, tv);
+    }
+
+    let tv = &WYCHEPROOF_TAG_VECTOR[0];
+    let c = Aes128Gcm::new(tv.key).unwrap();
+    let mut output = [0u8; MAX_MSG_LEN];
+    let msg_len = tv.input.len();
+    for tag in WYCHEPROOF_INVALID_TAGS.chunks(16) {
+        assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output,
+                             &mut output[..msg_len], tag),
+                   Err(AeadError::AuthenticationFailed));
+    }
+
+    // An empty IV is invalid
+    assert_eq!(c.decrypt(&[], tv.aad, tv.output, &mut output[..msg_len],
+                         tv.tag),
+               Err(AeadError::InvalidNonceLength));
+}
+
+#[test]
+fn aes_gcm_other_iv_lengths() {
+    for tv in IV_VECTORS.iter() {
+        check_encrypt!(Aes128Gcm, tv);
+        check_decrypt!(Aes128Gcm, tv);
+    }
+}
+
+#[test]
+fn aes_gcm_length_limits() {
+    assert_eq!(check_lengths(MAX_AAD_LEN, MAX_PLAINTEXT_LEN), Ok(()));
+    assert_eq!(check_lengths(MAX_AAD_LEN + 1, 0),
+               Err(AeadError::InvalidLength));
+    assert_eq!(check_lengths(0, MAX_PLAINTEXT_LEN + 1),
+               Err(AeadError::InvalidLength));
+}
+
+#[test]
+fn aes_gcm_encrypt() {
+    for tv in AES128_VECTORS.iter() { check_encrypt!(Aes128Gcm, tv); }
+    for tv in AES192_VECTORS.iter() { check_encrypt!(Aes192Gcm, tv); }
+    for tv in AES256_VECTORS.iter() { check_encrypt!(Aes256Gcm, tv); }
+}
+
+#[test]
+fn aes_gcm_decrypt() {
+    for tv in AES128_VECTORS.iter() { check_decrypt!(Aes128Gcm, tv); }
+    for tv in AES192_VECTORS.iter() { check_decrypt!(Aes192Gcm, tv); }
+    for tv in AES256_VECTORS.iter() { check_decrypt!(Aes256Gcm, tv); }
+}
+
+#[test]
+fn aes_gcm_counter_wrap() {
+    check_encrypt!(Aes128Gcm, WRAP_VECTORS[0]);
+    check_decrypt!(Aes128Gcm, WRAP_VECTORS[0]);
+    check_encrypt!(Aes256Gcm, WRAP_VECTORS[1]);
+    check_decrypt!(Aes256Gcm, WRAP_VECTORS[1]);
+}
+
+#[test]
+fn aes_gcm_in_place_detached() {
+    for tv in AES256_VECTORS.iter().chain(WRAP_VECTORS.iter().skip(1)) {
+        let msg_len = tv.input.len();
+        let mut buf = [0u8; MAX_MSG_LEN];
+        buf[..msg_len].copy_from_slice(tv.input);
+        let c = Aes256Gcm::new(tv.key).unwrap();
+        let tag = c.encrypt_in_place_detached(tv.nonce, tv.aad,
+                                              &mut buf[..msg_len]).unwrap();
+        assert_eq!(&buf[..msg_len], tv.output);
+        assert_eq!(&tag[..], tv.tag);
+
+        let result = c.decrypt_in_place_detached(tv.nonce, tv.aad,
+                                                 &mut buf[..msg_len], tv.tag);
+        assert_eq!(result, Ok(()));
+        assert_eq!(&buf[..msg_len], tv.input);
+    }
+}
+
+fn assert_sync<T: Sync>() {}
+
+#[test]
+fn aes_gcm_key_reuse() {
+    assert_sync::<Aes128Gcm>();
+    assert_sync::<Aes256Gcm>();
+
+    // Test cases 3 to 6 share their key, with 96, 64 and 480-bit IVs
+    let c = Aes128Gcm::new(AES128_VECTORS[2].key).unwrap();
+    let mut output = [0u8; MAX_MSG_LEN];
+    let mut tag = [0u8; 16];
+    for tv in AES128_VECTORS[2..].iter().chain(AES128_VECTORS[2..].iter()) {
+        let msg_len = tv.input.len();
+        c.encrypt(tv.nonce, tv.aad, tv.input, &mut output[..msg_len], &mut tag)
+            .unwrap();
+        assert_eq!(&output[..msg_len], tv.output);
+        assert_eq!(&tag[..], tv.tag);
+    }
+}
+
+#[test]
+fn aes_gcm_truncated_tag() {
+    let tv = &AES128_VECTORS[3];
+    let msg_len = tv.input.len();
+    let mut output = [0u8; MAX_MSG_LEN];
+    let mut tag = [0u8; 12];
+    let c = AesGcm::<Aes128, U12>::new(tv.key).unwrap();
+    c.encrypt(tv.nonce, tv.aad, tv.input, &mut output[..msg_len], &mut tag)
+        .unwrap();
+    assert_eq!(&tag[..], &tv.tag[..12]);
+
+    assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output, &mut output[..msg_len],
+                         &tv.tag[..12]),
+               Ok(()));
+    assert_eq!(&output[..msg_len], tv.input);
+
+    // Only tags of exactly the configured size are accepted
+    assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output, &mut output[..msg_len],
+                         tv.tag),
+               Err(AeadError::InvalidLength));
+    assert_eq!(c.encrypt(tv.nonce, tv.aad, tv.input, &mut output[..msg_len],
+                         &mut [0u8; 16]),
+               Err(AeadError::InvalidLength));
+    let c = Aes128Gcm::new(tv.key).unwrap();
+    for len in [0, 12, 15].iter() {
+        assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output,
+                             &mut output[..msg_len], &tv.tag[..*len]),
+                   Err(AeadError::InvalidLength));
+    }
+}
+
+#[test]
+fn aes_gcm_modified_input() {
+    let tv = &AES128_VECTORS[3];
+    let msg_len = tv.input.len();
+    let mut output = [0u8; MAX_MSG_LEN];
+    let mut ciphertext = [0u8; MAX_MSG_LEN];
+    ciphertext[..msg_len].copy_from_slice(tv.output);
+    ciphertext[msg_len - 1] ^= 1;
+    let c = Aes128Gcm::new(tv.key).unwrap();
+    assert_eq!(c.decrypt(tv.nonce, tv.aad, &ciphertext[..msg_len],
+                         &mut output[..msg_len], tv.tag),
+               Err(AeadError::AuthenticationFailed));
+
+    assert_eq!(c.decrypt(tv.nonce, &tv.aad[1..], tv.output,
+                         &mut output[..msg_len], tv.tag),
+               Err(AeadError::AuthenticationFailed));
+}
+
+#[test]
+fn aes_gcm_invalid_lengths() {
+    let tv = &AES128_VECTORS[3];
+    let msg_len = tv.input.len();
+    let mut output = [0u8; MAX_MSG_LEN];
+    assert_eq!(Aes128Gcm::new(&tv.key[..15]).err(),
+               Some(AeadError::InvalidKeyLength));
+    assert_eq!(Aes256Gcm::new(tv.key).err(),
+               Some(AeadError::InvalidKeyLength));
+
+    let c = Aes128Gcm::new(tv.key).unwrap();
+    assert_eq!(c.encrypt(&[], tv.aad, tv.input, &mut output[..msg_len],
+                         &mut [0u8; 16]),
+               Err(AeadError::InvalidNonceLength));
+    assert_eq!(c.encrypt(tv.nonce, tv.aad, tv.input, &mut output[..msg_len],
+                         &mut [0u8; 11]),
+               Err(AeadError::InvalidLength));
+    assert_eq!(c.encrypt(tv.nonce, tv.aad, tv.input,
+                         &mut output[..msg_len - 1], &mut [0u8; 16]),
+               Err(AeadError::InvalidLength));
+    assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output, &mut output[..msg_len],
+                         &[0u8; 17]),
+               Err(AeadError::InvalidLength));
+}
+
+// A 48-byte ciphertext and tag which authenticate under two AES-128 keys,
+// obtained by solving the GHASH equation for the last ciphertext block. The
+// first 32 bytes decrypt to zeros under the first key only. Generated with
+// the `cryptography` Python package.
+const COLLISION_KEY1: &'static [u8] = include_bytes!("data/collision.key1.bin");
+const COLLISION_KEY2: &'static [u8] = include_bytes!("data/collision.key2.bin");
+const COLLISION_NONCE: &'static [u8] =
+    include_bytes!("data/collision.nonce.bin");
+const COLLISION_OUTPUT: &'static [u8] =
+    include_bytes!("data/collision.output.bin");
+const COLLISION_TAG: &'static [u8] = include_bytes!("data/collision.tag.bin");
+
+#[test]
+fn aes_gcm_key_collision() {
+    let n = COLLISION_OUTPUT.len();
+    let mut buf = [0u8; MAX_MSG_LEN];
+
+    // GCM alone accepts the ciphertext under both keys
+    for key in [COLLISION_KEY1, COLLISION_KEY2].iter() {
+        let c = Aes128Gcm::new(key).unwrap();
+        assert_eq!(c.decrypt(COLLISION_NONCE, &[], COLLISION_OUTPUT,
+                             &mut buf[..n], COLLISION_TAG),
+                   Ok(()));
+    }
+
+    // With the commitment it only decrypts under the first one
+    let c = KeyCommitting::<Aes128Gcm>::new(COLLISION_KEY1).unwrap();
+    buf[..n].copy_from_slice(COLLISION_OUTPUT);
+    assert_eq!(c.decrypt_in_place_detached(COLLISION_NONCE, &[],
+                                           &mut buf[..n], COLLISION_TAG),
+               Ok(()));
+    assert!(buf[..COMMITMENT_LEN].iter().all(|&b| b == 0));
+
+    let c = KeyCommitting::<Aes128Gcm>::new(COLLISION_KEY2).unwrap();
+    buf[..n].copy_from_slice(COLLISION_OUTPUT);
+    assert_eq!(c.decrypt_in_place_detached(COLLISION_NONCE, &[],
+                                           &mut buf[..n], COLLISION_TAG),
+               Err(AeadError::AuthenticationFailed));
+    assert!(buf[..n].iter().all(|&b| b == 0));
+}
+
+/// Room for the longest message, its commitment and its tag,
+/// `MAX_MSG_LEN + COMMITMENT_LEN + 16`
+type TestBuffer = ArrayBuffer<U148>;
+
+/// Encrypts and decrypts `input` through the `Aead` trait only, returning
+/// the length of the sealed buffer
+fn generic_roundtrip<A: Aead>(aead: &A, tv: &TestVector) -> usize {
+    let mut buf = TestBuffer::from_slice(tv.input).unwrap();
+    aead.encrypt_in_place(tv.nonce, tv.aad, &mut buf).unwrap();
+    let sealed_len = buf.len();
+    assert_eq!(aead.decrypt_in_place(tv.nonce, tv.aad, &mut buf), Ok(()));
+    assert_eq!(buf.as_ref(), tv.input);
+    sealed_len
+}
+
+#[test]
+fn aes_gcm_key_committing() {
+    assert_sync::<KeyCommitting<Aes256Gcm>>();
+
+    for tv in AES128_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let c = KeyCommitting::<Aes128Gcm>::new(tv.key).unwrap();
+        let mut buf = TestBuffer::from_slice(tv.input).unwrap();
+        c.encrypt_in_place(tv.nonce, tv.aad, &mut buf).unwrap();
+        assert_eq!(buf.len(), COMMITMENT_LEN + msg_len + 16);
+        assert_eq!(c.decrypt_in_place(tv.nonce, tv.aad, &mut buf), Ok(()));
+        assert_eq!(buf.as_ref(), tv.input);
+
+        // Under another key the commitment and the tag are still removed
+        let other = KeyCommitting::<Aes128Gcm>::new(COLLISION_KEY1).unwrap();
+        c.encrypt_in_place(tv.nonce, tv.aad, &mut buf).unwrap();
+        assert_eq!(other.decrypt_in_place(tv.nonce, tv.aad, &mut buf),
+                   Err(AeadError::AuthenticationFailed));
+        assert_eq!(buf.len(), msg_len);
+        assert!(buf.as_ref().iter().all(|&b| b == 0));
+
+        // Generic code over `Aead` gets the commitment as well
+        assert_eq!(generic_roundtrip(&c, tv), COMMITMENT_LEN + msg_len + 16);
+        let gcm = Aes128Gcm::new(tv.key).unwrap();
+        assert_eq!(generic_roundtrip(&gcm, tv), msg_len + 16);
+    }
+
+    let tv = &AES128_VECTORS[0];
+    let c = KeyCommitting::<Aes128Gcm>::new(tv.key).unwrap();
+    assert_eq!(KeyCommitting::<Aes128Gcm>::new(&tv.key[..15]).err(),
+               Some(AeadError::InvalidKeyLength));
+    let mut buf = [0u8; COMMITMENT_LEN + 16];
+    assert_eq!(c.encrypt_in_place_detached(tv.nonce, &[],
+                                           &mut buf[..COMMITMENT_LEN - 1]),
+               Err(AeadError::InvalidLength));
+    let mut buf = TestBuffer::from_slice(&buf[1..]).unwrap();
+    assert_eq!(c.decrypt_in_place(tv.nonce, &[], &mut buf),
+               Err(AeadError::InvalidLength));
+}
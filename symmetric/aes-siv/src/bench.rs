@@ -0,0 +1,42 @@
+use test::Bencher;
+use crypto_aead::{Aead, NewAead};
+use super::{Aes128Siv, Aes256Siv};
+
+#[bench]
+pub fn aes128_siv_10(bh: & mut Bencher) {
+    let input = [1u8; 10];
+    let aad = [3u8; 10];
+    let siv = Aes128Siv::new(&[0; 32]).unwrap();
+    bh.iter( || {
+        let mut output = [0u8; 10];
+        let mut tag = [0u8; 16];
+        siv.encrypt(&[0; 16], &aad, &input, &mut output, &mut tag).unwrap();
+    });
+    bh.bytes = 10u64;
+}
+
+#[bench]
+pub fn aes128_siv_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let aad = [3u8; 1024];
+    let siv = Aes128Siv::new(&[0; 32]).unwrap();
+    bh.iter( || {
+        let mut output = [0u8; 1024];
+        let mut tag = [0u8; 16];
+        siv.encrypt(&[0; 16], &aad, &input, &mut output, &mut tag).unwrap();
+    });
+    bh.bytes = 1024u64;
+}
+
+#[bench]
+pub fn aes256_siv_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let aad = [3u8; 1024];
+    let siv = Aes256Siv::new(&[0; 64]).unwrap();
+    bh.iter( || {
+        let mut output = [0u8; 1024];
+        let mut tag = [0u8; 16];
+        siv.encrypt(&[0; 16], &aad, &input, &mut output, &mut tag).unwrap();
+    });
+    bh.bytes = 1024u64;
+}
@@ -0,0 +1,234 @@
+//! This is an implementation of the Synthetic Initialization Vector (SIV)
+//! mode for AES, as specified in RFC 5297 [1].
+//!
+//! The key is split in two halves of equal length. The first one keys
+//! S2V, a PRF built on CMAC (see the `cmac` crate) over a vector of strings,
+//! which is applied to the associated data components followed by the
+//! plaintext. The result is both the authentication tag and, with two bits
+//! cleared, the initial counter block for encrypting the plaintext in CTR
+//! mode under the second half of the key.
+//!
+//! Without a nonce SIV is deterministic: encrypting the same plaintext with
+//! the same associated data always gives the same ciphertext, which only
+//! reveals whether two messages are equal. A nonce can be added as the last
+//! associated data component to make it randomized.
+//!
+//! [1] - "Synthetic Initialization Vector (SIV) Authenticated Encryption
+//!       Using the Advanced Encryption Standard (AES)"
+//!       <https://tools.ietf.org/html/rfc5297>
+
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate generic_array;
+extern crate aes;
+extern crate blockmodes;
+extern crate cmac;
+extern crate crypto_aead;
+extern crate crypto_mac;
+extern crate crypto_ops;
+extern crate crypto_symmetric;
+
+use generic_array::GenericArray;
+use generic_array::typenum::U16;
+use aes::{Aes128, Aes192, Aes256};
+use blockmodes::{CtrModeX8, Ctr128BE};
+use cmac::{Cmac, dbl};
+use crypto_aead::{Aead, NewAead, AeadError, Tag};
+use crypto_mac::Mac;
+use crypto_ops::fixed_time_eq;
+use crypto_symmetric::{BlockCipher, BlockEncryptorX8,
+                       SynchronousStreamCipher};
+
+/// S2V accepts at most 126 associated data components
+pub const MAX_HEADERS: usize = 126;
+
+fn xor_block(block: &mut [u8; 16], other: &[u8]) {
+    for (b, o) in block.iter_mut().zip(other.iter()) {
+        *b ^= *o;
+    }
+}
+
+/// The S2V construction over a block cipher with a 128-bit block size,
+/// keyed by the cipher
+#[derive(Clone, Copy)]
+pub struct S2v<C> {
+    /// Keyed CMAC state, cloned for every string
+    cmac: Cmac<C>,
+}
+
+impl<C> S2v<C> where C: BlockCipher<BlockSize = U16> + Clone {
+    /// Sets up S2V with an already keyed `cipher`
+    pub fn new(cipher: C) -> S2v<C> {
+        S2v { cmac: Cmac::new(cipher) }
+    }
+
+    fn mac(&self, data: &[u8]) -> [u8; 16] {
+        let mut cmac = self.cmac.clone();
+        cmac.input(data);
+        let mut out = [0u8; 16];
+        cmac.raw_result(&mut out);
+        out
+    }
+
+    /// Computes S2V over the `headers` followed by `plaintext`, which is
+    /// always the last string of the vector
+    pub fn s2v(&self, headers: &[&[u8]], plaintext: &[u8])
+               -> Result<[u8; 16], AeadError> {
+        if headers.len() > MAX_HEADERS {
+            return Err(AeadError::InvalidLength);
+        }
+        let mut d = self.mac(&[0u8; 16]);
+        for header in headers {
+            d = dbl(&d);
+            xor_block(&mut d, &self.mac(header));
+        }
+
+        let mut cmac = self.cmac.clone();
+        if plaintext.len() >= 16 {
+            // xorend: D is XORed into the last 16 bytes of the plaintext
+            let (head, tail) = plaintext.split_at(plaintext.len() - 16);
+            xor_block(&mut d, tail);
+            cmac.input(head);
+            cmac.input(&d);
+        } else {
+            d = dbl(&d);
+            xor_block(&mut d, plaintext);
+            d[plaintext.len()] ^= 0x80;
+            cmac.input(&d);
+        }
+        let mut v = [0u8; 16];
+        cmac.raw_result(&mut v);
+        Ok(v)
+    }
+}
+
+/// SIV over a block cipher with a 128-bit block size. Use one of the
+/// `Aes128Siv`, `Aes192Siv` or `Aes256Siv` aliases, which take keys of
+/// twice the AES key size, or `from_ciphers`.
+#[derive(Clone, Copy)]
+pub struct AesSiv<C> {
+    s2v: S2v<C>,
+    cipher: C,
+}
+
+/// AES-SIV with a 256-bit key, `AEAD_AES_SIV_CMAC_256`
+pub type Aes128Siv = AesSiv<Aes128>;
+/// AES-SIV with a 384-bit key, `AEAD_AES_SIV_CMAC_384`
+pub type Aes192Siv = AesSiv<Aes192>;
+/// AES-SIV with a 512-bit key, `AEAD_AES_SIV_CMAC_512`
+pub type Aes256Siv = AesSiv<Aes256>;
+
+impl<C> AesSiv<C>
+    where C: BlockCipher<BlockSize = U16> + BlockEncryptorX8 + Clone
+{
+    /// Sets up SIV with a cipher keyed for S2V and one keyed for CTR mode
+    pub fn from_ciphers(mac: C, cipher: C) -> AesSiv<C> {
+        AesSiv { s2v: S2v::new(mac), cipher: cipher }
+    }
+
+    /// XORs `buf` with the CTR keystream, the initial counter is the
+    /// synthetic IV with the 31st and 63rd bits from the right cleared and
+    /// is incremented as a 128-bit big-endian integer
+    fn ctr(&self, iv: &[u8], buf: &mut [u8]) {
+        let mut counter = GenericArray::from_slice(iv);
+        counter[8] &= 0x7f;
+        counter[12] &= 0x7f;
+
+        let mut ctr: CtrModeX8<C, Ctr128BE> =
+            CtrModeX8::new(self.cipher.clone(), &counter);
+        let mut out = [0u8; 128];
+        for chunk in buf.chunks_mut(128) {
+            let out = &mut out[..chunk.len()];
+            ctr.process(chunk, out);
+            chunk.copy_from_slice(out);
+        }
+    }
+
+    /// Encrypts `buf` in place and returns the synthetic IV, which also
+    /// serves as the tag. `headers` are the associated data components, at
+    /// most `MAX_HEADERS` of them.
+    pub fn encrypt_in_place_with_headers(&self, headers: &[&[u8]],
+                                         buf: &mut [u8])
+                                         -> Result<Tag<U16>, AeadError> {
+        let iv = self.s2v.s2v(headers, buf)?;
+        self.ctr(&iv, buf);
+        let mut tag = GenericArray::new();
+        tag.copy_from_slice(&iv);
+        Ok(tag)
+    }
+
+    /// Decrypts `buf` in place and checks the synthetic IV `tag`. If
+    /// authentication fails `buf` is zeroed.
+    pub fn decrypt_in_place_with_headers(&self, headers: &[&[u8]],
+                                         buf: &mut [u8], tag: &[u8])
+                                         -> Result<(), AeadError> {
+        if tag.len() != 16 {
+            return Err(AeadError::InvalidLength);
+        }
+        if headers.len() > MAX_HEADERS {
+            return Err(AeadError::InvalidLength);
+        }
+        self.ctr(tag, buf);
+        if fixed_time_eq(&self.s2v.s2v(headers, buf)?, tag) {
+            Ok(())
+        } else {
+            for b in buf.iter_mut() {
+                *b = 0;
+            }
+            Err(AeadError::AuthenticationFailed)
+        }
+    }
+}
+
+macro_rules! impl_new_aead {
+    ($cipher:ident, $key_size:expr) => {
+        impl NewAead for AesSiv<$cipher> {
+            fn new(key: &[u8]) -> Result<AesSiv<$cipher>, AeadError> {
+                if key.len() != 2 * $key_size {
+                    return Err(AeadError::InvalidKeyLength);
+                }
+                let (k1, k2) = key.split_at($key_size);
+                Ok(AesSiv::from_ciphers($cipher::new(k1), $cipher::new(k2)))
+            }
+        }
+    }
+}
+
+impl_new_aead!(Aes128, 16);
+impl_new_aead!(Aes192, 24);
+impl_new_aead!(Aes256, 32);
+
+/// The AAD and the nonce are the two associated data components, in that
+/// order. An empty nonce is left out, which gives deterministic encryption.
+impl<C> Aead for AesSiv<C>
+    where C: BlockCipher<BlockSize = U16> + BlockEncryptorX8 + Clone
+{
+    type TagSize = U16;
+
+    fn encrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8])
+                                 -> Result<Tag<U16>, AeadError> {
+        if nonce.len() == 0 {
+            self.encrypt_in_place_with_headers(&[aad], buf)
+        } else {
+            self.encrypt_in_place_with_headers(&[aad, nonce], buf)
+        }
+    }
+
+    fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8], tag: &[u8])
+                                 -> Result<(), AeadError> {
+        if nonce.len() == 0 {
+            self.decrypt_in_place_with_headers(&[aad], buf, tag)
+        } else {
+            self.decrypt_in_place_with_headers(&[aad, nonce], buf, tag)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod bench;
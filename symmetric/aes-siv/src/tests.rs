@@ -0,0 +1,161 @@
+use super::{Aes128Siv, Aes192Siv, Aes256Siv, MAX_HEADERS};
+use crypto_aead::{Aead, NewAead, AeadError};
+
+struct TestVector {
+    pub name: &'static str,
+    pub key: &'static [u8],
+    pub nonce: &'static [u8],
+    pub input: &'static [u8],
+    pub output: &'static [u8],
+    pub aad: &'static [u8],
+    pub tag: &'static [u8],
+}
+
+macro_rules! get_vectors {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            TestVector {
+                name: $name,
+                key: include_bytes!(concat!("data/", $name, ".key.bin")),
+                nonce: include_bytes!(concat!("data/", $name, ".nonce.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                output: include_bytes!(concat!("data/", $name, ".output.bin")),
+                aad: include_bytes!(concat!("data/", $name, ".aad.bin")),
+                tag: include_bytes!(concat!("data/", $name, ".tag.bin")),
+            },
+        )*]
+    };
+}
+
+macro_rules! check_encrypt {
+    ($siv:ident, $tv:expr) => {{
+        let msg_len = $tv.input.len();
+        let c = $siv::new($tv.key).unwrap();
+        let mut output = [0u8; MAX_MSG_LEN];
+        let mut tag = [0u8; 16];
+        c.encrypt($tv.nonce, $tv.aad, $tv.input, &mut output[..msg_len],
+                  &mut tag).unwrap();
+        assert_eq!(&output[..msg_len], $tv.output);
+        assert_eq!(&tag[..], $tv.tag);
+    }}
+}
+
+macro_rules! check_decrypt {
+    ($siv:ident, $tv:expr) => {{
+        let msg_len = $tv.input.len();
+        let c = $siv::new($tv.key).unwrap();
+        let mut output = [0u8; MAX_MSG_LEN];
+        assert_eq!(c.decrypt($tv.nonce, $tv.aad, $tv.output,
+                             &mut output[..msg_len], $tv.tag),
+                   Ok(()));
+        assert_eq!(&output[..msg_len], $tv.input);
+
+        let mut tag = [0u8; 16];
+        tag.copy_from_slice($tv.tag);
+        tag[0] ^= 1;
+        assert_eq!(c.decrypt($tv.nonce, $tv.aad, $tv.output,
+                             &mut output[..msg_len], &tag),
+                   Err(AeadError::AuthenticationFailed));
+        assert!(output[..msg_len].iter().all(|&b| b == 0));
+    }}
+}
+
+const MAX_MSG_LEN: usize = 300;
+
+// RFC 5297, appendix A.1 (deterministic, without a nonce), followed by
+// vectors generated with the `cryptography` Python package. Vectors 4 to 8
+// have a 384 or 512-bit key, 7 has no nonce.
+const AES128_VECTORS: [TestVector; 2] = get_vectors!("1", "3");
+const AES192_VECTORS: [TestVector; 2] = get_vectors!("4", "5");
+const AES256_VECTORS: [TestVector; 3] = get_vectors!("6", "7", "8");
+
+#[test]
+fn aes_siv_encrypt() {
+    for tv in AES128_VECTORS.iter() { check_encrypt!(Aes128Siv, tv); }
+    for tv in AES192_VECTORS.iter() { check_encrypt!(Aes192Siv, tv); }
+    for tv in AES256_VECTORS.iter() { check_encrypt!(Aes256Siv, tv); }
+}
+
+#[test]
+fn aes_siv_decrypt() {
+    for tv in AES128_VECTORS.iter() { check_decrypt!(Aes128Siv, tv); }
+    for tv in AES192_VECTORS.iter() { check_decrypt!(Aes192Siv, tv); }
+    for tv in AES256_VECTORS.iter() { check_decrypt!(Aes256Siv, tv); }
+}
+
+#[test]
+fn aes_siv_multiple_headers() {
+    // RFC 5297, appendix A.2: two AAD components followed by a nonce
+    let key = include_bytes!("data/2.key.bin");
+    let ad1 = include_bytes!("data/2.ad1.bin");
+    let ad2 = include_bytes!("data/2.ad2.bin");
+    let nonce = include_bytes!("data/2.nonce.bin");
+    let input = include_bytes!("data/2.input.bin");
+    let output = include_bytes!("data/2.output.bin");
+    let expected_tag = include_bytes!("data/2.tag.bin");
+    let headers: [&[u8]; 3] = [ad1, ad2, nonce];
+
+    let msg_len = input.len();
+    let mut buf = [0u8; MAX_MSG_LEN];
+    buf[..msg_len].copy_from_slice(input);
+    let c = Aes128Siv::new(key).unwrap();
+    let tag = c.encrypt_in_place_with_headers(&headers, &mut buf[..msg_len])
+               .unwrap();
+    assert_eq!(&buf[..msg_len], &output[..]);
+    assert_eq!(&tag[..], &expected_tag[..]);
+
+    assert_eq!(c.decrypt_in_place_with_headers(&headers, &mut buf[..msg_len],
+                                               &tag),
+               Ok(()));
+    assert_eq!(&buf[..msg_len], &input[..]);
+
+    // The components are not simply concatenated
+    buf[..msg_len].copy_from_slice(output);
+    let swapped: [&[u8]; 3] = [ad2, ad1, nonce];
+    assert_eq!(c.decrypt_in_place_with_headers(&swapped, &mut buf[..msg_len],
+                                               &tag),
+               Err(AeadError::AuthenticationFailed));
+}
+
+#[test]
+fn aes_siv_deterministic() {
+    // Without a nonce equal messages give equal ciphertexts
+    let tv = &AES256_VECTORS[1];
+    let c = Aes256Siv::new(tv.key).unwrap();
+    let mut buf = [0u8; 16];
+    buf.copy_from_slice(tv.input);
+    let tag = c.encrypt_in_place_detached(&[], tv.aad, &mut buf).unwrap();
+    assert_eq!(&buf[..], tv.output);
+    assert_eq!(&tag[..], tv.tag);
+
+    // An empty nonce is not the same as no nonce at all
+    let c = Aes128Siv::new(AES128_VECTORS[0].key).unwrap();
+    let tv = &AES128_VECTORS[0];
+    let with_nonce = c.encrypt_in_place_with_headers(&[tv.aad, &[]],
+                                                     &mut [0u8; 4]).unwrap();
+    let without = c.encrypt_in_place_with_headers(&[tv.aad], &mut [0u8; 4])
+                   .unwrap();
+    assert!(with_nonce != without);
+}
+
+#[test]
+fn aes_siv_invalid_lengths() {
+    let tv = &AES128_VECTORS[1];
+    let msg_len = tv.input.len();
+    let mut output = [0u8; MAX_MSG_LEN];
+    assert_eq!(Aes128Siv::new(&tv.key[..16]).err(),
+               Some(AeadError::InvalidKeyLength));
+    assert_eq!(Aes256Siv::new(tv.key).err(),
+               Some(AeadError::InvalidKeyLength));
+
+    let c = Aes128Siv::new(tv.key).unwrap();
+    assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output, &mut output[..msg_len],
+                         &tv.tag[..15]),
+               Err(AeadError::InvalidLength));
+
+    let headers = [&[][..]; MAX_HEADERS + 1];
+    assert_eq!(c.encrypt_in_place_with_headers(&headers, &mut output).err(),
+               Some(AeadError::InvalidLength));
+    assert!(c.encrypt_in_place_with_headers(&headers[1..], &mut output)
+             .is_ok());
+}
@@ -0,0 +1,48 @@
+use test::Bencher;
+use generic_array::GenericArray;
+use crypto_symmetric::{BlockCipher, BlockEncryptorX8};
+use super::{Aes128, Aes256};
+
+#[bench]
+pub fn aes128_encrypt_block(bh: & mut Bencher) {
+    let cipher = Aes128::new(&[0; 16]);
+    let input = GenericArray::new();
+    let mut output = GenericArray::new();
+    bh.iter( || {
+        cipher.encrypt_block(&input, &mut output);
+    });
+    bh.bytes = 16u64;
+}
+
+#[bench]
+pub fn aes128_encrypt_x8(bh: & mut Bencher) {
+    let cipher = Aes128::new(&[0; 16]);
+    let input = [1u8; 128];
+    let mut output = [0u8; 128];
+    bh.iter( || {
+        cipher.encrypt_block_x8(&input, &mut output);
+    });
+    bh.bytes = input.len() as u64;
+}
+
+#[bench]
+pub fn aes256_encrypt_x8(bh: & mut Bencher) {
+    let cipher = Aes256::new(&[0; 32]);
+    let input = [1u8; 128];
+    let mut output = [0u8; 128];
+    bh.iter( || {
+        cipher.encrypt_block_x8(&input, &mut output);
+    });
+    bh.bytes = input.len() as u64;
+}
+
+#[bench]
+pub fn aes128_encrypt_x8_soft(bh: & mut Bencher) {
+    let cipher = Aes128::new_soft(&[0; 16]);
+    let input = [1u8; 128];
+    let mut output = [0u8; 128];
+    bh.iter( || {
+        cipher.encrypt_block_x8(&input, &mut output);
+    });
+    bh.bytes = input.len() as u64;
+}
@@ -0,0 +1,538 @@
+//! Constant-time bitsliced AES core, processing four blocks at once.
+//!
+//! This follows the "ct64" implementation from BearSSL by Thomas Pornin.
+//! The state of four blocks is held in eight 64-bit words, word `i`
+//! containing bit `i` of every state byte, so that the S-box can be
+//! computed with the Boyar-Peralta circuit using only boolean operations.
+//! No secret-dependent memory accesses or branches are performed.
+
+use crypto_bytes::{read_u32_le, write_u32_le};
+
+/// Bitsliced representation of a single round key, replicated for each of
+/// the four processed blocks
+pub type RoundKey = [u64; 8];
+
+/// Forward S-box applied in parallel to all bytes of the bitsliced state
+fn sub_bytes(q: &mut [u64; 8]) {
+    let x0 = q[7];
+    let x1 = q[6];
+    let x2 = q[5];
+    let x3 = q[4];
+    let x4 = q[3];
+    let x5 = q[2];
+    let x6 = q[1];
+    let x7 = q[0];
+
+    // Top linear transformation
+    let y14 = x3 ^ x5;
+    let y13 = x0 ^ x6;
+    let y9 = x0 ^ x3;
+    let y8 = x0 ^ x5;
+    let t0 = x1 ^ x2;
+    let y1 = t0 ^ x7;
+    let y4 = y1 ^ x3;
+    let y12 = y13 ^ y14;
+    let y2 = y1 ^ x0;
+    let y5 = y1 ^ x6;
+    let y3 = y5 ^ y8;
+    let t1 = x4 ^ y12;
+    let y15 = t1 ^ x5;
+    let y20 = t1 ^ x1;
+    let y6 = y15 ^ x7;
+    let y10 = y15 ^ t0;
+    let y11 = y20 ^ y9;
+    let y7 = x7 ^ y11;
+    let y17 = y10 ^ y11;
+    let y19 = y10 ^ y8;
+    let y16 = t0 ^ y11;
+    let y21 = y13 ^ y16;
+    let y18 = x0 ^ y16;
+
+    // Non-linear section
+    let t2 = y12 & y15;
+    let t3 = y3 & y6;
+    let t4 = t3 ^ t2;
+    let t5 = y4 & x7;
+    let t6 = t5 ^ t2;
+    let t7 = y13 & y16;
+    let t8 = y5 & y1;
+    let t9 = t8 ^ t7;
+    let t10 = y2 & y7;
+    let t11 = t10 ^ t7;
+    let t12 = y9 & y11;
+    let t13 = y14 & y17;
+    let t14 = t13 ^ t12;
+    let t15 = y8 & y10;
+    let t16 = t15 ^ t12;
+    let t17 = t4 ^ t14;
+    let t18 = t6 ^ t16;
+    let t19 = t9 ^ t14;
+    let t20 = t11 ^ t16;
+    let t21 = t17 ^ y20;
+    let t22 = t18 ^ y19;
+    let t23 = t19 ^ y21;
+    let t24 = t20 ^ y18;
+
+    let t25 = t21 ^ t22;
+    let t26 = t21 & t23;
+    let t27 = t24 ^ t26;
+    let t28 = t25 & t27;
+    let t29 = t28 ^ t22;
+    let t30 = t23 ^ t24;
+    let t31 = t22 ^ t26;
+    let t32 = t31 & t30;
+    let t33 = t32 ^ t24;
+    let t34 = t23 ^ t33;
+    let t35 = t27 ^ t33;
+    let t36 = t24 & t35;
+    let t37 = t36 ^ t34;
+    let t38 = t27 ^ t36;
+    let t39 = t29 & t38;
+    let t40 = t25 ^ t39;
+
+    let t41 = t40 ^ t37;
+    let t42 = t29 ^ t33;
+    let t43 = t29 ^ t40;
+    let t44 = t33 ^ t37;
+    let t45 = t42 ^ t41;
+    let z0 = t44 & y15;
+    let z1 = t37 & y6;
+    let z2 = t33 & x7;
+    let z3 = t43 & y16;
+    let z4 = t40 & y1;
+    let z5 = t29 & y7;
+    let z6 = t42 & y11;
+    let z7 = t45 & y17;
+    let z8 = t41 & y10;
+    let z9 = t44 & y12;
+    let z10 = t37 & y3;
+    let z11 = t33 & y4;
+    let z12 = t43 & y13;
+    let z13 = t40 & y5;
+    let z14 = t29 & y2;
+    let z15 = t42 & y9;
+    let z16 = t45 & y14;
+    let z17 = t41 & y8;
+
+    // Bottom linear transformation
+    let t46 = z15 ^ z16;
+    let t47 = z10 ^ z11;
+    let t48 = z5 ^ z13;
+    let t49 = z9 ^ z10;
+    let t50 = z2 ^ z12;
+    let t51 = z2 ^ z5;
+    let t52 = z7 ^ z8;
+    let t53 = z0 ^ z3;
+    let t54 = z6 ^ z7;
+    let t55 = z16 ^ z17;
+    let t56 = z12 ^ t48;
+    let t57 = t50 ^ t53;
+    let t58 = z4 ^ t46;
+    let t59 = z3 ^ t54;
+    let t60 = t46 ^ t57;
+    let t61 = z14 ^ t57;
+    let t62 = t52 ^ t58;
+    let t63 = t49 ^ t58;
+    let t64 = z4 ^ t59;
+    let t65 = t61 ^ t62;
+    let t66 = z1 ^ t63;
+    let s0 = t59 ^ t63;
+    let s6 = t56 ^ !t62;
+    let s7 = t48 ^ !t60;
+    let t67 = t64 ^ t65;
+    let s3 = t53 ^ t66;
+    let s4 = t51 ^ t66;
+    let s5 = t47 ^ t65;
+    let s1 = t64 ^ !s3;
+    let s2 = t55 ^ !t67;
+
+    q[7] = s0;
+    q[6] = s1;
+    q[5] = s2;
+    q[4] = s3;
+    q[3] = s4;
+    q[2] = s5;
+    q[1] = s6;
+    q[0] = s7;
+}
+
+/// Computes `A^-1(x ^ 0x63)`, where `A` is the affine map of the S-box
+fn inv_affine(q: &mut [u64; 8]) {
+    let q0 = !q[0];
+    let q1 = !q[1];
+    let q2 = q[2];
+    let q3 = q[3];
+    let q4 = q[4];
+    let q5 = !q[5];
+    let q6 = !q[6];
+    let q7 = q[7];
+    q[7] = q1 ^ q4 ^ q6;
+    q[6] = q0 ^ q3 ^ q5;
+    q[5] = q7 ^ q2 ^ q4;
+    q[4] = q6 ^ q1 ^ q3;
+    q[3] = q5 ^ q0 ^ q2;
+    q[2] = q4 ^ q7 ^ q1;
+    q[1] = q3 ^ q6 ^ q0;
+    q[0] = q2 ^ q5 ^ q7;
+}
+
+/// Inverse S-box, computed from the forward one as
+/// `InvS(y) = A^-1(S(A^-1(y ^ 0x63)) ^ 0x63)`
+fn inv_sub_bytes(q: &mut [u64; 8]) {
+    inv_affine(q);
+    sub_bytes(q);
+    inv_affine(q);
+}
+
+macro_rules! swapn {
+    ($cl:expr, $ch:expr, $s:expr, $x:expr, $y:expr) => {{
+        let a = $x;
+        let b = $y;
+        $x = (a & $cl) | ((b & $cl) << $s);
+        $y = ((a & $ch) >> $s) | (b & $ch);
+    }}
+}
+
+/// Transposes the state between the interleaved and the bitsliced
+/// representations. The transformation is an involution.
+fn ortho(q: &mut [u64; 8]) {
+    const CL2: u64 = 0x5555555555555555;
+    const CH2: u64 = 0xAAAAAAAAAAAAAAAA;
+    const CL4: u64 = 0x3333333333333333;
+    const CH4: u64 = 0xCCCCCCCCCCCCCCCC;
+    const CL8: u64 = 0x0F0F0F0F0F0F0F0F;
+    const CH8: u64 = 0xF0F0F0F0F0F0F0F0;
+
+    swapn!(CL2, CH2, 1, q[0], q[1]);
+    swapn!(CL2, CH2, 1, q[2], q[3]);
+    swapn!(CL2, CH2, 1, q[4], q[5]);
+    swapn!(CL2, CH2, 1, q[6], q[7]);
+
+    swapn!(CL4, CH4, 2, q[0], q[2]);
+    swapn!(CL4, CH4, 2, q[1], q[3]);
+    swapn!(CL4, CH4, 2, q[4], q[6]);
+    swapn!(CL4, CH4, 2, q[5], q[7]);
+
+    swapn!(CL8, CH8, 4, q[0], q[4]);
+    swapn!(CL8, CH8, 4, q[1], q[5]);
+    swapn!(CL8, CH8, 4, q[2], q[6]);
+    swapn!(CL8, CH8, 4, q[3], q[7]);
+}
+
+/// Spreads the four 32-bit words of a block over two 64-bit words
+fn interleave_in(w: &[u32]) -> (u64, u64) {
+    let mut x0 = w[0] as u64;
+    let mut x1 = w[1] as u64;
+    let mut x2 = w[2] as u64;
+    let mut x3 = w[3] as u64;
+    x0 |= x0 << 16;
+    x1 |= x1 << 16;
+    x2 |= x2 << 16;
+    x3 |= x3 << 16;
+    x0 &= 0x0000FFFF0000FFFF;
+    x1 &= 0x0000FFFF0000FFFF;
+    x2 &= 0x0000FFFF0000FFFF;
+    x3 &= 0x0000FFFF0000FFFF;
+    x0 |= x0 << 8;
+    x1 |= x1 << 8;
+    x2 |= x2 << 8;
+    x3 |= x3 << 8;
+    x0 &= 0x00FF00FF00FF00FF;
+    x1 &= 0x00FF00FF00FF00FF;
+    x2 &= 0x00FF00FF00FF00FF;
+    x3 &= 0x00FF00FF00FF00FF;
+    (x0 | (x2 << 8), x1 | (x3 << 8))
+}
+
+/// Reverse of `interleave_in`
+fn interleave_out(w: &mut [u32], q0: u64, q1: u64) {
+    let mut x0 = q0 & 0x00FF00FF00FF00FF;
+    let mut x1 = q1 & 0x00FF00FF00FF00FF;
+    let mut x2 = (q0 >> 8) & 0x00FF00FF00FF00FF;
+    let mut x3 = (q1 >> 8) & 0x00FF00FF00FF00FF;
+    x0 |= x0 >> 8;
+    x1 |= x1 >> 8;
+    x2 |= x2 >> 8;
+    x3 |= x3 >> 8;
+    x0 &= 0x0000FFFF0000FFFF;
+    x1 &= 0x0000FFFF0000FFFF;
+    x2 &= 0x0000FFFF0000FFFF;
+    x3 &= 0x0000FFFF0000FFFF;
+    w[0] = x0 as u32 | (x0 >> 16) as u32;
+    w[1] = x1 as u32 | (x1 >> 16) as u32;
+    w[2] = x2 as u32 | (x2 >> 16) as u32;
+    w[3] = x3 as u32 | (x3 >> 16) as u32;
+}
+
+/// Loads up to four 16 byte blocks into the bitsliced state. Missing
+/// blocks are filled with zeros.
+fn load_blocks(blocks: &[u8]) -> [u64; 8] {
+    assert!(blocks.len() % 16 == 0 && blocks.len() <= 64);
+    let mut q = [0u64; 8];
+    for (i, block) in blocks.chunks(16).enumerate() {
+        let w = [read_u32_le(&block[0..4]), read_u32_le(&block[4..8]),
+                 read_u32_le(&block[8..12]), read_u32_le(&block[12..16])];
+        let (q0, q1) = interleave_in(&w);
+        q[i] = q0;
+        q[i + 4] = q1;
+    }
+    ortho(&mut q);
+    q
+}
+
+/// Stores the bitsliced state into up to four 16 byte blocks
+fn store_blocks(mut q: [u64; 8], blocks: &mut [u8]) {
+    assert!(blocks.len() % 16 == 0 && blocks.len() <= 64);
+    ortho(&mut q);
+    for (i, block) in blocks.chunks_mut(16).enumerate() {
+        let mut w = [0u32; 4];
+        interleave_out(&mut w, q[i], q[i + 4]);
+        for (chunk, &x) in block.chunks_mut(4).zip(w.iter()) {
+            write_u32_le(chunk, x);
+        }
+    }
+}
+
+fn sub_word(x: u32) -> u32 {
+    let mut q = [x as u64, 0, 0, 0, 0, 0, 0, 0];
+    ortho(&mut q);
+    sub_bytes(&mut q);
+    ortho(&mut q);
+    q[0] as u32
+}
+
+const RCON: [u32; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80,
+                         0x1B, 0x36];
+
+/// Standard AES key expansion (FIPS-197, section 5.2), writing the round
+/// key words in little-endian order into `w`. The number of rounds is
+/// derived from the key length, which must be 16, 24 or 32 bytes.
+///
+/// The S-box is evaluated with the bitsliced circuit, so this is constant
+/// time as well and can be shared with the other backends.
+pub fn key_schedule(key: &[u8], w: &mut [u32]) {
+    let nk = key.len() / 4;
+    let nkf = w.len();
+    assert!(nk == 4 || nk == 6 || nk == 8);
+    assert!(nkf == (nk + 7) * 4);
+
+    for (i, chunk) in key.chunks(4).enumerate() {
+        w[i] = read_u32_le(chunk);
+    }
+
+    let mut tmp = w[nk - 1];
+    let mut j = 0;
+    let mut k = 0;
+    for i in nk..nkf {
+        if j == 0 {
+            tmp = (tmp << 24) | (tmp >> 8);
+            tmp = sub_word(tmp) ^ RCON[k];
+        } else if nk > 6 && j == 4 {
+            tmp = sub_word(tmp);
+        }
+        tmp ^= w[i - nk];
+        w[i] = tmp;
+        j += 1;
+        if j == nk {
+            j = 0;
+            k += 1;
+        }
+    }
+}
+
+/// Converts round key words produced by `key_schedule` into the bitsliced
+/// representation
+pub fn bitslice_round_keys(w: &[u32], round_keys: &mut [RoundKey]) {
+    assert!(w.len() == round_keys.len() * 4);
+    for (rk, words) in round_keys.iter_mut().zip(w.chunks(4)) {
+        let (q0, q1) = interleave_in(words);
+        let mut q = [q0, q0, q0, q0, q1, q1, q1, q1];
+        ortho(&mut q);
+        *rk = q;
+    }
+}
+
+fn add_round_key(q: &mut [u64; 8], rk: &RoundKey) {
+    for (x, k) in q.iter_mut().zip(rk.iter()) {
+        *x ^= *k;
+    }
+}
+
+fn shift_rows(q: &mut [u64; 8]) {
+    for x in q.iter_mut() {
+        let v = *x;
+        *x = (v & 0x000000000000FFFF)
+            | ((v & 0x00000000FFF00000) >> 4)
+            | ((v & 0x00000000000F0000) << 12)
+            | ((v & 0x0000FF0000000000) >> 8)
+            | ((v & 0x000000FF00000000) << 8)
+            | ((v & 0xF000000000000000) >> 12)
+            | ((v & 0x0FFF000000000000) << 4);
+    }
+}
+
+fn inv_shift_rows(q: &mut [u64; 8]) {
+    for x in q.iter_mut() {
+        let v = *x;
+        *x = (v & 0x000000000000FFFF)
+            | ((v & 0x000000000FFF0000) << 4)
+            | ((v & 0x00000000F0000000) >> 12)
+            | ((v & 0x000000FF00000000) << 8)
+            | ((v & 0x0000FF0000000000) >> 8)
+            | ((v & 0x000F000000000000) << 12)
+            | ((v & 0xFFF0000000000000) >> 4);
+    }
+}
+
+#[inline(always)]
+fn rotr32(x: u64) -> u64 { (x << 32) | (x >> 32) }
+
+#[inline(always)]
+fn rotr16(x: u64) -> u64 { (x >> 16) | (x << 48) }
+
+fn mix_columns(q: &mut [u64; 8]) {
+    let (q0, q1, q2, q3) = (q[0], q[1], q[2], q[3]);
+    let (q4, q5, q6, q7) = (q[4], q[5], q[6], q[7]);
+    let (r0, r1, r2, r3) = (rotr16(q0), rotr16(q1), rotr16(q2), rotr16(q3));
+    let (r4, r5, r6, r7) = (rotr16(q4), rotr16(q5), rotr16(q6), rotr16(q7));
+
+    q[0] = q7 ^ r7 ^ r0 ^ rotr32(q0 ^ r0);
+    q[1] = q0 ^ r0 ^ q7 ^ r7 ^ r1 ^ rotr32(q1 ^ r1);
+    q[2] = q1 ^ r1 ^ r2 ^ rotr32(q2 ^ r2);
+    q[3] = q2 ^ r2 ^ q7 ^ r7 ^ r3 ^ rotr32(q3 ^ r3);
+    q[4] = q3 ^ r3 ^ q7 ^ r7 ^ r4 ^ rotr32(q4 ^ r4);
+    q[5] = q4 ^ r4 ^ r5 ^ rotr32(q5 ^ r5);
+    q[6] = q5 ^ r5 ^ r6 ^ rotr32(q6 ^ r6);
+    q[7] = q6 ^ r6 ^ r7 ^ rotr32(q7 ^ r7);
+}
+
+fn inv_mix_columns(q: &mut [u64; 8]) {
+    let (q0, q1, q2, q3) = (q[0], q[1], q[2], q[3]);
+    let (q4, q5, q6, q7) = (q[4], q[5], q[6], q[7]);
+    let (r0, r1, r2, r3) = (rotr16(q0), rotr16(q1), rotr16(q2), rotr16(q3));
+    let (r4, r5, r6, r7) = (rotr16(q4), rotr16(q5), rotr16(q6), rotr16(q7));
+
+    q[0] = q5 ^ q6 ^ q7 ^ r0 ^ r5 ^ r7
+        ^ rotr32(q0 ^ q5 ^ q6 ^ r0 ^ r5);
+    q[1] = q0 ^ q5 ^ r0 ^ r1 ^ r5 ^ r6 ^ r7
+        ^ rotr32(q1 ^ q5 ^ q7 ^ r1 ^ r5 ^ r6);
+    q[2] = q0 ^ q1 ^ q6 ^ r1 ^ r2 ^ r6 ^ r7
+        ^ rotr32(q0 ^ q2 ^ q6 ^ r2 ^ r6 ^ r7);
+    q[3] = q0 ^ q1 ^ q2 ^ q5 ^ q6 ^ r0 ^ r2 ^ r3 ^ r5
+        ^ rotr32(q0 ^ q1 ^ q3 ^ q5 ^ q6 ^ q7 ^ r0 ^ r3 ^ r5 ^ r7);
+    q[4] = q1 ^ q2 ^ q3 ^ q5 ^ r1 ^ r3 ^ r4 ^ r5 ^ r6 ^ r7
+        ^ rotr32(q1 ^ q2 ^ q4 ^ q5 ^ q7 ^ r1 ^ r4 ^ r5 ^ r6);
+    q[5] = q2 ^ q3 ^ q4 ^ q6 ^ r2 ^ r4 ^ r5 ^ r6 ^ r7
+        ^ rotr32(q2 ^ q3 ^ q5 ^ q6 ^ r2 ^ r5 ^ r6 ^ r7);
+    q[6] = q3 ^ q4 ^ q5 ^ q7 ^ r3 ^ r5 ^ r6 ^ r7
+        ^ rotr32(q3 ^ q4 ^ q6 ^ q7 ^ r3 ^ r6 ^ r7);
+    q[7] = q4 ^ q5 ^ q6 ^ r4 ^ r6 ^ r7
+        ^ rotr32(q4 ^ q5 ^ q7 ^ r4 ^ r7);
+}
+
+/// Encrypts up to four blocks in place
+pub fn encrypt(round_keys: &[RoundKey], blocks: &mut [u8]) {
+    let rounds = round_keys.len() - 1;
+    let mut q = load_blocks(blocks);
+    add_round_key(&mut q, &round_keys[0]);
+    for rk in round_keys[1..rounds].iter() {
+        sub_bytes(&mut q);
+        shift_rows(&mut q);
+        mix_columns(&mut q);
+        add_round_key(&mut q, rk);
+    }
+    sub_bytes(&mut q);
+    shift_rows(&mut q);
+    add_round_key(&mut q, &round_keys[rounds]);
+    store_blocks(q, blocks);
+}
+
+/// Decrypts up to four blocks in place
+pub fn decrypt(round_keys: &[RoundKey], blocks: &mut [u8]) {
+    let rounds = round_keys.len() - 1;
+    let mut q = load_blocks(blocks);
+    add_round_key(&mut q, &round_keys[rounds]);
+    for rk in round_keys[1..rounds].iter().rev() {
+        inv_shift_rows(&mut q);
+        inv_sub_bytes(&mut q);
+        add_round_key(&mut q, rk);
+        inv_mix_columns(&mut q);
+    }
+    inv_shift_rows(&mut q);
+    inv_sub_bytes(&mut q);
+    add_round_key(&mut q, &round_keys[0]);
+    store_blocks(q, blocks);
+}
+
+/// Applies SubBytes, ShiftRows and MixColumns to up to four blocks in
+/// place, i.e. a full encryption round without the key addition
+pub fn round(blocks: &mut [u8]) {
+    let mut q = load_blocks(blocks);
+    sub_bytes(&mut q);
+    shift_rows(&mut q);
+    mix_columns(&mut q);
+    store_blocks(q, blocks);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sub_bytes, inv_sub_bytes, load_blocks, store_blocks};
+
+    // The S-box from FIPS-197, figure 7
+    const SBOX: [u8; 256] = [
+        0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5,
+        0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+        0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0,
+        0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+        0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc,
+        0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+        0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a,
+        0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+        0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0,
+        0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+        0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b,
+        0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+        0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85,
+        0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+        0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5,
+        0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+        0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17,
+        0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+        0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88,
+        0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+        0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c,
+        0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+        0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9,
+        0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+        0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6,
+        0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+        0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e,
+        0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+        0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94,
+        0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+        0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68,
+        0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+    ];
+
+    #[test]
+    fn sbox() {
+        let mut input = [0u8; 64];
+        let mut output = [0u8; 64];
+        for chunk in 0..4 {
+            for (i, b) in input.iter_mut().enumerate() {
+                *b = (chunk * 64 + i) as u8;
+            }
+            let mut q = load_blocks(&input);
+            sub_bytes(&mut q);
+            store_blocks(q, &mut output);
+            for (&x, &y) in input.iter().zip(output.iter()) {
+                assert_eq!(SBOX[x as usize], y);
+            }
+
+            let mut q = load_blocks(&output);
+            inv_sub_bytes(&mut q);
+            store_blocks(q, &mut output);
+            assert_eq!(&input[..], &output[..]);
+        }
+    }
+}
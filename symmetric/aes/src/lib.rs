@@ -0,0 +1,188 @@
+//! This is an implementation of the AES block cipher (FIPS-197) with 128,
+//! 192 and 256-bit keys.
+//!
+//! On x86_64 processors supporting AES-NI (detected at runtime with CPUID
+//! when the key is set up) the dedicated instructions are used. Otherwise a
+//! bitsliced representation of the cipher state is used, so that no table
+//! lookups or branches depend on the key or the data, which makes it
+//! resistant to cache-timing attacks.
+//!
+//! Both backends benefit from processing several blocks at once (the
+//! bitsliced core always works on four, AES-NI interleaves eight), so
+//! `BlockEncryptorX8`/`BlockDecryptorX8` should be preferred over single
+//! block operations when several blocks are available (e.g. in CTR mode).
+//!
+//! A single encryption round is exposed as `aes_round`, for constructions
+//! such as AEGIS and Deoxys which use the AES round function as a building
+//! block.
+
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate generic_array;
+extern crate crypto_bytes;
+extern crate crypto_symmetric;
+
+use generic_array::typenum::U16;
+use crypto_symmetric::{BlockCipher, Block128, BlockEncryptorX8,
+                       BlockDecryptorX8};
+
+mod bitslice;
+use bitslice::RoundKey;
+
+#[cfg(target_arch = "x86_64")]
+mod ni;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::__m128i;
+
+macro_rules! define_aes {
+    ($name:ident, $backend:ident, $key_size:expr, $rounds:expr,
+     $doc:expr) => {
+        #[derive(Clone, Copy)]
+        enum $backend {
+            Soft([RoundKey; $rounds + 1]),
+            #[cfg(target_arch = "x86_64")]
+            Ni([__m128i; $rounds + 1], [__m128i; $rounds + 1]),
+        }
+
+        #[doc=$doc]
+        #[derive(Clone, Copy)]
+        pub struct $name {
+            backend: $backend,
+        }
+
+        impl $name {
+            /// Uses AES-NI if the processor supports it and the bitsliced
+            /// implementation otherwise
+            pub fn new(key: &[u8]) -> $name {
+                #[cfg(target_arch = "x86_64")]
+                {
+                    if ni::is_supported() {
+                        assert!(key.len() == $key_size);
+                        let mut w = [0u32; ($rounds + 1) * 4];
+                        bitslice::key_schedule(key, &mut w);
+                        let mut enc_keys = [ni::zero(); $rounds + 1];
+                        let mut dec_keys = [ni::zero(); $rounds + 1];
+                        ni::expand_key(&w, &mut enc_keys, &mut dec_keys);
+                        return $name {
+                            backend: $backend::Ni(enc_keys, dec_keys),
+                        };
+                    }
+                }
+                $name::new_soft(key)
+            }
+
+            fn new_soft(key: &[u8]) -> $name {
+                assert!(key.len() == $key_size);
+                let mut w = [0u32; ($rounds + 1) * 4];
+                bitslice::key_schedule(key, &mut w);
+                let mut round_keys = [[0u64; 8]; $rounds + 1];
+                bitslice::bitslice_round_keys(&w, &mut round_keys);
+                $name { backend: $backend::Soft(round_keys) }
+            }
+
+            fn encrypt_blocks(&self, blocks: &mut [u8]) {
+                match self.backend {
+                    $backend::Soft(ref round_keys) => {
+                        for chunk in blocks.chunks_mut(64) {
+                            bitslice::encrypt(round_keys, chunk);
+                        }
+                    }
+                    #[cfg(target_arch = "x86_64")]
+                    $backend::Ni(ref enc_keys, _) => {
+                        ni::encrypt(enc_keys, blocks);
+                    }
+                }
+            }
+
+            fn decrypt_blocks(&self, blocks: &mut [u8]) {
+                match self.backend {
+                    $backend::Soft(ref round_keys) => {
+                        for chunk in blocks.chunks_mut(64) {
+                            bitslice::decrypt(round_keys, chunk);
+                        }
+                    }
+                    #[cfg(target_arch = "x86_64")]
+                    $backend::Ni(_, ref dec_keys) => {
+                        ni::decrypt(dec_keys, blocks);
+                    }
+                }
+            }
+        }
+
+        impl BlockCipher for $name {
+            type BlockSize = U16;
+
+            fn encrypt_block(&self, input: &Block128, output: &mut Block128) {
+                output.copy_from_slice(input);
+                self.encrypt_blocks(output);
+            }
+
+            fn decrypt_block(&self, input: &Block128, output: &mut Block128) {
+                output.copy_from_slice(input);
+                self.decrypt_blocks(output);
+            }
+        }
+
+        impl BlockEncryptorX8 for $name {
+            fn block_size(&self) -> usize { 16 }
+
+            fn encrypt_block_x8(&self, input: &[u8], output: &mut [u8]) {
+                assert!(input.len() == 128 && output.len() == 128);
+                output.copy_from_slice(input);
+                self.encrypt_blocks(output);
+            }
+        }
+
+        impl BlockDecryptorX8 for $name {
+            fn block_size(&self) -> usize { 16 }
+
+            fn decrypt_block_x8(&self, input: &[u8], output: &mut [u8]) {
+                assert!(input.len() == 128 && output.len() == 128);
+                output.copy_from_slice(input);
+                self.decrypt_blocks(output);
+            }
+        }
+    }
+}
+
+define_aes!(Aes128, Aes128Backend, 16, 10,
+            "AES block cipher with a 128-bit key");
+define_aes!(Aes192, Aes192Backend, 24, 12,
+            "AES block cipher with a 192-bit key");
+define_aes!(Aes256, Aes256Backend, 32, 14,
+            "AES block cipher with a 256-bit key");
+
+/// Applies one AES encryption round (SubBytes, ShiftRows, MixColumns and
+/// AddRoundKey) to every 16 byte block of `blocks`, using the block at the
+/// same position in `round_keys` as the round key. This matches the x86
+/// `aesenc` instruction, which is used when available.
+///
+/// Rounds on several blocks at once are cheaper with either backend, the
+/// bitsliced one processes four blocks per call.
+pub fn aes_round(blocks: &mut [u8], round_keys: &[u8]) {
+    assert!(blocks.len() % 16 == 0 && blocks.len() == round_keys.len());
+    #[cfg(target_arch = "x86_64")]
+    {
+        if ni::is_supported() {
+            ni::round(blocks, round_keys);
+            return;
+        }
+    }
+    aes_round_soft(blocks, round_keys);
+}
+
+fn aes_round_soft(blocks: &mut [u8], round_keys: &[u8]) {
+    for chunk in blocks.chunks_mut(64) {
+        bitslice::round(chunk);
+    }
+    for (b, k) in blocks.iter_mut().zip(round_keys.iter()) {
+        *b ^= *k;
+    }
+}
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod bench;
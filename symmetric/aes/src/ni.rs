@@ -0,0 +1,145 @@
+//! AES implementation using the AES-NI instruction set extension of x86_64
+//! processors.
+//!
+//! The AES instructions run in constant time, so like the bitsliced core
+//! this backend is not vulnerable to cache-timing attacks. Each `aesenc`
+//! instruction has a latency of several cycles but can be issued every
+//! cycle, so eight independent blocks are interleaved to keep the pipeline
+//! busy.
+
+use core::arch::x86_64::*;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+static SUPPORTED: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns `true` if the processor supports the AES-NI instructions. SSE2
+/// is part of the x86_64 baseline, so only the AES bit is checked.
+pub fn is_supported() -> bool {
+    // Executing CPUID for every new key is costly under virtualization,
+    // remember the answer (1 = no, 2 = yes)
+    match SUPPORTED.load(Ordering::Relaxed) {
+        1 => false,
+        2 => true,
+        _ => {
+            // CPUID leaf 1, ECX bit 25
+            let info = unsafe { __cpuid(1) };
+            let supported = info.ecx & (1 << 25) != 0;
+            SUPPORTED.store(if supported { 2 } else { 1 }, Ordering::Relaxed);
+            supported
+        }
+    }
+}
+
+/// Initial value for round key arrays
+pub fn zero() -> __m128i {
+    unsafe { _mm_setzero_si128() }
+}
+
+/// Loads the round key words produced by `bitslice::key_schedule` into
+/// `enc_keys`, and derives the keys for the equivalent inverse cipher into
+/// `dec_keys`.
+///
+/// Must only be called if `is_supported` returned `true`.
+pub fn expand_key(w: &[u32], enc_keys: &mut [__m128i],
+                  dec_keys: &mut [__m128i]) {
+    assert!(w.len() == enc_keys.len() * 4);
+    assert!(enc_keys.len() == dec_keys.len());
+    unsafe { expand_key_impl(w, enc_keys, dec_keys) }
+}
+
+#[target_feature(enable = "aes")]
+unsafe fn expand_key_impl(w: &[u32], enc_keys: &mut [__m128i],
+                          dec_keys: &mut [__m128i]) {
+    let rounds = enc_keys.len() - 1;
+    for (k, words) in enc_keys.iter_mut().zip(w.chunks(4)) {
+        *k = _mm_set_epi32(words[3] as i32, words[2] as i32,
+                           words[1] as i32, words[0] as i32);
+    }
+
+    dec_keys[0] = enc_keys[rounds];
+    for i in 1..rounds {
+        dec_keys[i] = _mm_aesimc_si128(enc_keys[rounds - i]);
+    }
+    dec_keys[rounds] = enc_keys[0];
+}
+
+/// Encrypts the 16 byte blocks of `blocks` in place with the round keys
+/// from `expand_key`. Must only be called if `is_supported` returned `true`.
+pub fn encrypt(enc_keys: &[__m128i], blocks: &mut [u8]) {
+    assert!(blocks.len() % 16 == 0);
+    unsafe { encrypt_impl(enc_keys, blocks) }
+}
+
+/// Decrypts the 16 byte blocks of `blocks` in place with the inverse round
+/// keys from `expand_key`. Must only be called if `is_supported` returned
+/// `true`.
+pub fn decrypt(dec_keys: &[__m128i], blocks: &mut [u8]) {
+    assert!(blocks.len() % 16 == 0);
+    unsafe { decrypt_impl(dec_keys, blocks) }
+}
+
+/// Applies the `aesenc` instruction to each block of `blocks`, with the
+/// round key at the same position in `round_keys`. Must only be called if
+/// `is_supported` returned `true`.
+pub fn round(blocks: &mut [u8], round_keys: &[u8]) {
+    assert!(blocks.len() % 16 == 0 && blocks.len() == round_keys.len());
+    unsafe { round_impl(blocks, round_keys) }
+}
+
+#[target_feature(enable = "aes")]
+unsafe fn round_impl(blocks: &mut [u8], round_keys: &[u8]) {
+    let chunks = blocks.chunks_mut(16).zip(round_keys.chunks(16));
+    for (block, key) in chunks {
+        let p = block.as_mut_ptr() as *mut __m128i;
+        let k = _mm_loadu_si128(key.as_ptr() as *const __m128i);
+        _mm_storeu_si128(p, _mm_aesenc_si128(_mm_loadu_si128(p), k));
+    }
+}
+
+macro_rules! process_blocks {
+    ($keys:expr, $blocks:expr, $round:ident, $last:ident) => {{
+        let keys = $keys;
+        let rounds = keys.len() - 1;
+
+        for chunk in $blocks.chunks_mut(128) {
+            if chunk.len() < 128 {
+                // Fewer than eight blocks are left, process them one by one
+                for block in chunk.chunks_mut(16) {
+                    let p = block.as_mut_ptr() as *mut __m128i;
+                    let mut x = _mm_xor_si128(_mm_loadu_si128(p), keys[0]);
+                    for k in &keys[1..rounds] {
+                        x = $round(x, *k);
+                    }
+                    _mm_storeu_si128(p, $last(x, keys[rounds]));
+                }
+                continue;
+            }
+
+            let p = chunk.as_mut_ptr() as *mut __m128i;
+            let mut x = [_mm_setzero_si128(); 8];
+            for i in 0..8 {
+                x[i] = _mm_xor_si128(_mm_loadu_si128(p.offset(i as isize)),
+                                     keys[0]);
+            }
+            for k in &keys[1..rounds] {
+                for i in 0..8 {
+                    x[i] = $round(x[i], *k);
+                }
+            }
+            for i in 0..8 {
+                _mm_storeu_si128(p.offset(i as isize),
+                                 $last(x[i], keys[rounds]));
+            }
+        }
+    }}
+}
+
+#[target_feature(enable = "aes")]
+unsafe fn encrypt_impl(keys: &[__m128i], blocks: &mut [u8]) {
+    process_blocks!(keys, blocks, _mm_aesenc_si128, _mm_aesenclast_si128);
+}
+
+#[target_feature(enable = "aes")]
+unsafe fn decrypt_impl(keys: &[__m128i], blocks: &mut [u8]) {
+    process_blocks!(keys, blocks, _mm_aesdec_si128, _mm_aesdeclast_si128);
+}
@@ -0,0 +1,127 @@
+use super::{Aes128, Aes192, Aes256, aes_round, aes_round_soft};
+use generic_array::GenericArray;
+use crypto_symmetric::{BlockCipher, BlockEncryptorX8, BlockDecryptorX8};
+
+struct Test {
+    pub name: &'static str,
+    pub key: &'static [u8],
+    pub input: &'static [u8],
+    pub output: &'static [u8],
+}
+
+// Every file contains a concatenation of keys, plaintext blocks and
+// ciphertext blocks respectively, one per known answer.
+macro_rules! get_tests {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            Test {
+                name: $name,
+                key: include_bytes!(concat!("data/", $name, ".key.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                output: include_bytes!(concat!("data/", $name, ".output.bin")),
+            },
+        )*]
+    };
+}
+
+macro_rules! check_block {
+    ($cipher:ident, $key_size:expr, $tests:expr) => {
+        for test in $tests.iter() {
+            let keys = test.key.chunks($key_size);
+            let inputs = test.input.chunks(16);
+            let outputs = test.output.chunks(16);
+            for ((key, input), output) in keys.zip(inputs).zip(outputs) {
+                // Check the software fallback even if AES-NI is available
+                for cipher in [$cipher::new(key), $cipher::new_soft(key)].iter() {
+                    let mut buf = GenericArray::new();
+                    let mut block = GenericArray::new();
+
+                    block.copy_from_slice(input);
+                    cipher.encrypt_block(&block, &mut buf);
+                    assert_eq!(&buf[..], output);
+
+                    block.copy_from_slice(output);
+                    cipher.decrypt_block(&block, &mut buf);
+                    assert_eq!(&buf[..], input);
+                }
+            }
+        }
+    }
+}
+
+macro_rules! check_x8 {
+    ($cipher:ident, $tests:expr) => {
+        // Every known answer of a set shares the same key
+        for test in $tests.iter() {
+            let key = &test.key[..test.key.len() / 128];
+            for cipher in [$cipher::new(key), $cipher::new_soft(key)].iter() {
+                let inputs = test.input.chunks(128);
+                let outputs = test.output.chunks(128);
+                let mut buf = [0u8; 128];
+                for (input, output) in inputs.zip(outputs) {
+                    cipher.encrypt_block_x8(input, &mut buf);
+                    assert_eq!(&buf[..], output);
+                    cipher.decrypt_block_x8(output, &mut buf);
+                    assert_eq!(&buf[..], input);
+                }
+            }
+        }
+    }
+}
+
+// FIPS-197, appendix C
+#[test]
+fn aes_fips197() {
+    check_block!(Aes128, 16, get_tests!("fips197_128"));
+    check_block!(Aes192, 24, get_tests!("fips197_192"));
+    check_block!(Aes256, 32, get_tests!("fips197_256"));
+}
+
+// NIST AESAVS, appendices B-E
+#[test]
+fn aes128_aesavs() {
+    check_block!(Aes128, 16, get_tests!("gfsbox128", "vartxt128", "varkey128"));
+}
+
+#[test]
+fn aes192_aesavs() {
+    check_block!(Aes192, 24, get_tests!("vartxt192", "varkey192"));
+}
+
+#[test]
+fn aes256_aesavs() {
+    check_block!(Aes256, 32, get_tests!("vartxt256", "varkey256"));
+}
+
+#[test]
+fn aes_x8() {
+    check_x8!(Aes128, get_tests!("vartxt128"));
+    check_x8!(Aes192, get_tests!("vartxt192"));
+    check_x8!(Aes256, get_tests!("vartxt256"));
+}
+
+#[test]
+fn aes_round_function() {
+    // The AESRound test vector of draft-irtf-cfrg-aegis-aead, repeated in
+    // five blocks so that the bitsliced core also handles a partial batch
+    let mut input = [0u8; 80];
+    let mut round_keys = [0u8; 80];
+    for (i, (b, k)) in input.iter_mut().zip(round_keys.iter_mut())
+                            .enumerate() {
+        *b = (i % 16) as u8;
+        *k = (i % 16) as u8 + 0x10;
+    }
+    let expected = [0x7a, 0x7b, 0x4e, 0x56, 0x38, 0x78, 0x25, 0x46,
+                    0xa8, 0xc0, 0x47, 0x7a, 0x3b, 0x81, 0x3f, 0x43];
+
+    let mut blocks = input;
+    aes_round(&mut blocks, &round_keys);
+    for block in blocks.chunks(16) {
+        assert_eq!(block, &expected[..]);
+    }
+    let mut blocks = input;
+    aes_round_soft(&mut blocks, &round_keys);
+    for block in blocks.chunks(16) {
+        assert_eq!(block, &expected[..]);
+    }
+}
@@ -0,0 +1,42 @@
+use test::Bencher;
+use crypto_aead::{Aead, NewAead};
+use crypto_digest::Digest;
+use super::{AsconAead128, AsconHash256};
+
+#[bench]
+pub fn ascon_aead128_10(bh: & mut Bencher) {
+    let input = [1u8; 10];
+    let aad = [3u8; 10];
+    let c = AsconAead128::new(&[0; 16]).unwrap();
+    bh.iter( || {
+        let mut output = [0u8; 10];
+        let mut tag = [0u8; 16];
+        c.encrypt(&[0; 16], &aad, &input, &mut output, &mut tag).unwrap();
+    });
+    bh.bytes = 10u64;
+}
+
+#[bench]
+pub fn ascon_aead128_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let aad = [3u8; 1024];
+    let c = AsconAead128::new(&[0; 16]).unwrap();
+    bh.iter( || {
+        let mut output = [0u8; 1024];
+        let mut tag = [0u8; 16];
+        c.encrypt(&[0; 16], &aad, &input, &mut output, &mut tag).unwrap();
+    });
+    bh.bytes = 1024u64;
+}
+
+#[bench]
+pub fn ascon_hash256_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let mut output = [0u8; 32];
+    bh.iter( || {
+        let mut h = AsconHash256::new();
+        h.input(&input);
+        h.result(&mut output);
+    });
+    bh.bytes = 1024u64;
+}
@@ -0,0 +1,356 @@
+//! This is an implementation of the Ascon family as standardized in NIST
+//! SP 800-232 [1]: the Ascon-AEAD128 authenticated cipher, the Ascon-Hash256
+//! hash function and the Ascon-XOF128 extendable output function.
+//!
+//! All three are sponge constructions over the same 320-bit permutation,
+//! made of a bitsliced 5-bit S-box and XORs of rotated 64-bit words. It has
+//! no tables and no secret-dependent branches, and is much smaller and faster
+//! than AES in software on 32 and 64-bit microcontrollers.
+//!
+//! Ascon-AEAD128 takes a 128-bit key and a 128-bit nonce and produces a
+//! 128-bit tag. A nonce must never be reused with the same key. The hash
+//! functions absorb 64 bits per call of the permutation, Ascon-Hash256 gives
+//! a 256-bit digest and Ascon-XOF128 an output of any length.
+//!
+//! Words are read from and written to byte strings in little-endian order,
+//! as in the final standard. The earlier Ascon-128/128a submission used
+//! big-endian words and is not compatible.
+//!
+//! [1] - "Ascon-Based Lightweight Cryptography Standards for Constrained
+//!       Devices" <https://doi.org/10.6028/NIST.SP.800-232>
+
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate generic_array;
+extern crate crypto_aead;
+extern crate crypto_bytes;
+extern crate crypto_digest;
+extern crate crypto_ops;
+
+use generic_array::GenericArray;
+use generic_array::typenum::U16;
+use crypto_aead::{Aead, NewAead, AeadError, Tag};
+use crypto_bytes::{read_u64_le, write_u64_le};
+use crypto_digest::Digest;
+use crypto_ops::fixed_time_eq;
+
+const AEAD128_IV: u64 = 0x00001000808c0001;
+const HASH256_IV: u64 = 0x0000080100cc0002;
+const XOF128_IV: u64 = 0x0000080000cc0003;
+
+/// Round constants, the last `n` are used by the `n`-round permutation
+const ROUND_CONSTANTS: [u64; 12] = [0xf0, 0xe1, 0xd2, 0xc3, 0xb4, 0xa5, 0x96,
+                                    0x87, 0x78, 0x69, 0x5a, 0x4b];
+
+/// The 320-bit state as five 64-bit words
+#[derive(Clone, Copy)]
+struct State([u64; 5]);
+
+impl State {
+    /// The Ascon permutation with the given number of rounds
+    fn permute(&mut self, rounds: usize) {
+        let [mut x0, mut x1, mut x2, mut x3, mut x4] = self.0;
+        for &c in ROUND_CONSTANTS[12 - rounds..].iter() {
+            // Constant addition
+            x2 ^= c;
+
+            // Substitution layer
+            x0 ^= x4;
+            x4 ^= x3;
+            x2 ^= x1;
+            let t0 = !x0 & x1;
+            let t1 = !x1 & x2;
+            let t2 = !x2 & x3;
+            let t3 = !x3 & x4;
+            let t4 = !x4 & x0;
+            x0 ^= t1;
+            x1 ^= t2;
+            x2 ^= t3;
+            x3 ^= t4;
+            x4 ^= t0;
+            x1 ^= x0;
+            x0 ^= x4;
+            x3 ^= x2;
+            x2 = !x2;
+
+            // Linear diffusion layer
+            x0 ^= x0.rotate_right(19) ^ x0.rotate_right(28);
+            x1 ^= x1.rotate_right(61) ^ x1.rotate_right(39);
+            x2 ^= x2.rotate_right(1) ^ x2.rotate_right(6);
+            x3 ^= x3.rotate_right(10) ^ x3.rotate_right(17);
+            x4 ^= x4.rotate_right(7) ^ x4.rotate_right(41);
+        }
+        self.0 = [x0, x1, x2, x3, x4];
+    }
+
+    /// XORs the padding byte 0x01 right after the first `len` bytes of the
+    /// rate
+    fn pad(&mut self, len: usize) {
+        self.0[len / 8] ^= 1 << (8 * (len % 8));
+    }
+}
+
+/// Ascon-AEAD128, with 128-bit keys, nonces and tags
+#[derive(Clone, Copy)]
+pub struct AsconAead128 {
+    key: [u64; 2],
+}
+
+impl NewAead for AsconAead128 {
+    fn new(key: &[u8]) -> Result<AsconAead128, AeadError> {
+        if key.len() != 16 {
+            return Err(AeadError::InvalidKeyLength);
+        }
+        let key = [read_u64_le(&key[..8]), read_u64_le(&key[8..])];
+        Ok(AsconAead128 { key: key })
+    }
+}
+
+impl AsconAead128 {
+    /// Initializes the state with the key and nonce, and absorbs the AAD.
+    /// The rate is 128 bits, the first two words of the state.
+    fn start(&self, nonce: &[u8], aad: &[u8]) -> Result<State, AeadError> {
+        if nonce.len() != 16 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+        let mut state = State([AEAD128_IV, self.key[0], self.key[1],
+                               read_u64_le(&nonce[..8]),
+                               read_u64_le(&nonce[8..])]);
+        state.permute(12);
+        state.0[3] ^= self.key[0];
+        state.0[4] ^= self.key[1];
+
+        // Empty AAD is skipped, otherwise it is always padded, so a full
+        // last block is followed by a block of padding
+        if aad.len() > 0 {
+            let (blocks, rest) = aad.split_at(aad.len() - aad.len() % 16);
+            for block in blocks.chunks(16) {
+                state.0[0] ^= read_u64_le(&block[..8]);
+                state.0[1] ^= read_u64_le(&block[8..]);
+                state.permute(8);
+            }
+            let mut last = [0u8; 16];
+            last[..rest.len()].copy_from_slice(rest);
+            state.0[0] ^= read_u64_le(&last[..8]);
+            state.0[1] ^= read_u64_le(&last[8..]);
+            state.pad(rest.len());
+            state.permute(8);
+        }
+
+        // Domain separation between the AAD and the message
+        state.0[4] ^= 1 << 63;
+        Ok(state)
+    }
+
+    fn finish(&self, mut state: State) -> [u8; 16] {
+        state.0[2] ^= self.key[0];
+        state.0[3] ^= self.key[1];
+        state.permute(12);
+        let mut tag = [0u8; 16];
+        write_u64_le(&mut tag[..8], state.0[3] ^ self.key[0]);
+        write_u64_le(&mut tag[8..], state.0[4] ^ self.key[1]);
+        tag
+    }
+}
+
+impl Aead for AsconAead128 {
+    type TagSize = U16;
+
+    fn encrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8])
+                                 -> Result<Tag<U16>, AeadError> {
+        let mut state = self.start(nonce, aad)?;
+        let full = buf.len() - buf.len() % 16;
+        let (blocks, rest) = buf.split_at_mut(full);
+        for block in blocks.chunks_mut(16) {
+            state.0[0] ^= read_u64_le(&block[..8]);
+            state.0[1] ^= read_u64_le(&block[8..]);
+            write_u64_le(&mut block[..8], state.0[0]);
+            write_u64_le(&mut block[8..], state.0[1]);
+            state.permute(8);
+        }
+
+        let mut last = [0u8; 16];
+        last[..rest.len()].copy_from_slice(rest);
+        state.0[0] ^= read_u64_le(&last[..8]);
+        state.0[1] ^= read_u64_le(&last[8..]);
+        write_u64_le(&mut last[..8], state.0[0]);
+        write_u64_le(&mut last[8..], state.0[1]);
+        rest.copy_from_slice(&last[..rest.len()]);
+        state.pad(rest.len());
+
+        let mut tag = GenericArray::new();
+        tag.copy_from_slice(&self.finish(state));
+        Ok(tag)
+    }
+
+    fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8], tag: &[u8])
+                                 -> Result<(), AeadError> {
+        if tag.len() != 16 {
+            return Err(AeadError::InvalidLength);
+        }
+        let mut state = self.start(nonce, aad)?;
+        let full = buf.len() - buf.len() % 16;
+        let (blocks, rest) = buf.split_at_mut(full);
+        for block in blocks.chunks_mut(16) {
+            let c0 = read_u64_le(&block[..8]);
+            let c1 = read_u64_le(&block[8..]);
+            write_u64_le(&mut block[..8], state.0[0] ^ c0);
+            write_u64_le(&mut block[8..], state.0[1] ^ c1);
+            state.0[0] = c0;
+            state.0[1] = c1;
+            state.permute(8);
+        }
+
+        // The ciphertext replaces the first bytes of the rate, the others
+        // are kept
+        let mut last = [0u8; 16];
+        write_u64_le(&mut last[..8], state.0[0]);
+        write_u64_le(&mut last[8..], state.0[1]);
+        for (l, b) in last.iter_mut().zip(rest.iter_mut()) {
+            let c = *b;
+            *b ^= *l;
+            *l = c;
+        }
+        state.0[0] = read_u64_le(&last[..8]);
+        state.0[1] = read_u64_le(&last[8..]);
+        state.pad(rest.len());
+
+        if fixed_time_eq(&self.finish(state), tag) {
+            Ok(())
+        } else {
+            for b in buf.iter_mut() {
+                *b = 0;
+            }
+            Err(AeadError::AuthenticationFailed)
+        }
+    }
+}
+
+/// The sponge shared by the hash and the XOF, with a rate of 64 bits
+#[derive(Clone, Copy)]
+struct Sponge {
+    state: State,
+    iv: u64,
+    buf: [u8; 8],
+    pos: usize,
+}
+
+impl Sponge {
+    fn new(iv: u64) -> Sponge {
+        let mut state = State([iv, 0, 0, 0, 0]);
+        state.permute(12);
+        Sponge { state: state, iv: iv, buf: [0u8; 8], pos: 0 }
+    }
+
+    fn input(&mut self, mut data: &[u8]) {
+        while data.len() > 0 {
+            let n = core::cmp::min(8 - self.pos, data.len());
+            self.buf[self.pos..self.pos + n].copy_from_slice(&data[..n]);
+            self.pos += n;
+            data = &data[n..];
+            if self.pos == 8 {
+                self.state.0[0] ^= read_u64_le(&self.buf);
+                self.state.permute(12);
+                self.pos = 0;
+            }
+        }
+    }
+
+    /// Pads the message and fills `output`, the state is left unchanged
+    fn squeeze(&self, output: &mut [u8]) {
+        let mut state = self.state;
+        let mut last = [0u8; 8];
+        last[..self.pos].copy_from_slice(&self.buf[..self.pos]);
+        state.0[0] ^= read_u64_le(&last);
+        state.pad(self.pos);
+        state.permute(12);
+
+        let mut block = [0u8; 8];
+        for (i, chunk) in output.chunks_mut(8).enumerate() {
+            if i > 0 {
+                state.permute(12);
+            }
+            write_u64_le(&mut block, state.0[0]);
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Sponge::new(self.iv);
+    }
+}
+
+/// Ascon-Hash256, with a 256-bit digest
+#[derive(Clone, Copy)]
+pub struct AsconHash256 {
+    sponge: Sponge,
+}
+
+impl AsconHash256 {
+    pub fn new() -> AsconHash256 {
+        AsconHash256 { sponge: Sponge::new(HASH256_IV) }
+    }
+}
+
+impl Default for AsconHash256 {
+    fn default() -> AsconHash256 { AsconHash256::new() }
+}
+
+impl Digest for AsconHash256 {
+    fn input(&mut self, input: &[u8]) { self.sponge.input(input); }
+
+    /// Writes the digest of the data fed so far, more data can be added
+    /// afterwards
+    fn result(&mut self, out: &mut [u8]) {
+        self.sponge.squeeze(&mut out[..32]);
+    }
+
+    fn reset(&mut self) { self.sponge.reset(); }
+
+    fn output_bits(&self) -> usize { 256 }
+
+    fn block_size(&self) -> usize { 8 }
+}
+
+/// Ascon-XOF128. `result` fills its whole output buffer, whatever its
+/// length; `output_bits` reports 256, the length giving the full 128-bit
+/// security level.
+#[derive(Clone, Copy)]
+pub struct AsconXof128 {
+    sponge: Sponge,
+}
+
+impl AsconXof128 {
+    pub fn new() -> AsconXof128 {
+        AsconXof128 { sponge: Sponge::new(XOF128_IV) }
+    }
+}
+
+impl Default for AsconXof128 {
+    fn default() -> AsconXof128 { AsconXof128::new() }
+}
+
+impl Digest for AsconXof128 {
+    fn input(&mut self, input: &[u8]) { self.sponge.input(input); }
+
+    /// Writes `out.len()` bytes of output for the data fed so far, a shorter
+    /// output is a prefix of a longer one
+    fn result(&mut self, out: &mut [u8]) {
+        self.sponge.squeeze(out);
+    }
+
+    fn reset(&mut self) { self.sponge.reset(); }
+
+    fn output_bits(&self) -> usize { 256 }
+
+    fn block_size(&self) -> usize { 8 }
+}
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod bench;
@@ -0,0 +1,155 @@
+use crypto_aead::{Aead, NewAead, AeadError};
+use crypto_digest::Digest;
+use super::{AsconAead128, AsconHash256, AsconXof128};
+
+// The KAT files of the reference implementation, with the outputs of all
+// entries concatenated. Key, nonce, AAD and plaintext are prefixes of
+// 00 01 02 ..., the AEAD file iterates over plaintext lengths 0 to 32 and,
+// for each of them, over AAD lengths 0 to 32. The hash files cover messages
+// of 0 to 1024 bytes.
+const AEAD128_KAT: &'static [u8] =
+    include_bytes!("data/aead128_kat.output.bin");
+const HASH256_KAT: &'static [u8] =
+    include_bytes!("data/hash256_kat.output.bin");
+const XOF128_KAT: &'static [u8] =
+    include_bytes!("data/xof128_kat.output.bin");
+// 512 bytes of Ascon-XOF128 output for "abc"
+const XOF128_LONG: &'static [u8] =
+    include_bytes!("data/xof128_long.output.bin");
+
+fn counting(buf: &mut [u8]) -> &[u8] {
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    buf
+}
+
+#[test]
+fn ascon_aead128_kat() {
+    let mut key = [0u8; 16];
+    let key = counting(&mut key);
+    let c = AsconAead128::new(key).unwrap();
+    let mut aad = [0u8; 32];
+    let aad = counting(&mut aad);
+    let mut input = [0u8; 32];
+    let input = counting(&mut input);
+
+    let mut expected = AEAD128_KAT;
+    for msg_len in 0..33 {
+        for aad_len in 0..33 {
+            let (output, rest) = expected.split_at(msg_len);
+            let (tag, rest) = rest.split_at(16);
+            expected = rest;
+
+            let mut buf = [0u8; 32];
+            buf[..msg_len].copy_from_slice(&input[..msg_len]);
+            let calc_tag = c.encrypt_in_place_detached(key, &aad[..aad_len],
+                                                       &mut buf[..msg_len])
+                            .unwrap();
+            assert_eq!(&buf[..msg_len], output);
+            assert_eq!(&calc_tag[..], tag);
+
+            assert_eq!(c.decrypt_in_place_detached(key, &aad[..aad_len],
+                                                   &mut buf[..msg_len], tag),
+                       Ok(()));
+            assert_eq!(&buf[..msg_len], &input[..msg_len]);
+        }
+    }
+    assert_eq!(expected.len(), 0);
+}
+
+#[test]
+fn ascon_aead128_decrypt_failure() {
+    let c = AsconAead128::new(&[0; 16]).unwrap();
+    let nonce = [1u8; 16];
+    let mut buf = [7u8; 40];
+    let tag = c.encrypt_in_place_detached(&nonce, b"aad", &mut buf).unwrap();
+
+    for i in 0..buf.len() {
+        let mut forged = buf;
+        forged[i] ^= 4;
+        assert_eq!(c.decrypt_in_place_detached(&nonce, b"aad", &mut forged,
+                                               &tag),
+                   Err(AeadError::AuthenticationFailed));
+        assert!(forged.iter().all(|&b| b == 0));
+    }
+    let mut forged = buf;
+    assert_eq!(c.decrypt_in_place_detached(&nonce, b"aae", &mut forged, &tag),
+               Err(AeadError::AuthenticationFailed));
+    let mut forged = buf;
+    assert_eq!(c.decrypt_in_place_detached(&nonce, b"aad", &mut forged[..39],
+                                           &tag),
+               Err(AeadError::AuthenticationFailed));
+    let mut bad_tag = tag;
+    bad_tag[15] ^= 0x80;
+    assert_eq!(c.decrypt_in_place_detached(&nonce, b"aad", &mut buf, &bad_tag),
+               Err(AeadError::AuthenticationFailed));
+}
+
+#[test]
+fn ascon_aead128_invalid_lengths() {
+    assert_eq!(AsconAead128::new(&[0; 32]).err(),
+               Some(AeadError::InvalidKeyLength));
+    let c = AsconAead128::new(&[0; 16]).unwrap();
+    let mut buf = [0u8; 4];
+    assert_eq!(c.encrypt_in_place_detached(&[0; 12], &[], &mut buf).err(),
+               Some(AeadError::InvalidNonceLength));
+    assert_eq!(c.decrypt_in_place_detached(&[0; 16], &[], &mut buf, &[0; 8]),
+               Err(AeadError::InvalidLength));
+}
+
+fn check_hash<D: Digest>(mut digest: D, expected: &[u8], output_len: usize) {
+    let mut msg = [0u8; 1024];
+    let msg = counting(&mut msg);
+    let mut out = [0u8; 32];
+    for (len, expected) in expected.chunks(output_len).enumerate() {
+        digest.reset();
+        digest.input(&msg[..len]);
+        digest.result(&mut out[..output_len]);
+        assert_eq!(&out[..output_len], expected);
+
+        // Splitting the input must not change the result
+        digest.reset();
+        for chunk in msg[..len].chunks(3) {
+            digest.input(chunk);
+        }
+        digest.result(&mut out[..output_len]);
+        assert_eq!(&out[..output_len], expected);
+    }
+}
+
+#[test]
+fn ascon_hash256_kat() {
+    assert_eq!(HASH256_KAT.len(), 1025 * 32);
+    check_hash(AsconHash256::new(), HASH256_KAT, 32);
+}
+
+#[test]
+fn ascon_xof128_kat() {
+    assert_eq!(XOF128_KAT.len(), 1025 * 32);
+    check_hash(AsconXof128::new(), XOF128_KAT, 32);
+}
+
+#[test]
+fn ascon_xof128_output_lengths() {
+    let mut xof = AsconXof128::new();
+    xof.input(b"ab");
+    xof.input(b"c");
+    let mut out = [0u8; 512];
+    xof.result(&mut out);
+    assert_eq!(&out[..], XOF128_LONG);
+
+    // Shorter outputs are prefixes, `result` leaves the state unchanged
+    for len in [0, 1, 7, 8, 9, 100].iter() {
+        let mut out = [0u8; 100];
+        xof.result(&mut out[..*len]);
+        assert_eq!(&out[..*len], &XOF128_LONG[..*len]);
+    }
+}
+
+#[test]
+fn ascon_sync() {
+    fn assert_sync<T: Sync>() {}
+    assert_sync::<AsconAead128>();
+    assert_sync::<AsconHash256>();
+}
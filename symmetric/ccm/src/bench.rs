@@ -0,0 +1,43 @@
+use test::Bencher;
+use generic_array::typenum::{U8, U13, U16, U12};
+use crypto_aead::{Aead, NewAead};
+use super::{Aes128Ccm, Aes256Ccm};
+
+#[bench]
+pub fn aes128_ccm_10(bh: & mut Bencher) {
+    let input = [1u8; 10];
+    let aad = [3u8; 10];
+    let ccm = Aes128Ccm::<U8, U13>::new(&[0; 16]).unwrap();
+    bh.iter( || {
+        let mut output = [0u8; 10];
+        let mut tag = [0u8; 8];
+        ccm.encrypt(&[0; 13], &aad, &input, &mut output, &mut tag).unwrap();
+    });
+    bh.bytes = 10u64;
+}
+
+#[bench]
+pub fn aes128_ccm_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let aad = [3u8; 1024];
+    let ccm = Aes128Ccm::<U8, U13>::new(&[0; 16]).unwrap();
+    bh.iter( || {
+        let mut output = [0u8; 1024];
+        let mut tag = [0u8; 8];
+        ccm.encrypt(&[0; 13], &aad, &input, &mut output, &mut tag).unwrap();
+    });
+    bh.bytes = 1024u64;
+}
+
+#[bench]
+pub fn aes256_ccm_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let aad = [3u8; 1024];
+    let ccm = Aes256Ccm::<U16, U12>::new(&[0; 32]).unwrap();
+    bh.iter( || {
+        let mut output = [0u8; 1024];
+        let mut tag = [0u8; 16];
+        ccm.encrypt(&[0; 12], &aad, &input, &mut output, &mut tag).unwrap();
+    });
+    bh.bytes = 1024u64;
+}
@@ -0,0 +1,278 @@
+//! This is an implementation of the Counter with CBC-MAC (CCM) mode of
+//! operation, as specified in NIST SP 800-38C [1] and RFC 3610 [2], over
+//! any block cipher with a 128-bit block size.
+//!
+//! CBC-MAC is computed over a first block B0, which encodes the tag length,
+//! the nonce and the message length, followed by the length-prefixed AAD
+//! and the plaintext, each zero padded to a multiple of 16 bytes. The
+//! plaintext is then encrypted in CTR mode from the counter block with
+//! index 1, and the MAC is truncated to the tag length and masked with the
+//! block of index 0.
+//!
+//! The tag size (4 to 16 bytes, even) and the nonce size (7 to 13 bytes) are
+//! part of the type. A nonce of `n` bytes leaves `15 - n` bytes for the
+//! message length, so shorter nonces allow longer messages: 13-byte nonces
+//! limit them to 64 KiB. A nonce must never be reused with the same key.
+//!
+//! [1] - "Recommendation for Block Cipher Modes of Operation: The CCM Mode
+//!       for Authentication and Confidentiality"
+//!       <https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38c.pdf>
+//! [2] - "Counter with CBC-MAC (CCM)" <https://tools.ietf.org/html/rfc3610>
+
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate generic_array;
+extern crate aes;
+extern crate blockmodes;
+extern crate crypto_aead;
+extern crate crypto_ops;
+extern crate crypto_symmetric;
+
+use core::marker::PhantomData;
+use generic_array::{GenericArray, ArrayLength};
+use generic_array::typenum::{U4, U6, U7, U8, U9, U10, U11, U12, U13,
+                             U14, U16};
+use aes::{Aes128, Aes192, Aes256};
+use blockmodes::{CtrModeX8, Ctr128BE};
+use crypto_aead::{Aead, NewAead, AeadError, Tag};
+use crypto_ops::fixed_time_eq;
+use crypto_symmetric::{Block128, BlockCipher, BlockEncryptorX8,
+                       SynchronousStreamCipher};
+
+/// Tag sizes allowed by CCM: 4, 6, 8, 10, 12, 14 or 16 bytes
+pub trait CcmTagSize: ArrayLength<u8> {}
+
+impl CcmTagSize for U4 {}
+impl CcmTagSize for U6 {}
+impl CcmTagSize for U8 {}
+impl CcmTagSize for U10 {}
+impl CcmTagSize for U12 {}
+impl CcmTagSize for U14 {}
+impl CcmTagSize for U16 {}
+
+/// Nonce sizes allowed by CCM: 7 to 13 bytes
+pub trait CcmNonceSize: ArrayLength<u8> {}
+
+impl CcmNonceSize for U7 {}
+impl CcmNonceSize for U8 {}
+impl CcmNonceSize for U9 {}
+impl CcmNonceSize for U10 {}
+impl CcmNonceSize for U11 {}
+impl CcmNonceSize for U12 {}
+impl CcmNonceSize for U13 {}
+
+/// CCM over a block cipher with a 128-bit block size, with `TagSize` byte
+/// tags and `NonceSize` byte nonces. Use `from_cipher`, or the AES aliases
+/// with `NewAead`.
+#[derive(Clone, Copy)]
+pub struct Ccm<C, TagSize, NonceSize> {
+    cipher: C,
+    sizes: PhantomData<(TagSize, NonceSize)>,
+}
+
+pub type Aes128Ccm<TagSize, NonceSize> = Ccm<Aes128, TagSize, NonceSize>;
+pub type Aes192Ccm<TagSize, NonceSize> = Ccm<Aes192, TagSize, NonceSize>;
+pub type Aes256Ccm<TagSize, NonceSize> = Ccm<Aes256, TagSize, NonceSize>;
+
+/// CBC-MAC over zero padded fields
+struct CbcMac<'a, C: 'a> {
+    cipher: &'a C,
+    state: Block128,
+    pos: usize,
+}
+
+impl<'a, C> CbcMac<'a, C> where C: BlockCipher<BlockSize = U16> {
+    fn new(cipher: &'a C, b0: &[u8; 16]) -> CbcMac<'a, C> {
+        let mut mac = CbcMac { cipher: cipher, state: GenericArray::new(),
+                               pos: 0 };
+        mac.input(b0);
+        mac
+    }
+
+    fn input(&mut self, data: &[u8]) {
+        for &b in data {
+            self.state[self.pos] ^= b;
+            self.pos += 1;
+            if self.pos == 16 {
+                let input = self.state;
+                self.cipher.encrypt_block(&input, &mut self.state);
+                self.pos = 0;
+            }
+        }
+    }
+
+    /// Completes the current block with zeros
+    fn pad(&mut self) {
+        if self.pos != 0 {
+            let input = self.state;
+            self.cipher.encrypt_block(&input, &mut self.state);
+            self.pos = 0;
+        }
+    }
+}
+
+impl<C, TagSize, NonceSize> Ccm<C, TagSize, NonceSize>
+    where C: BlockCipher<BlockSize = U16> + BlockEncryptorX8 + Clone,
+          TagSize: CcmTagSize,
+          NonceSize: CcmNonceSize
+{
+    /// Sets up CCM with an already keyed `cipher`
+    pub fn from_cipher(cipher: C) -> Ccm<C, TagSize, NonceSize> {
+        Ccm { cipher: cipher, sizes: PhantomData }
+    }
+
+    /// Checks the nonce and message lengths and returns the counter block
+    /// with index 0, whose first byte holds `L - 1` where `L` is the size of
+    /// the length and counter field
+    fn ctr0(&self, nonce: &[u8], msg_len: usize)
+            -> Result<[u8; 16], AeadError> {
+        let n = NonceSize::to_usize();
+        if nonce.len() != n {
+            return Err(AeadError::InvalidNonceLength);
+        }
+        let l = 15 - n;
+        if l < 8 && (msg_len as u64) >> (8 * l) != 0 {
+            return Err(AeadError::InvalidLength);
+        }
+        let mut ctr = [0u8; 16];
+        ctr[0] = (l - 1) as u8;
+        ctr[1..1 + n].copy_from_slice(nonce);
+        Ok(ctr)
+    }
+
+    /// Computes the unmasked tag over the AAD and `plaintext`
+    fn mac(&self, ctr0: &[u8; 16], aad: &[u8], plaintext: &[u8])
+           -> [u8; 16] {
+        // B0 = flags || nonce || message length, the flags add the AAD bit
+        // and the encoded tag size to the L - 1 of the counter blocks
+        let mut b0 = *ctr0;
+        let t = TagSize::to_usize();
+        b0[0] |= (((t - 2) / 2) << 3) as u8;
+        if aad.len() > 0 {
+            b0[0] |= 0x40;
+        }
+        let mut len = plaintext.len() as u64;
+        for b in b0[1 + NonceSize::to_usize()..].iter_mut().rev() {
+            *b = len as u8;
+            len >>= 8;
+        }
+
+        let mut mac = CbcMac::new(&self.cipher, &b0);
+        if aad.len() > 0 {
+            let a = aad.len() as u64;
+            if a < 0xff00 {
+                mac.input(&[(a >> 8) as u8, a as u8]);
+            } else if a >> 32 == 0 {
+                mac.input(&[0xff, 0xfe, (a >> 24) as u8, (a >> 16) as u8,
+                            (a >> 8) as u8, a as u8]);
+            } else {
+                mac.input(&[0xff, 0xff, (a >> 56) as u8, (a >> 48) as u8,
+                            (a >> 40) as u8, (a >> 32) as u8,
+                            (a >> 24) as u8, (a >> 16) as u8,
+                            (a >> 8) as u8, a as u8]);
+            }
+            mac.input(aad);
+            mac.pad();
+        }
+        mac.input(plaintext);
+        mac.pad();
+
+        let mut tag = [0u8; 16];
+        tag.copy_from_slice(&mac.state);
+        tag
+    }
+
+    /// XORs the keystream block for the counter in `ctr` into `buf`
+    fn xor_block(&self, ctr: &[u8; 16], buf: &mut [u8]) {
+        let mut input = GenericArray::new();
+        let mut keystream = GenericArray::new();
+        input.copy_from_slice(ctr);
+        self.cipher.encrypt_block(&input, &mut keystream);
+        for (b, k) in buf.iter_mut().zip(keystream.iter()) {
+            *b ^= *k;
+        }
+    }
+
+    /// Encrypts `buf` in CTR mode from the counter block with index 1, the
+    /// length check guarantees the counter field does not overflow
+    fn ctr(&self, ctr0: &[u8; 16], buf: &mut [u8]) {
+        // The counter field of the block with index 0 is all zeros
+        let mut counter = GenericArray::from_slice(ctr0);
+        counter[15] = 1;
+        let mut ctr: CtrModeX8<C, Ctr128BE> =
+            CtrModeX8::new(self.cipher.clone(), &counter);
+        let mut out = [0u8; 128];
+        for chunk in buf.chunks_mut(128) {
+            let out = &mut out[..chunk.len()];
+            ctr.process(chunk, out);
+            chunk.copy_from_slice(out);
+        }
+    }
+}
+
+macro_rules! impl_new_aead {
+    ($cipher:ident, $key_size:expr) => {
+        impl<TagSize, NonceSize> NewAead for Ccm<$cipher, TagSize, NonceSize>
+            where TagSize: CcmTagSize, NonceSize: CcmNonceSize
+        {
+            fn new(key: &[u8])
+                   -> Result<Ccm<$cipher, TagSize, NonceSize>, AeadError> {
+                if key.len() != $key_size {
+                    return Err(AeadError::InvalidKeyLength);
+                }
+                Ok(Ccm::from_cipher($cipher::new(key)))
+            }
+        }
+    }
+}
+
+impl_new_aead!(Aes128, 16);
+impl_new_aead!(Aes192, 24);
+impl_new_aead!(Aes256, 32);
+
+impl<C, TagSize, NonceSize> Aead for Ccm<C, TagSize, NonceSize>
+    where C: BlockCipher<BlockSize = U16> + BlockEncryptorX8 + Clone,
+          TagSize: CcmTagSize,
+          NonceSize: CcmNonceSize
+{
+    type TagSize = TagSize;
+
+    fn encrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8])
+                                 -> Result<Tag<TagSize>, AeadError> {
+        let ctr0 = self.ctr0(nonce, buf.len())?;
+        let mut mac = self.mac(&ctr0, aad, buf);
+        self.ctr(&ctr0, buf);
+        self.xor_block(&ctr0, &mut mac);
+        let mut tag = GenericArray::new();
+        tag.copy_from_slice(&mac[..TagSize::to_usize()]);
+        Ok(tag)
+    }
+
+    fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8], tag: &[u8])
+                                 -> Result<(), AeadError> {
+        if tag.len() != TagSize::to_usize() {
+            return Err(AeadError::InvalidLength);
+        }
+        let ctr0 = self.ctr0(nonce, buf.len())?;
+        self.ctr(&ctr0, buf);
+        let mut mac = self.mac(&ctr0, aad, buf);
+        self.xor_block(&ctr0, &mut mac);
+        if fixed_time_eq(&mac[..tag.len()], tag) {
+            Ok(())
+        } else {
+            for b in buf.iter_mut() {
+                *b = 0;
+            }
+            Err(AeadError::AuthenticationFailed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod bench;
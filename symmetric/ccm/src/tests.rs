@@ -0,0 +1,147 @@
+use generic_array::typenum::{U4, U6, U7, U8, U10, U11, U12, U13, U14, U16};
+use aes::Aes128;
+use crypto_aead::{Aead, NewAead, AeadError};
+use super::{Ccm, Aes128Ccm, Aes192Ccm, Aes256Ccm};
+
+struct TestVector {
+    pub name: &'static str,
+    pub key: &'static [u8],
+    pub nonce: &'static [u8],
+    pub input: &'static [u8],
+    pub output: &'static [u8],
+    pub aad: &'static [u8],
+    pub tag: &'static [u8],
+}
+
+macro_rules! get_vectors {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            TestVector {
+                name: $name,
+                key: include_bytes!(concat!("data/", $name, ".key.bin")),
+                nonce: include_bytes!(concat!("data/", $name, ".nonce.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                output: include_bytes!(concat!("data/", $name, ".output.bin")),
+                aad: include_bytes!(concat!("data/", $name, ".aad.bin")),
+                tag: include_bytes!(concat!("data/", $name, ".tag.bin")),
+            },
+        )*]
+    };
+}
+
+macro_rules! check_vector {
+    ($ccm:ty, $tv:expr) => {{
+        let tv = $tv;
+        let msg_len = tv.input.len();
+        let c = <$ccm>::new(tv.key).unwrap();
+        let mut buf = [0u8; MAX_MSG_LEN];
+        buf[..msg_len].copy_from_slice(tv.input);
+        let tag = c.encrypt_in_place_detached(tv.nonce, tv.aad,
+                                              &mut buf[..msg_len]).unwrap();
+        assert_eq!(&buf[..msg_len], tv.output);
+        assert_eq!(&tag[..], tv.tag);
+
+        assert_eq!(c.decrypt_in_place_detached(tv.nonce, tv.aad,
+                                               &mut buf[..msg_len], tv.tag),
+                   Ok(()));
+        assert_eq!(&buf[..msg_len], tv.input);
+
+        let mut bad_tag = [0u8; 16];
+        bad_tag[..tv.tag.len()].copy_from_slice(tv.tag);
+        bad_tag[tv.tag.len() - 1] ^= 1;
+        buf[..msg_len].copy_from_slice(tv.output);
+        assert_eq!(c.decrypt_in_place_detached(tv.nonce, tv.aad,
+                                               &mut buf[..msg_len],
+                                               &bad_tag[..tv.tag.len()]),
+                   Err(AeadError::AuthenticationFailed));
+        assert!(buf[..msg_len].iter().all(|&b| b == 0));
+    }}
+}
+
+const MAX_MSG_LEN: usize = 300;
+
+// RFC 3610, packet vectors #1 to #12: 8-byte tags for the first six and
+// 10-byte tags for the others, with 13-byte nonces
+const RFC_VECTORS: [TestVector; 12] = get_vectors!("rfc_1", "rfc_2", "rfc_3",
+    "rfc_4", "rfc_5", "rfc_6", "rfc_7", "rfc_8", "rfc_9", "rfc_10", "rfc_11",
+    "rfc_12");
+
+// NIST SP 800-38C, appendix C. Example 4 has 64 KiB of associated data,
+// which needs the 6-byte length encoding.
+const NIST_VECTORS: [TestVector; 4] = get_vectors!("nist_1", "nist_2",
+    "nist_3", "nist_4");
+
+// Generated with the `cryptography` Python package
+const GEN_VECTORS: [TestVector; 4] = get_vectors!("gen_1", "gen_2", "gen_3",
+    "gen_4");
+
+#[test]
+fn ccm_rfc3610_vectors() {
+    for tv in RFC_VECTORS[..6].iter() {
+        check_vector!(Aes128Ccm<U8, U13>, tv);
+    }
+    for tv in RFC_VECTORS[6..].iter() {
+        check_vector!(Aes128Ccm<U10, U13>, tv);
+    }
+}
+
+#[test]
+fn ccm_sp800_38c_vectors() {
+    check_vector!(Aes128Ccm<U4, U7>, &NIST_VECTORS[0]);
+    check_vector!(Aes128Ccm<U6, U8>, &NIST_VECTORS[1]);
+    check_vector!(Aes128Ccm<U8, U12>, &NIST_VECTORS[2]);
+    check_vector!(Aes128Ccm<U14, U13>, &NIST_VECTORS[3]);
+}
+
+#[test]
+fn ccm_generated_vectors() {
+    check_vector!(Aes192Ccm<U12, U11>, &GEN_VECTORS[0]);
+    check_vector!(Aes256Ccm<U16, U12>, &GEN_VECTORS[1]);
+    check_vector!(Aes256Ccm<U16, U12>, &GEN_VECTORS[2]);
+    check_vector!(Aes128Ccm<U4, U13>, &GEN_VECTORS[3]);
+}
+
+#[test]
+fn ccm_attached() {
+    // The `encrypt` and `decrypt` wrappers with a cipher keyed by the caller
+    let tv = &RFC_VECTORS[0];
+    let msg_len = tv.input.len();
+    let c: Ccm<Aes128, U8, U13> = Ccm::from_cipher(Aes128::new(tv.key));
+    let mut output = [0u8; MAX_MSG_LEN];
+    let mut tag = [0u8; 8];
+    c.encrypt(tv.nonce, tv.aad, tv.input, &mut output[..msg_len], &mut tag)
+        .unwrap();
+    assert_eq!(&output[..msg_len], tv.output);
+    assert_eq!(&tag[..], tv.tag);
+    assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output, &mut output[..msg_len],
+                         tv.tag),
+               Ok(()));
+    assert_eq!(&output[..msg_len], tv.input);
+}
+
+#[test]
+fn ccm_invalid_lengths() {
+    let tv = &RFC_VECTORS[0];
+    let msg_len = tv.input.len();
+    let mut buf = [0u8; 0x10000];
+    assert_eq!(Aes128Ccm::<U8, U13>::new(&tv.key[..8]).err(),
+               Some(AeadError::InvalidKeyLength));
+
+    let c = Aes128Ccm::<U8, U13>::new(tv.key).unwrap();
+    assert_eq!(c.encrypt_in_place_detached(&tv.nonce[..12], tv.aad,
+                                           &mut buf[..msg_len]).err(),
+               Some(AeadError::InvalidNonceLength));
+    assert_eq!(c.decrypt_in_place_detached(tv.nonce, tv.aad,
+                                           &mut buf[..msg_len],
+                                           &tv.tag[..4]),
+               Err(AeadError::InvalidLength));
+
+    // With 13-byte nonces the length field is 2 bytes long
+    assert!(c.encrypt_in_place_detached(tv.nonce, &[], &mut buf[..0xffff])
+             .is_ok());
+    assert_eq!(c.encrypt_in_place_detached(tv.nonce, &[], &mut buf).err(),
+               Some(AeadError::InvalidLength));
+    let c = Aes128Ccm::<U8, U12>::new(tv.key).unwrap();
+    assert!(c.encrypt_in_place_detached(&tv.nonce[..12], &[], &mut buf)
+             .is_ok());
+}
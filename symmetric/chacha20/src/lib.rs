@@ -87,6 +87,27 @@ static S12:u32x4 = u32x4(12, 12, 12, 12);
 static S8:u32x4 = u32x4(8, 8, 8, 8);
 static S7:u32x4 = u32x4(7, 7, 7, 7);
 
+/// Derives a 256-bit subkey from a 256-bit key and a 128-bit input with
+/// HChaCha20, as used by XChaCha20 and libsodium's secretstream
+pub fn hchacha20(key: &[u8], input: &[u8]) -> [u8; 32] {
+    assert!(key.len() == 32);
+    assert!(input.len() == 16);
+
+    // HChaCha20 produces a 256-bit output block starting from a 512 bit
+    // input block where (x0,x1,...,x15) where
+    //
+    //  * (x0, x1, x2, x3) is the ChaCha20 constant.
+    //  * (x4, x5, ... x11) is a 256 bit key.
+    //  * (x12, x13, x14, x15) is a 128 bit nonce.
+    let mut chacha = ChaCha20{
+        state: ChaCha20::expand(key, input),
+        output: [0u8; 64],
+        offset: 64 };
+    let mut out = [0u8; 32];
+    chacha.hchacha20(&mut out);
+    out
+}
+
 impl ChaCha20 {
     pub fn new(key: &[u8], nonce: &[u8]) -> ChaCha20 {
         assert!(key.len() == 16 || key.len() == 32);
@@ -99,24 +120,30 @@ impl ChaCha20 {
         assert!(key.len() == 32);
         assert!(nonce.len() == 24);
 
-        // HChaCha20 produces a 256-bit output block starting from a 512 bit
-        // input block where (x0,x1,...,x15) where
-        //
-        //  * (x0, x1, x2, x3) is the ChaCha20 constant.
-        //  * (x4, x5, ... x11) is a 256 bit key.
-        //  * (x12, x13, x14, x15) is a 128 bit nonce.
-        let mut xchacha20 = ChaCha20{
-            state: ChaCha20::expand(key, &nonce[0..16]),
-            output: [0u8; 64],
-            offset: 64 };
-
         // Use HChaCha to derive the subkey, and initialize a ChaCha20 instance
         // with the subkey and the remaining 8 bytes of the nonce.
-        let mut new_key = [0; 32];
-        xchacha20.hchacha20(&mut new_key);
-        xchacha20.state = ChaCha20::expand(&new_key, &nonce[16..24]);
+        let new_key = hchacha20(key, &nonce[0..16]);
+        ChaCha20::new(&new_key, &nonce[16..24])
+    }
+
+    /// XORs the keystream into `buf`, equivalent to `process` with the
+    /// same slice as input and output
+    pub fn process_in_place(&mut self, buf: &mut [u8]) {
+        let len = buf.len();
+        let mut i = 0;
+        while i < len {
+            if self.offset == 64 {
+                self.update();
+            }
 
-        xchacha20
+            let count = cmp::min(64 - self.offset, len - i);
+            let keystream = &self.output[self.offset..self.offset+count];
+            for (b, k) in buf[i..i+count].iter_mut().zip(keystream.iter()) {
+                *b ^= *k;
+            }
+            i += count;
+            self.offset += count;
+        }
     }
 
     fn expand(key: &[u8], nonce: &[u8]) -> ChaChaState {
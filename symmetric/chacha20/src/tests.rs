@@ -1,4 +1,4 @@
-use super::ChaCha20;
+use super::{ChaCha20, hchacha20};
 use crypto_symmetric::SynchronousStreamCipher;
 
 struct Test {
@@ -53,3 +53,27 @@ fn xchacha20() {
     }
 }
 
+#[test]
+fn hchacha20_subkey() {
+    // draft-irtf-cfrg-xchacha-03, section 2.2.1
+    let key = include_bytes!("data/h1.key.bin");
+    let input = include_bytes!("data/h1.input.bin");
+    let output = include_bytes!("data/h1.output.bin");
+    assert_eq!(&hchacha20(key, input)[..], &output[..]);
+}
+
+#[test]
+fn chacha20_process_in_place() {
+    let tests = get_tests!("1", "2", "3", "4", "5", "6", "7", "8", "9", "10");
+    let mut buf = [0u8; MAX_LEN];
+    for test in tests.iter() {
+        let n = test.input.len();
+        let mut state = ChaCha20::new(test.key, test.nonce);
+        buf[..n].copy_from_slice(test.input);
+        // Uneven chunks to exercise partially consumed keystream blocks
+        for chunk in buf[..n].chunks_mut(37) {
+            state.process_in_place(chunk);
+        }
+        assert_eq!(test.output, &buf[..n]);
+    }
+}
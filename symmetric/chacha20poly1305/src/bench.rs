@@ -1,20 +1,18 @@
 use test::Bencher;
-use super::ChaCha20Poly1305;
-use crypto_aead::{AeadEncryptor, AeadDecryptor};
+use super::{ChaCha20Poly1305, ChaCha20Poly1305Ietf};
+use crypto_aead::{Aead, NewAead};
 
 #[bench]
 pub fn chacha20poly1305_10(bh: & mut Bencher) {
   let input = [1u8; 10];
   let aad = [3u8; 10];
+  let cipher = ChaCha20Poly1305::new(&[0; 32]).unwrap();
   bh.iter( || {
-      let mut cipher = ChaCha20Poly1305::new(&[0; 32], &[0; 8], &aad);
-      let mut decipher = ChaCha20Poly1305::new(&[0; 32], &[0; 8], &aad);
-
       let mut output = [0u8; 10];
       let mut tag = [0u8; 16];
       let mut output2 = [0u8; 10];
-      cipher.encrypt(&input, &mut output, &mut tag);
-      decipher.decrypt(&output, &mut output2, &tag);
+      cipher.encrypt(&[0; 8], &aad, &input, &mut output, &mut tag).unwrap();
+      cipher.decrypt(&[0; 8], &aad, &output, &mut output2, &tag).unwrap();
 
     });
     bh.bytes = 10u64;
@@ -24,16 +22,14 @@ pub fn chacha20poly1305_10(bh: & mut Bencher) {
 pub fn chacha20poly1305_1k(bh: & mut Bencher) {
   let input = [1u8; 1024];
   let aad = [3u8; 1024];
+  let cipher = ChaCha20Poly1305::new(&[0; 32]).unwrap();
   bh.iter( || {
-    let mut cipher = ChaCha20Poly1305::new(&[0; 32], &[0; 8], &aad);
-    let mut decipher = ChaCha20Poly1305::new(&[0; 32], &[0; 8], &aad);
-
     let mut output = [0u8; 1024];
     let mut tag = [0u8; 16];
     let mut output2 = [0u8; 1024];
 
-    cipher.encrypt(&input, &mut output, &mut tag);
-    decipher.decrypt(&output, &mut output2, &tag);
+    cipher.encrypt(&[0; 8], &aad, &input, &mut output, &mut tag).unwrap();
+    cipher.decrypt(&[0; 8], &aad, &output, &mut output2, &tag).unwrap();
     });
   bh.bytes = 1024u64;
 
@@ -43,17 +39,31 @@ pub fn chacha20poly1305_1k(bh: & mut Bencher) {
 pub fn chacha20poly1305_64k(bh: & mut Bencher) {
   let input = [1u8; 65536];
   let aad = [3u8; 65536];
+  let cipher = ChaCha20Poly1305::new(&[0; 32]).unwrap();
     bh.iter( || {
-      let mut cipher = ChaCha20Poly1305::new(&[0; 32], &[0; 8], &aad);
-      let mut decipher = ChaCha20Poly1305::new(&[0; 32], &[0; 8], &aad);
-
       let mut output = [0u8; 65536];
       let mut tag = [0u8; 16];
       let mut output2 = [0u8; 65536];
 
-      cipher.encrypt(&input, &mut output, &mut tag);
-      decipher.decrypt(&output, &mut output2, &tag);
+      cipher.encrypt(&[0; 8], &aad, &input, &mut output, &mut tag).unwrap();
+      cipher.decrypt(&[0; 8], &aad, &output, &mut output2, &tag).unwrap();
 
     });
      bh.bytes = 65536u64;
-}
\ No newline at end of file
+}
+
+#[bench]
+pub fn chacha20poly1305_ietf_1k(bh: & mut Bencher) {
+  let input = [1u8; 1024];
+  let aad = [3u8; 1024];
+  let cipher = ChaCha20Poly1305Ietf::new(&[0; 32]).unwrap();
+  bh.iter( || {
+    let mut output = [0u8; 1024];
+    let mut tag = [0u8; 16];
+    let mut output2 = [0u8; 1024];
+
+    cipher.encrypt(&[0; 12], &aad, &input, &mut output, &mut tag).unwrap();
+    cipher.decrypt(&[0; 12], &aad, &output, &mut output2, &tag).unwrap();
+    });
+  bh.bytes = 1024u64;
+}
@@ -1,6 +1,7 @@
 #![no_std]
 #![feature(test)]
 extern crate test;
+extern crate generic_array;
 extern crate crypto_aead;
 extern crate crypto_symmetric;
 extern crate crypto_bytes;
@@ -8,8 +9,12 @@ extern crate crypto_mac;
 extern crate crypto_ops;
 extern crate chacha20;
 extern crate poly1305;
+#[cfg(all(test, feature = "alloc"))]
+extern crate alloc;
 
-use crypto_aead::{AeadEncryptor, AeadDecryptor};
+use generic_array::GenericArray;
+use generic_array::typenum::U16;
+use crypto_aead::{Aead, NewAead, AeadError, Tag};
 use crypto_symmetric::SynchronousStreamCipher;
 use crypto_mac::Mac;
 use crypto_bytes::write_u64_le;
@@ -17,78 +22,281 @@ use crypto_ops::fixed_time_eq;
 use chacha20::ChaCha20;
 use poly1305::Poly1305;
 
+/// Wipes `output` after a failed authentication
+fn zero_output(output: &mut [u8]) {
+    for b in output.iter_mut() {
+        *b = 0;
+    }
+}
+
+/// Longest message, 2^32 - 2 blocks: the first block of keystream is the
+/// Poly1305 key and ChaCha20 panics once its 32-bit counter would wrap
+/// after the block with counter 2^32 - 1. This is one block less than the
+/// 2^38 - 64 bytes of RFC 8439.
+const MAX_PLAINTEXT_LEN: u64 = (1 << 38) - 128;
+
+/// Rejects messages too long for the 32-bit block counter, before the
+/// cipher is set up
+fn check_length(len: usize) -> Result<(), AeadError> {
+    if len as u64 > MAX_PLAINTEXT_LEN {
+        Err(AeadError::InvalidLength)
+    } else {
+        Ok(())
+    }
+}
+
+/// Copies a 32 byte key, `ChaCha20Poly1305` also accepts 16 byte keys
+fn copy_key(key: &[u8]) -> [u8; 32] {
+    let mut k = [0u8; 32];
+    k[..key.len()].copy_from_slice(key);
+    k
+}
 
+/// The original ChaCha20-Poly1305 construction from
+/// draft-agl-tls-chacha20poly1305, with a 64-bit nonce
 #[derive(Clone, Copy)]
 pub struct ChaCha20Poly1305 {
-    cipher  : ChaCha20,
-    mac: Poly1305,
-    finished: bool,
-    data_len: usize
+    key: [u8; 32],
+    key_len: usize,
+}
+
+impl NewAead for ChaCha20Poly1305 {
+    fn new(key: &[u8]) -> Result<ChaCha20Poly1305, AeadError> {
+        if key.len() != 16 && key.len() != 32 {
+            return Err(AeadError::InvalidKeyLength);
+        }
+        Ok(ChaCha20Poly1305 { key: copy_key(key), key_len: key.len() })
+    }
 }
 
 impl ChaCha20Poly1305 {
-  pub fn new(key: &[u8], nonce: &[u8], aad: &[u8]) -> ChaCha20Poly1305 {
-      assert!(key.len() == 16 || key.len() == 32);
-      assert!(nonce.len() == 8);
-
-      let mut cipher = ChaCha20::new(key, nonce);
-      let mut mac_key = [0u8; 64];
-      let zero_key = [0u8; 64];
-      cipher.process(&zero_key, &mut mac_key);
-
-      let mut mac = Poly1305::new(&mac_key[..32]);
-      mac.input(aad);
-      let mut aad_len = [0u8; 8];
-      let aad_len_uint: u64 = aad.len() as u64;
-      write_u64_le(&mut aad_len, aad_len_uint);
-      mac.input(&aad_len);
-      ChaCha20Poly1305 {
-        cipher: cipher,
-        mac: mac,
-        finished: false,
-        data_len: 0
-      }
-  }
-}
-
-impl AeadEncryptor for ChaCha20Poly1305 {
-    fn encrypt(&mut self, input: &[u8], output: &mut [u8], out_tag: &mut [u8]) {
-        assert!(input.len() == output.len());
-        assert!(self.finished == false);
-        self.cipher.process(input, output);
-        self.data_len += input.len();
-        self.mac.input(output);
-        self.finished = true;
-        let mut data_len_buf = [0u8; 8];
-        write_u64_le(&mut data_len_buf, self.data_len as u64);
-        self.mac.input(&data_len_buf);
-        self.mac.raw_result(out_tag);
-    }
-}
-
-impl AeadDecryptor for ChaCha20Poly1305 {
-    fn decrypt(&mut self, input: &[u8], output: &mut [u8], tag: &[u8]) -> bool {
-        assert!(input.len() == output.len());
-        assert!(self.finished == false);
-
-        self.finished = true;
-
-        self.mac.input(input);
-
-        self.data_len += input.len();
-        let mut data_len_buf = [0u8; 8];
-
-        write_u64_le(&mut data_len_buf, self.data_len as u64);
-        self.mac.input(&data_len_buf);
-
-        let mut calc_tag =  [0u8; 16];
-        self.mac.raw_result(&mut calc_tag);
-        if fixed_time_eq(&calc_tag[..tag.len()], tag) {
-            self.cipher.process(input, output);
-            true
+    /// Derives the one-time Poly1305 key for `nonce` and authenticates the
+    /// AAD, followed by its length
+    fn start(&self, nonce: &[u8], aad: &[u8])
+             -> Result<(ChaCha20, Poly1305), AeadError> {
+        if nonce.len() != 8 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+
+        let mut cipher = ChaCha20::new(&self.key[..self.key_len], nonce);
+        let mut mac_key = [0u8; 64];
+        let zero_key = [0u8; 64];
+        cipher.process(&zero_key, &mut mac_key);
+
+        let mut mac = Poly1305::new(&mac_key[..32]);
+        mac.input(aad);
+        let mut aad_len = [0u8; 8];
+        write_u64_le(&mut aad_len, aad.len() as u64);
+        mac.input(&aad_len);
+        Ok((cipher, mac))
+    }
+}
+
+/// Authenticates the ciphertext followed by its length
+fn finish(mut mac: Poly1305, ciphertext: &[u8], tag: &mut [u8]) {
+    mac.input(ciphertext);
+    let mut data_len_buf = [0u8; 8];
+    write_u64_le(&mut data_len_buf, ciphertext.len() as u64);
+    mac.input(&data_len_buf);
+    mac.raw_result(tag);
+}
+
+impl Aead for ChaCha20Poly1305 {
+    type TagSize = U16;
+
+    fn encrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8])
+                                 -> Result<Tag<U16>, AeadError> {
+        check_length(buf.len())?;
+        let (mut cipher, mac) = self.start(nonce, aad)?;
+        cipher.process_in_place(buf);
+        let mut tag = GenericArray::new();
+        finish(mac, buf, &mut tag);
+        Ok(tag)
+    }
+
+    /// The tag must be exactly 16 bytes. This is a breaking change: earlier
+    /// versions of this construction accepted tags truncated to as few as
+    /// one byte, which now fail with `InvalidLength`.
+    fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8], tag: &[u8])
+                                 -> Result<(), AeadError> {
+        if tag.len() != 16 {
+            return Err(AeadError::InvalidLength);
+        }
+        check_length(buf.len())?;
+        let (mut cipher, mac) = self.start(nonce, aad)?;
+
+        let mut calc_tag = [0u8; 16];
+        finish(mac, buf, &mut calc_tag);
+        if fixed_time_eq(&calc_tag, tag) {
+            cipher.process_in_place(buf);
+            Ok(())
+        } else {
+            zero_output(buf);
+            Err(AeadError::AuthenticationFailed)
+        }
+    }
+}
+
+/// ChaCha20-Poly1305 construction as specified in RFC 8439 (also used by
+/// TLS 1.3 and libsodium's `crypto_aead_chacha20poly1305_ietf`).
+///
+/// It differs from `ChaCha20Poly1305` by using a 96-bit nonce with a 32-bit
+/// block counter and by padding AAD and ciphertext to a multiple of 16 bytes
+/// before authenticating them.
+#[derive(Clone, Copy)]
+pub struct ChaCha20Poly1305Ietf {
+    key: [u8; 32],
+}
+
+/// Feeds zero bytes into `mac` until `len` is a multiple of 16
+fn pad16(mac: &mut Poly1305, len: usize) {
+    let rem = len % 16;
+    if rem != 0 {
+        let zeros = [0u8; 16];
+        mac.input(&zeros[..16 - rem]);
+    }
+}
+
+/// State of the RFC 8439 construction for a single message
+struct IetfMessage {
+    cipher: ChaCha20,
+    mac: Poly1305,
+    aad_len: u64,
+}
+
+impl IetfMessage {
+    fn new(mut cipher: ChaCha20, aad: &[u8]) -> IetfMessage {
+        // The first keystream block (counter 0) is used for the Poly1305
+        // key, encryption starts with counter 1
+        let mut mac_key = [0u8; 64];
+        let zero_key = [0u8; 64];
+        cipher.process(&zero_key, &mut mac_key);
+
+        let mut mac = Poly1305::new(&mac_key[..32]);
+        mac.input(aad);
+        pad16(&mut mac, aad.len());
+        IetfMessage { cipher: cipher, mac: mac, aad_len: aad.len() as u64 }
+    }
+
+    fn finish(&mut self, ciphertext: &[u8], tag: &mut [u8]) {
+        self.mac.input(ciphertext);
+        pad16(&mut self.mac, ciphertext.len());
+        let mut len_buf = [0u8; 16];
+        write_u64_le(&mut len_buf[..8], self.aad_len);
+        write_u64_le(&mut len_buf[8..], ciphertext.len() as u64);
+        self.mac.input(&len_buf);
+        self.mac.raw_result(tag);
+    }
+
+    fn encrypt(mut self, buf: &mut [u8]) -> Tag<U16> {
+        self.cipher.process_in_place(buf);
+        let mut tag = GenericArray::new();
+        self.finish(buf, &mut tag);
+        tag
+    }
+
+    fn decrypt(mut self, buf: &mut [u8], tag: &[u8]) -> Result<(), AeadError> {
+        if tag.len() != 16 {
+            return Err(AeadError::InvalidLength);
+        }
+
+        let mut calc_tag = [0u8; 16];
+        self.finish(buf, &mut calc_tag);
+        if fixed_time_eq(&calc_tag, tag) {
+            self.cipher.process_in_place(buf);
+            Ok(())
         } else {
-            false
+            zero_output(buf);
+            Err(AeadError::AuthenticationFailed)
+        }
+    }
+}
+
+impl NewAead for ChaCha20Poly1305Ietf {
+    fn new(key: &[u8]) -> Result<ChaCha20Poly1305Ietf, AeadError> {
+        if key.len() != 32 {
+            return Err(AeadError::InvalidKeyLength);
+        }
+        Ok(ChaCha20Poly1305Ietf { key: copy_key(key) })
+    }
+}
+
+impl ChaCha20Poly1305Ietf {
+    fn start(&self, nonce: &[u8], aad: &[u8])
+             -> Result<IetfMessage, AeadError> {
+        if nonce.len() != 12 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+        Ok(IetfMessage::new(ChaCha20::new(&self.key, nonce), aad))
+    }
+}
+
+impl Aead for ChaCha20Poly1305Ietf {
+    type TagSize = U16;
+
+    fn encrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8])
+                                 -> Result<Tag<U16>, AeadError> {
+        check_length(buf.len())?;
+        Ok(self.start(nonce, aad)?.encrypt(buf))
+    }
+
+    fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8], tag: &[u8])
+                                 -> Result<(), AeadError> {
+        check_length(buf.len())?;
+        self.start(nonce, aad)?.decrypt(buf, tag)
+    }
+}
+
+/// XChaCha20-Poly1305 construction as specified in draft-irtf-cfrg-xchacha
+/// (libsodium's `crypto_aead_xchacha20poly1305_ietf`).
+///
+/// The 192-bit nonce is large enough to be safely generated at random for
+/// every message. HChaCha20 derives a subkey from the key and the first 16
+/// bytes of the nonce, the rest is processed as in `ChaCha20Poly1305Ietf`.
+#[derive(Clone, Copy)]
+pub struct XChaCha20Poly1305 {
+    key: [u8; 32],
+}
+
+impl NewAead for XChaCha20Poly1305 {
+    fn new(key: &[u8]) -> Result<XChaCha20Poly1305, AeadError> {
+        if key.len() != 32 {
+            return Err(AeadError::InvalidKeyLength);
         }
+        Ok(XChaCha20Poly1305 { key: copy_key(key) })
+    }
+}
+
+impl XChaCha20Poly1305 {
+    fn start(&self, nonce: &[u8], aad: &[u8])
+             -> Result<IetfMessage, AeadError> {
+        if nonce.len() != 24 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+        let cipher = ChaCha20::new_xchacha20(&self.key, nonce);
+        Ok(IetfMessage::new(cipher, aad))
+    }
+}
+
+impl Aead for XChaCha20Poly1305 {
+    type TagSize = U16;
+
+    fn encrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8])
+                                 -> Result<Tag<U16>, AeadError> {
+        check_length(buf.len())?;
+        Ok(self.start(nonce, aad)?.encrypt(buf))
+    }
+
+    fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8], tag: &[u8])
+                                 -> Result<(), AeadError> {
+        check_length(buf.len())?;
+        self.start(nonce, aad)?.decrypt(buf, tag)
     }
 }
 
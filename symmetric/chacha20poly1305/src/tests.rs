@@ -1,5 +1,8 @@
-use super::ChaCha20Poly1305;
-use crypto_aead::{AeadEncryptor, AeadDecryptor};
+use super::{ChaCha20Poly1305, ChaCha20Poly1305Ietf, XChaCha20Poly1305,
+            check_length, MAX_PLAINTEXT_LEN};
+use generic_array::typenum::{U16, U311};
+use crypto_aead::{Aead, NewAead, AeadError, Buffer, ArrayBuffer};
+use crypto_aead::stream::{StreamEncryptor, StreamDecryptor};
 
 struct TestVector {
     pub name: &'static str,
@@ -44,10 +47,11 @@ fn chacha20poly1305_boringssl_vectors_encrypt() {
         let msg_len = tv.input.len();
         let tag_len = tv.tag.len();
         let ext_tag_len = if tag_len < 16 {16} else {tv.tag.len()};
-        let mut c = ChaCha20Poly1305::new(tv.key, tv.nonce, tv.aad);
+        let c = ChaCha20Poly1305::new(tv.key).unwrap();
         let mut output = [0u8; MAX_MSG_LEN];
         let mut tag = [0u8; MAX_TAG_LEN];
-        c.encrypt(tv.input, &mut output[..msg_len], &mut tag[..ext_tag_len]);
+        c.encrypt(tv.nonce, tv.aad, tv.input, &mut output[..msg_len],
+                  &mut tag[..ext_tag_len]).unwrap();
         assert_eq!(&output[..msg_len], tv.output);
         assert_eq!(&tag[..tag_len], tv.tag);
     }
@@ -56,10 +60,384 @@ fn chacha20poly1305_boringssl_vectors_encrypt() {
 fn test_chacha20_256_poly1305_boringssl_vectors_decrypt() {
     for tv in TEST_VECTORS.iter() {
         let msg_len = tv.input.len();
-        let mut c = ChaCha20Poly1305::new(tv.key, tv.nonce, tv.aad);
+        let c = ChaCha20Poly1305::new(tv.key).unwrap();
         let mut output = [0u8; MAX_MSG_LEN];
-        let result = c.decrypt(tv.output, &mut output[..msg_len], tv.tag);
-        assert!(result);
+        let result = c.decrypt(tv.nonce, tv.aad, tv.output,
+                               &mut output[..msg_len], tv.tag);
+        if tv.tag.len() == 16 {
+            assert_eq!(result, Ok(()));
+            assert_eq!(&output[..msg_len], tv.input);
+        } else {
+            // Some BoringSSL vectors have truncated tags. Earlier versions
+            // accepted them, decryption now requires the full 16 bytes.
+            assert_eq!(result, Err(AeadError::InvalidLength));
+        }
+    }
+}
+
+// RFC 8439, section 2.8.2 and appendix A.5
+const IETF_TEST_VECTORS: [TestVector; 2] = get_vectors!("ietf_1", "ietf_2");
+
+#[test]
+fn chacha20poly1305_ietf_rfc8439_vectors_encrypt() {
+    for tv in IETF_TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let c = ChaCha20Poly1305Ietf::new(tv.key).unwrap();
+        let mut output = [0u8; MAX_MSG_LEN];
+        let mut tag = [0u8; 16];
+        c.encrypt(tv.nonce, tv.aad, tv.input, &mut output[..msg_len], &mut tag)
+            .unwrap();
+        assert_eq!(&output[..msg_len], tv.output);
+        assert_eq!(&tag[..], tv.tag);
+    }
+}
+
+#[test]
+fn chacha20poly1305_ietf_rfc8439_vectors_decrypt() {
+    for tv in IETF_TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let c = ChaCha20Poly1305Ietf::new(tv.key).unwrap();
+        let mut output = [0u8; MAX_MSG_LEN];
+        let result = c.decrypt(tv.nonce, tv.aad, tv.output,
+                               &mut output[..msg_len], tv.tag);
+        assert_eq!(result, Ok(()));
+        assert_eq!(&output[..msg_len], tv.input);
+    }
+}
+
+#[test]
+fn chacha20poly1305_ietf_bad_tag() {
+    for tv in IETF_TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let mut tag = [0u8; 16];
+        tag.copy_from_slice(tv.tag);
+        tag[0] ^= 1;
+        let c = ChaCha20Poly1305Ietf::new(tv.key).unwrap();
+        let mut output = [1u8; MAX_MSG_LEN];
+        assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output,
+                             &mut output[..msg_len], &tag),
+                   Err(AeadError::AuthenticationFailed));
+        assert!(output[..msg_len].iter().all(|&b| b == 0));
+
+        // Truncated and empty tags must not authenticate anything
+        for len in [0, 1, 15].iter() {
+            assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output,
+                                 &mut output[..msg_len], &tv.tag[..*len]),
+                       Err(AeadError::InvalidLength));
+        }
+    }
+}
+
+// The first vector is from draft-irtf-cfrg-xchacha-03, appendix A.3.1,
+// the others were generated with libsodium 1.0.18
+const XCHACHA_TEST_VECTORS: [TestVector; 3] = get_vectors!(
+    "xchacha_1", "xchacha_2", "xchacha_3");
+
+#[test]
+fn xchacha20poly1305_vectors_encrypt() {
+    for tv in XCHACHA_TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let c = XChaCha20Poly1305::new(tv.key).unwrap();
+        let mut output = [0u8; MAX_MSG_LEN];
+        let mut tag = [0u8; 16];
+        c.encrypt(tv.nonce, tv.aad, tv.input, &mut output[..msg_len], &mut tag)
+            .unwrap();
+        assert_eq!(&output[..msg_len], tv.output);
+        assert_eq!(&tag[..], tv.tag);
+    }
+}
+
+#[test]
+fn xchacha20poly1305_vectors_decrypt() {
+    for tv in XCHACHA_TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let c = XChaCha20Poly1305::new(tv.key).unwrap();
+        let mut output = [0u8; MAX_MSG_LEN];
+        let result = c.decrypt(tv.nonce, tv.aad, tv.output,
+                               &mut output[..msg_len], tv.tag);
+        assert_eq!(result, Ok(()));
         assert_eq!(&output[..msg_len], tv.input);
     }
 }
+
+fn assert_sync<T: Sync>() {}
+
+#[test]
+fn chacha20poly1305_key_reuse() {
+    assert_sync::<ChaCha20Poly1305>();
+    assert_sync::<ChaCha20Poly1305Ietf>();
+    assert_sync::<XChaCha20Poly1305>();
+
+    // A single key object processes every message, whatever the order
+    let tv = &IETF_TEST_VECTORS[1];
+    let msg_len = tv.input.len();
+    let c = ChaCha20Poly1305Ietf::new(tv.key).unwrap();
+    let mut buf = [0u8; MAX_MSG_LEN];
+    let mut tag = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    for i in 0..4 {
+        nonce[0] = i;
+        c.encrypt(&nonce, tv.aad, tv.input, &mut buf[..msg_len], &mut tag)
+            .unwrap();
+        assert!(&buf[..msg_len] != tv.output);
+
+        let result = c.decrypt(tv.nonce, tv.aad, tv.output,
+                               &mut buf[..msg_len], tv.tag);
+        assert_eq!(result, Ok(()));
+        assert_eq!(&buf[..msg_len], tv.input);
+    }
+}
+
+#[test]
+fn chacha20poly1305_invalid_lengths() {
+    let tv = &IETF_TEST_VECTORS[0];
+    assert_eq!(ChaCha20Poly1305Ietf::new(&tv.key[..16]).err(),
+               Some(AeadError::InvalidKeyLength));
+    assert_eq!(XChaCha20Poly1305::new(&tv.key[..31]).err(),
+               Some(AeadError::InvalidKeyLength));
+
+    let msg_len = tv.input.len();
+    let mut output = [0u8; MAX_MSG_LEN];
+    let mut tag = [0u8; 16];
+    let c = ChaCha20Poly1305Ietf::new(tv.key).unwrap();
+    assert_eq!(c.encrypt(&tv.nonce[..8], tv.aad, tv.input,
+                         &mut output[..msg_len], &mut tag),
+               Err(AeadError::InvalidNonceLength));
+    assert_eq!(c.encrypt(tv.nonce, tv.aad, tv.input,
+                         &mut output[..msg_len - 1], &mut tag),
+               Err(AeadError::InvalidLength));
+    assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output,
+                         &mut output[..msg_len], &tv.tag[..15]),
+               Err(AeadError::InvalidLength));
+    assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output,
+                         &mut output[..msg_len + 1], tv.tag),
+               Err(AeadError::InvalidLength));
+
+    let c = XChaCha20Poly1305::new(tv.key).unwrap();
+    assert_eq!(c.encrypt(tv.nonce, tv.aad, tv.input,
+                         &mut output[..msg_len], &mut tag),
+               Err(AeadError::InvalidNonceLength));
+    let c = ChaCha20Poly1305::new(tv.key).unwrap();
+    assert_eq!(c.encrypt(tv.nonce, tv.aad, tv.input,
+                         &mut output[..msg_len], &mut tag),
+               Err(AeadError::InvalidNonceLength));
+
+    // The original construction requires full 16-byte tags as well
+    let tv = &TEST_VECTORS[0];
+    let c = ChaCha20Poly1305::new(tv.key).unwrap();
+    assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output,
+                         &mut output[..tv.input.len()], &[]),
+               Err(AeadError::InvalidLength));
+}
+
+#[test]
+fn chacha20poly1305_length_limit() {
+    // A longer message would exhaust the 32-bit block counter, which
+    // panics in ChaCha20. All three variants check the length first.
+    let max = MAX_PLAINTEXT_LEN as usize;
+    assert_eq!(check_length(max), Ok(()));
+    assert_eq!(check_length(max + 1), Err(AeadError::InvalidLength));
+    assert_eq!(check_length(!0), Err(AeadError::InvalidLength));
+}
+
+/// Room for the longest message and its tag, `MAX_MSG_LEN + 16`
+type TestBuffer = ArrayBuffer<U311>;
+
+#[test]
+fn chacha20poly1305_in_place_detached() {
+    for tv in TEST_VECTORS.iter().filter(|tv| tv.tag.len() == 16) {
+        let msg_len = tv.input.len();
+        let mut buf = [0u8; MAX_MSG_LEN];
+        buf[..msg_len].copy_from_slice(tv.input);
+        let c = ChaCha20Poly1305::new(tv.key).unwrap();
+        let tag = c.encrypt_in_place_detached(tv.nonce, tv.aad,
+                                              &mut buf[..msg_len]).unwrap();
+        assert_eq!(&buf[..msg_len], tv.output);
+        assert_eq!(&tag[..], tv.tag);
+
+        let result = c.decrypt_in_place_detached(tv.nonce, tv.aad,
+                                                 &mut buf[..msg_len], tv.tag);
+        assert_eq!(result, Ok(()));
+        assert_eq!(&buf[..msg_len], tv.input);
+    }
+
+    for tv in IETF_TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let mut buf = [0u8; MAX_MSG_LEN];
+        buf[..msg_len].copy_from_slice(tv.input);
+        let c = ChaCha20Poly1305Ietf::new(tv.key).unwrap();
+        let tag = c.encrypt_in_place_detached(tv.nonce, tv.aad,
+                                              &mut buf[..msg_len]).unwrap();
+        assert_eq!(&buf[..msg_len], tv.output);
+        assert_eq!(&tag[..], tv.tag);
+    }
+}
+
+#[test]
+fn chacha20poly1305_in_place_attached() {
+    for tv in XCHACHA_TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let mut buf = TestBuffer::from_slice(tv.input).unwrap();
+        let c = XChaCha20Poly1305::new(tv.key).unwrap();
+        c.encrypt_in_place(tv.nonce, tv.aad, &mut buf).unwrap();
+        assert_eq!(&buf.as_ref()[..msg_len], tv.output);
+        assert_eq!(&buf.as_ref()[msg_len..], tv.tag);
+
+        assert_eq!(c.decrypt_in_place(tv.nonce, tv.aad, &mut buf), Ok(()));
+        assert_eq!(buf.as_ref(), tv.input);
+    }
+
+    // A modified box is wiped and its tag removed
+    let tv = &XCHACHA_TEST_VECTORS[0];
+    let msg_len = tv.input.len();
+    let mut buf = TestBuffer::from_slice(tv.output).unwrap();
+    buf.extend_from_slice(tv.tag).unwrap();
+    buf.as_mut()[0] ^= 1;
+    let c = XChaCha20Poly1305::new(tv.key).unwrap();
+    assert_eq!(c.decrypt_in_place(tv.nonce, tv.aad, &mut buf),
+               Err(AeadError::AuthenticationFailed));
+    assert_eq!(buf.len(), msg_len);
+    assert!(buf.as_ref().iter().all(|&b| b == 0));
+
+    let mut buf = TestBuffer::from_slice(&tv.tag[..15]).unwrap();
+    assert_eq!(c.decrypt_in_place(tv.nonce, tv.aad, &mut buf),
+               Err(AeadError::InvalidLength));
+
+    // No room left for the tag
+    let mut buf = ArrayBuffer::<U16>::from_slice(&tv.input[..1]).unwrap();
+    assert_eq!(c.encrypt_in_place(tv.nonce, tv.aad, &mut buf),
+               Err(AeadError::InvalidLength));
+    assert_eq!(buf.len(), 1);
+    assert_eq!(ArrayBuffer::<U16>::from_slice(&tv.input[..17]).err(),
+               Some(AeadError::InvalidLength));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn chacha20poly1305_in_place_attached_vec() {
+    for tv in XCHACHA_TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let mut buf = tv.input.to_vec();
+        let c = XChaCha20Poly1305::new(tv.key).unwrap();
+        c.encrypt_in_place(tv.nonce, tv.aad, &mut buf).unwrap();
+        assert_eq!(&buf[..msg_len], tv.output);
+        assert_eq!(&buf[msg_len..], tv.tag);
+
+        assert_eq!(c.decrypt_in_place(tv.nonce, tv.aad, &mut buf), Ok(()));
+        assert_eq!(&buf[..], tv.input);
+
+        c.encrypt_in_place(tv.nonce, tv.aad, &mut buf).unwrap();
+        buf[msg_len] ^= 1;
+        assert_eq!(c.decrypt_in_place(tv.nonce, tv.aad, &mut buf),
+                   Err(AeadError::AuthenticationFailed));
+        assert_eq!(buf.len(), msg_len);
+        assert!(buf.iter().all(|&b| b == 0));
+    }
+}
+
+// STREAM over ChaCha20-Poly1305 (IETF) with 64-byte segments, generated with
+// the `cryptography` Python package by building the segment nonces by hand
+const STREAM_VECTOR: TestVector = TestVector {
+    name: "stream",
+    key: include_bytes!("data/stream.key.bin"),
+    nonce: include_bytes!("data/stream.nonce.bin"),
+    input: include_bytes!("data/stream.input.bin"),
+    output: include_bytes!("data/stream.output.bin"),
+    aad: &[],
+    tag: &[],
+};
+const SEGMENT_LEN: usize = 64;
+
+#[test]
+fn stream_encrypt() {
+    let tv = &STREAM_VECTOR;
+    let c = ChaCha20Poly1305Ietf::new(tv.key).unwrap();
+    let mut stream = StreamEncryptor::new(c, tv.nonce).unwrap();
+    let mut output = [0u8; MAX_MSG_LEN + 16];
+    let mut pos = 0;
+    let mut segments = tv.input.chunks(SEGMENT_LEN).peekable();
+    while let Some(segment) = segments.next() {
+        if segments.peek().is_some() {
+            let mut buf = [0u8; SEGMENT_LEN];
+            buf.copy_from_slice(segment);
+            let tag = stream.encrypt_next_in_place_detached(&[], &mut buf)
+                            .unwrap();
+            output[pos..pos + SEGMENT_LEN].copy_from_slice(&buf);
+            output[pos + SEGMENT_LEN..pos + SEGMENT_LEN + 16]
+                .copy_from_slice(&tag);
+            pos += SEGMENT_LEN + 16;
+        } else {
+            let mut buf = TestBuffer::from_slice(segment).unwrap();
+            stream.encrypt_last_in_place(&[], &mut buf).unwrap();
+            output[pos..pos + buf.len()].copy_from_slice(buf.as_ref());
+            pos += buf.len();
+            break;
+        }
+    }
+    assert_eq!(&output[..pos], tv.output);
+}
+
+#[test]
+fn stream_decrypt() {
+    let tv = &STREAM_VECTOR;
+    let c = ChaCha20Poly1305Ietf::new(tv.key).unwrap();
+    let mut stream = StreamDecryptor::new(c, tv.nonce).unwrap();
+    let mut segments = tv.output.chunks(SEGMENT_LEN + 16).peekable();
+    let mut expected = tv.input.chunks(SEGMENT_LEN);
+    while let Some(segment) = segments.next() {
+        let mut buf = TestBuffer::from_slice(segment).unwrap();
+        if segments.peek().is_some() {
+            assert_eq!(stream.decrypt_next_in_place(&[], &mut buf), Ok(()));
+        } else {
+            assert_eq!(stream.decrypt_last_in_place(&[], &mut buf), Ok(()));
+        }
+        assert_eq!(buf.as_ref(), expected.next().unwrap());
+    }
+
+    // Nothing can follow the final segment
+    let segment = &tv.output[..SEGMENT_LEN + 16];
+    let mut buf = TestBuffer::from_slice(segment).unwrap();
+    assert_eq!(stream.decrypt_next_in_place(&[], &mut buf),
+               Err(AeadError::InvalidLength));
+}
+
+#[test]
+fn stream_truncation_and_reordering() {
+    let tv = &STREAM_VECTOR;
+    let c = ChaCha20Poly1305Ietf::new(tv.key).unwrap();
+    let seg = |i: usize| {
+        let start = i * (SEGMENT_LEN + 16);
+        &tv.output[start..(start + SEGMENT_LEN + 16).min(tv.output.len())]
+    };
+
+    // Segments swapped
+    let mut stream = StreamDecryptor::new(c, tv.nonce).unwrap();
+    let mut buf = TestBuffer::from_slice(seg(1)).unwrap();
+    assert_eq!(stream.decrypt_next_in_place(&[], &mut buf),
+               Err(AeadError::AuthenticationFailed));
+    assert!(buf.as_ref().iter().all(|&b| b == 0));
+
+    // The failure did not move the decryptor forward
+    let mut buf = TestBuffer::from_slice(seg(0)).unwrap();
+    assert_eq!(stream.decrypt_next_in_place(&[], &mut buf), Ok(()));
+
+    // Truncated after the third segment, which is not marked as the last
+    let mut buf = TestBuffer::from_slice(seg(1)).unwrap();
+    assert_eq!(stream.decrypt_next_in_place(&[], &mut buf), Ok(()));
+    let mut buf = TestBuffer::from_slice(seg(2)).unwrap();
+    assert_eq!(stream.decrypt_last_in_place(&[], &mut buf),
+               Err(AeadError::AuthenticationFailed));
+
+    // The final segment cannot pass for an intermediate one
+    let mut buf = TestBuffer::from_slice(seg(2)).unwrap();
+    assert_eq!(stream.decrypt_next_in_place(&[], &mut buf), Ok(()));
+    let mut buf = TestBuffer::from_slice(seg(3)).unwrap();
+    assert_eq!(stream.decrypt_next_in_place(&[], &mut buf),
+               Err(AeadError::AuthenticationFailed));
+
+    // A prefix of the wrong length is caught by the AEAD
+    let mut stream = StreamEncryptor::new(c, &tv.nonce[..6]).unwrap();
+    assert_eq!(stream.encrypt_next_in_place_detached(&[], &mut []).err(),
+               Some(AeadError::InvalidNonceLength));
+    assert!(StreamEncryptor::new(c, &[0; 20]).is_err());
+}
@@ -0,0 +1,29 @@
+use test::Bencher;
+use crypto_aead::{Aead, NewAead};
+use super::DeoxysII256;
+
+#[bench]
+pub fn deoxys_ii_10(bh: & mut Bencher) {
+    let input = [1u8; 10];
+    let aad = [3u8; 10];
+    let c = DeoxysII256::new(&[0; 32]).unwrap();
+    bh.iter( || {
+        let mut output = [0u8; 10];
+        let mut tag = [0u8; 16];
+        c.encrypt(&[0; 15], &aad, &input, &mut output, &mut tag).unwrap();
+    });
+    bh.bytes = 10u64;
+}
+
+#[bench]
+pub fn deoxys_ii_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let aad = [3u8; 1024];
+    let c = DeoxysII256::new(&[0; 32]).unwrap();
+    bh.iter( || {
+        let mut output = [0u8; 1024];
+        let mut tag = [0u8; 16];
+        c.encrypt(&[0; 15], &aad, &input, &mut output, &mut tag).unwrap();
+    });
+    bh.bytes = 1024u64;
+}
@@ -0,0 +1,247 @@
+//! This is an implementation of Deoxys-II-256-128, the nonce misuse-resistant
+//! authenticated cipher of the Deoxys family [1], which was selected in the
+//! final portfolio of the CAESAR competition.
+//!
+//! Deoxys-II is built on the Deoxys-BC-384 tweakable block cipher: 16 AES
+//! rounds whose round keys are derived from a 256-bit key and a 128-bit
+//! tweak by the TWEAKEY schedule. The associated data and the message are
+//! authenticated by encrypting every block under a tweak made of a domain
+//! prefix and the block index and summing the results, and the encryption
+//! of this sum under the nonce is the tag. The message is then encrypted in
+//! counter mode, with the counter XORed into the tag. As in SIV, the tag
+//! depends on the whole message, so reusing a nonce only reveals whether two
+//! messages are identical instead of breaking confidentiality.
+//!
+//! Keys are 32 bytes, nonces 15 bytes and tags 16 bytes. The rounds are
+//! performed by `aes::aes_round`, eight blocks at a time when possible.
+//!
+//! [1] - "Deoxys v1.43", Jean, Nikolić, Peyrin and Seurin
+//!       <https://competitions.cr.yp.to/round3/deoxysv143.pdf>
+
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate generic_array;
+extern crate aes;
+extern crate crypto_aead;
+extern crate crypto_ops;
+
+use generic_array::GenericArray;
+use generic_array::typenum::U16;
+use aes::aes_round;
+use crypto_aead::{Aead, NewAead, AeadError, Tag};
+use crypto_ops::fixed_time_eq;
+
+const ROUNDS: usize = 16;
+/// Blocks are processed in batches of 8
+const BATCH: usize = 8 * 16;
+
+const RCON: [u8; ROUNDS + 1] = [0x2f, 0x5e, 0xbc, 0x63, 0xc6, 0x97, 0x35,
+                                0x6a, 0xd4, 0xb3, 0x7d, 0xfa, 0xef, 0xc5,
+                                0x91, 0x39, 0x72];
+
+/// The byte permutation `h` applied to every tweakey word between rounds
+const H: [usize; 16] = [1, 6, 11, 12, 5, 10, 15, 0, 9, 14, 3, 4, 13, 2, 7, 8];
+
+// Tweak prefixes of the different kinds of blocks
+const PREFIX_AAD: u8 = 0x20;
+const PREFIX_MSG: u8 = 0x00;
+/// Set on the prefix of a padded final block
+const PREFIX_PADDED: u8 = 0x40;
+const PREFIX_TAG: u8 = 0x10;
+const PREFIX_CTR: u8 = 0x80;
+
+fn xor(a: &mut [u8], b: &[u8]) {
+    for (a, b) in a.iter_mut().zip(b.iter()) {
+        *a ^= *b;
+    }
+}
+
+fn h(tk: &mut [u8]) {
+    let mut t = [0u8; 16];
+    for (t, &i) in t.iter_mut().zip(H.iter()) {
+        *t = tk[i];
+    }
+    tk.copy_from_slice(&t);
+}
+
+fn lfsr2(x: u8) -> u8 {
+    (x << 1) | (((x >> 7) ^ (x >> 5)) & 1)
+}
+
+fn lfsr3(x: u8) -> u8 {
+    (x >> 1) | (((x << 7) ^ (x << 1)) & 0x80)
+}
+
+/// Writes a tweak for block `index` with the given prefix
+fn block_tweak(prefix: u8, index: usize, tweak: &mut [u8]) {
+    for b in tweak.iter_mut() {
+        *b = 0;
+    }
+    tweak[0] = prefix;
+    let index = index as u64;
+    for (i, b) in tweak[8..].iter_mut().enumerate() {
+        *b = (index >> (56 - 8 * i)) as u8;
+    }
+}
+
+/// Deoxys-II-256-128
+#[derive(Clone, Copy)]
+pub struct DeoxysII256 {
+    /// `TK2 ^ TK3 ^ RC` for every round, the part of the subtweakeys which
+    /// does not depend on the tweak
+    subkeys: [[u8; 16]; ROUNDS + 1],
+}
+
+impl NewAead for DeoxysII256 {
+    fn new(key: &[u8]) -> Result<DeoxysII256, AeadError> {
+        if key.len() != 32 {
+            return Err(AeadError::InvalidKeyLength);
+        }
+        let mut tk2 = [0u8; 16];
+        let mut tk3 = [0u8; 16];
+        tk2.copy_from_slice(&key[16..]);
+        tk3.copy_from_slice(&key[..16]);
+        let mut subkeys = [[0u8; 16]; ROUNDS + 1];
+        for (subkey, &rcon) in subkeys.iter_mut().zip(RCON.iter()) {
+            subkey.copy_from_slice(&tk2);
+            xor(subkey, &tk3);
+            xor(subkey, &[1, 2, 4, 8, rcon, rcon, rcon, rcon]);
+            h(&mut tk2);
+            h(&mut tk3);
+            for (a, b) in tk2.iter_mut().zip(tk3.iter_mut()) {
+                *a = lfsr2(*a);
+                *b = lfsr3(*b);
+            }
+        }
+        Ok(DeoxysII256 { subkeys: subkeys })
+    }
+}
+
+impl DeoxysII256 {
+    /// Encrypts up to 8 blocks with Deoxys-BC-384, block `i` under the tweak
+    /// `tweaks[16 * i..16 * i + 16]`. The tweaks are overwritten.
+    fn encrypt_blocks(&self, tweaks: &mut [u8], blocks: &mut [u8]) {
+        let n = blocks.len();
+        let mut round_keys = [0u8; BATCH];
+        xor(blocks, tweaks);
+        for block in blocks.chunks_mut(16) {
+            xor(block, &self.subkeys[0]);
+        }
+        for subkey in self.subkeys[1..].iter() {
+            for (tweak, rk) in tweaks.chunks_mut(16)
+                                     .zip(round_keys.chunks_mut(16)) {
+                h(tweak);
+                rk.copy_from_slice(tweak);
+                xor(rk, subkey);
+            }
+            aes_round(blocks, &round_keys[..n]);
+        }
+    }
+
+    /// Adds the encryption of every block of `data` to `auth`, a final
+    /// partial block is padded with `10*`
+    fn authenticate(&self, prefix: u8, data: &[u8], auth: &mut [u8; 16]) {
+        let mut tweaks = [0u8; BATCH];
+        let mut blocks = [0u8; BATCH];
+        let full = data.len() / 16;
+        let (data, rest) = data.split_at(16 * full);
+        for (i, chunk) in data.chunks(BATCH).enumerate() {
+            let n = chunk.len();
+            for (j, tweak) in tweaks[..n].chunks_mut(16).enumerate() {
+                block_tweak(prefix, i * 8 + j, tweak);
+            }
+            blocks[..n].copy_from_slice(chunk);
+            self.encrypt_blocks(&mut tweaks[..n], &mut blocks[..n]);
+            for block in blocks[..n].chunks(16) {
+                xor(auth, block);
+            }
+        }
+        if rest.len() > 0 {
+            block_tweak(prefix | PREFIX_PADDED, full, &mut tweaks[..16]);
+            for b in blocks[..16].iter_mut() {
+                *b = 0;
+            }
+            blocks[..rest.len()].copy_from_slice(rest);
+            blocks[rest.len()] = 0x80;
+            self.encrypt_blocks(&mut tweaks[..16], &mut blocks[..16]);
+            xor(auth, &blocks[..16]);
+        }
+    }
+
+    fn tag(&self, nonce: &[u8], aad: &[u8], msg: &[u8]) -> [u8; 16] {
+        let mut auth = [0u8; 16];
+        self.authenticate(PREFIX_AAD, aad, &mut auth);
+        self.authenticate(PREFIX_MSG, msg, &mut auth);
+        let mut tweak = [0u8; 16];
+        tweak[0] = PREFIX_TAG;
+        tweak[1..].copy_from_slice(nonce);
+        self.encrypt_blocks(&mut tweak, &mut auth);
+        auth
+    }
+
+    /// Encrypts or decrypts `buf` in counter mode, block `j` is XORed with
+    /// the encryption of `0^8 || nonce` under the tweak
+    /// `(tag | 0x80 << 120) ^ j`
+    fn ctr(&self, nonce: &[u8], tag: &[u8], buf: &mut [u8]) {
+        let mut tweaks = [0u8; BATCH];
+        let mut blocks = [0u8; BATCH];
+        for (i, chunk) in buf.chunks_mut(BATCH).enumerate() {
+            let n = (chunk.len() + 15) & !15;
+            for (j, (tweak, block)) in tweaks[..n].chunks_mut(16)
+                                                  .zip(blocks.chunks_mut(16))
+                                                  .enumerate() {
+                block_tweak(0, i * 8 + j, tweak);
+                xor(tweak, tag);
+                tweak[0] |= PREFIX_CTR;
+                block[0] = 0;
+                block[1..].copy_from_slice(nonce);
+            }
+            self.encrypt_blocks(&mut tweaks[..n], &mut blocks[..n]);
+            xor(chunk, &blocks);
+        }
+    }
+}
+
+impl Aead for DeoxysII256 {
+    type TagSize = U16;
+
+    fn encrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8])
+                                 -> Result<Tag<U16>, AeadError> {
+        if nonce.len() != 15 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+        let tag = self.tag(nonce, aad, buf);
+        self.ctr(nonce, &tag, buf);
+        let mut out = GenericArray::new();
+        out.copy_from_slice(&tag);
+        Ok(out)
+    }
+
+    fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8], tag: &[u8])
+                                 -> Result<(), AeadError> {
+        if nonce.len() != 15 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+        if tag.len() != 16 {
+            return Err(AeadError::InvalidLength);
+        }
+        self.ctr(nonce, tag, buf);
+        if fixed_time_eq(&self.tag(nonce, aad, buf), tag) {
+            Ok(())
+        } else {
+            for b in buf.iter_mut() {
+                *b = 0;
+            }
+            Err(AeadError::AuthenticationFailed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod bench;
@@ -0,0 +1,188 @@
+use crypto_aead::{Aead, NewAead, AeadError};
+use crypto_aead::commit::{KeyCommitting, COMMITMENT_LEN};
+use super::DeoxysII256;
+
+struct TestVector {
+    pub name: &'static str,
+    pub key: &'static [u8],
+    pub nonce: &'static [u8],
+    pub input: &'static [u8],
+    pub output: &'static [u8],
+    pub aad: &'static [u8],
+    pub tag: &'static [u8],
+}
+
+macro_rules! get_vectors {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            TestVector {
+                name: $name,
+                key: include_bytes!(concat!("data/", $name, ".key.bin")),
+                nonce: include_bytes!(concat!("data/", $name, ".nonce.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                output: include_bytes!(concat!("data/", $name, ".output.bin")),
+                aad: include_bytes!(concat!("data/", $name, ".aad.bin")),
+                tag: include_bytes!(concat!("data/", $name, ".tag.bin")),
+            },
+        )*]
+    };
+}
+
+const MAX_MSG_LEN: usize = 200;
+
+// Known answer tests published with the reference implementation of the
+// Deoxys-II-256-128 CAESAR submission: an empty AAD and message, 32 bytes
+// of AAD only, a 32-byte message only, and 16 bytes of AAD with a 32-byte
+// message
+const REF_VECTORS: [TestVector; 4] = get_vectors!("ref_1", "ref_2", "ref_3",
+    "ref_4");
+
+// Generated with a Python model of the v1.43 specification, which matches
+// the vectors above. They cover partial blocks and messages longer than a
+// batch of 8 blocks.
+const GEN_VECTORS: [TestVector; 6] = get_vectors!("gen_1", "gen_2", "gen_3",
+    "gen_4", "gen_5", "gen_6");
+
+#[test]
+fn deoxys_ii_encrypt() {
+    for tv in REF_VECTORS.iter().chain(GEN_VECTORS.iter()) {
+        let msg_len = tv.input.len();
+        let c = DeoxysII256::new(tv.key).unwrap();
+        let mut output = [0u8; MAX_MSG_LEN];
+        let mut tag = [0u8; 16];
+        c.encrypt(tv.nonce, tv.aad, tv.input, &mut output[..msg_len],
+                  &mut tag).unwrap();
+        assert_eq!(&output[..msg_len], tv.output);
+        assert_eq!(&tag[..], tv.tag);
+    }
+}
+
+#[test]
+fn deoxys_ii_decrypt() {
+    for tv in REF_VECTORS.iter().chain(GEN_VECTORS.iter()) {
+        let msg_len = tv.input.len();
+        let c = DeoxysII256::new(tv.key).unwrap();
+        let mut output = [0u8; MAX_MSG_LEN];
+        assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output,
+                             &mut output[..msg_len], tv.tag),
+                   Ok(()));
+        assert_eq!(&output[..msg_len], tv.input);
+
+        let mut tag = [0u8; 16];
+        tag.copy_from_slice(tv.tag);
+        tag[0] ^= 1;
+        assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output,
+                             &mut output[..msg_len], &tag),
+                   Err(AeadError::AuthenticationFailed));
+        assert!(output[..msg_len].iter().all(|&b| b == 0));
+    }
+}
+
+// A ciphertext and tag which authenticate under two keys. The message
+// decrypts to 32 zero bytes under the first key. The AAD is made of 272
+// blocks, each chosen from two random candidates by solving the linear
+// system over GF(2) which makes the XOR of their encryptions match the tag
+// under both keys. Generated with the same Python model.
+const COLLISION_KEY1: &'static [u8] = include_bytes!("data/collision.key1.bin");
+const COLLISION_KEY2: &'static [u8] = include_bytes!("data/collision.key2.bin");
+const COLLISION_NONCE: &'static [u8] =
+    include_bytes!("data/collision.nonce.bin");
+const COLLISION_AAD: &'static [u8] = include_bytes!("data/collision.aad.bin");
+const COLLISION_OUTPUT: &'static [u8] =
+    include_bytes!("data/collision.output.bin");
+const COLLISION_TAG: &'static [u8] = include_bytes!("data/collision.tag.bin");
+
+#[test]
+fn deoxys_ii_key_collision() {
+    let n = COLLISION_OUTPUT.len();
+    let mut buf = [0u8; MAX_MSG_LEN];
+
+    // Deoxys-II alone accepts the ciphertext under both keys
+    for key in [COLLISION_KEY1, COLLISION_KEY2].iter() {
+        let c = DeoxysII256::new(key).unwrap();
+        assert_eq!(c.decrypt(COLLISION_NONCE, COLLISION_AAD, COLLISION_OUTPUT,
+                             &mut buf[..n], COLLISION_TAG),
+                   Ok(()));
+    }
+
+    // With the commitment it only decrypts under the first one
+    let c = KeyCommitting::<DeoxysII256>::new(COLLISION_KEY1).unwrap();
+    buf[..n].copy_from_slice(COLLISION_OUTPUT);
+    assert_eq!(c.decrypt_in_place_detached(COLLISION_NONCE, COLLISION_AAD,
+                                           &mut buf[..n], COLLISION_TAG),
+               Ok(()));
+    assert!(buf[..COMMITMENT_LEN].iter().all(|&b| b == 0));
+
+    let c = KeyCommitting::<DeoxysII256>::new(COLLISION_KEY2).unwrap();
+    buf[..n].copy_from_slice(COLLISION_OUTPUT);
+    assert_eq!(c.decrypt_in_place_detached(COLLISION_NONCE, COLLISION_AAD,
+                                           &mut buf[..n], COLLISION_TAG),
+               Err(AeadError::AuthenticationFailed));
+    assert!(buf[..n].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn deoxys_ii_nonce_reuse() {
+    // With a repeated nonce, a change in the last byte of the message still
+    // yields an unrelated tag and keystream
+    let tv = &GEN_VECTORS[5];
+    let msg_len = tv.input.len();
+    let mut input = [0u8; MAX_MSG_LEN];
+    let mut output = [0u8; MAX_MSG_LEN];
+    let mut tag = [0u8; 16];
+    input[..msg_len].copy_from_slice(tv.input);
+    input[msg_len - 1] ^= 1;
+    let c = DeoxysII256::new(tv.key).unwrap();
+    c.encrypt(tv.nonce, tv.aad, &input[..msg_len], &mut output[..msg_len],
+              &mut tag).unwrap();
+    assert!(tag[..] != tv.tag[..]);
+    assert!(output[..16] != tv.output[..16]);
+}
+
+#[test]
+fn deoxys_ii_key_committing() {
+    let tv = &GEN_VECTORS[1];
+    let len = COMMITMENT_LEN + tv.input.len();
+    let mut buf = [0u8; COMMITMENT_LEN + MAX_MSG_LEN];
+    buf[COMMITMENT_LEN..len].copy_from_slice(tv.input);
+    let c = KeyCommitting::<DeoxysII256>::new(tv.key).unwrap();
+    let tag = c.encrypt_in_place_detached(tv.nonce, tv.aad, &mut buf[..len])
+        .unwrap();
+    let ciphertext = buf;
+
+    assert_eq!(c.decrypt_in_place_detached(tv.nonce, tv.aad, &mut buf[..len],
+                                           &tag),
+               Ok(()));
+    assert_eq!(&buf[COMMITMENT_LEN..len], tv.input);
+
+    let c = KeyCommitting::<DeoxysII256>::new(GEN_VECTORS[0].key).unwrap();
+    buf = ciphertext;
+    assert_eq!(c.decrypt_in_place_detached(tv.nonce, tv.aad, &mut buf[..len],
+                                           &tag),
+               Err(AeadError::AuthenticationFailed));
+    assert!(buf[..len].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn deoxys_ii_invalid_lengths() {
+    let tv = &GEN_VECTORS[0];
+    let mut buf = [0u8; 4];
+    assert_eq!(DeoxysII256::new(&tv.key[..16]).err(),
+               Some(AeadError::InvalidKeyLength));
+
+    let c = DeoxysII256::new(tv.key).unwrap();
+    assert_eq!(c.encrypt_in_place_detached(&tv.nonce[1..], &[], &mut buf)
+                .err(),
+               Some(AeadError::InvalidNonceLength));
+    assert_eq!(c.encrypt_in_place_detached(&[0; 16], &[], &mut buf).err(),
+               Some(AeadError::InvalidNonceLength));
+    assert_eq!(c.decrypt_in_place_detached(tv.nonce, &[], &mut buf,
+                                           &tv.tag[..15]),
+               Err(AeadError::InvalidLength));
+}
+
+#[test]
+fn deoxys_ii_sync() {
+    fn assert_sync<T: Sync>() {}
+    assert_sync::<DeoxysII256>();
+}
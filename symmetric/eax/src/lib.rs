@@ -0,0 +1,182 @@
+//! This is an implementation of the EAX mode of operation of Bellare,
+//! Rogaway and Wagner [1], over any block cipher with a 128-bit block size.
+//!
+//! EAX combines CTR mode with OMAC (CMAC, see the `cmac` crate) under a
+//! single key. OMAC is tweaked by prefixing its input with a block holding
+//! 0, 1 or 2, which gives three independent functions:
+//!
+//! ```text
+//! N = OMAC_0(nonce), H = OMAC_1(aad)
+//! C = CTR(N, plaintext), tag = N ^ H ^ OMAC_2(C)
+//! ```
+//!
+//! The nonce may have any length. The tag size is a type parameter, `U16`
+//! by default, and tags truncated to 4 to 15 bytes are available as e.g.
+//! `Eax<Aes128, U8>`. Unlike CCM, EAX is online and the AAD can be
+//! processed independently of the message. A nonce must never be reused
+//! with the same key.
+//!
+//! [1] - "The EAX Mode of Operation"
+//!       <https://web.cs.ucdavis.edu/~rogaway/papers/eax.pdf>
+
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate generic_array;
+extern crate aes;
+extern crate blockmodes;
+extern crate cmac;
+extern crate crypto_aead;
+extern crate crypto_mac;
+extern crate crypto_ops;
+extern crate crypto_symmetric;
+
+use core::marker::PhantomData;
+use generic_array::{GenericArray, ArrayLength};
+use generic_array::typenum::{U4, U5, U6, U7, U8, U9, U10, U11, U12, U13, U14,
+                             U15, U16};
+use aes::{Aes128, Aes192, Aes256};
+use blockmodes::{CtrModeX8, Ctr128BE};
+use cmac::Cmac;
+use crypto_aead::{Aead, NewAead, AeadError, Tag};
+use crypto_mac::Mac;
+use crypto_ops::fixed_time_eq;
+use crypto_symmetric::{BlockCipher, BlockEncryptorX8,
+                       SynchronousStreamCipher};
+
+/// Tag sizes allowed by EAX, truncated to no less than 32 bits
+pub trait EaxTagSize: ArrayLength<u8> {}
+
+macro_rules! impl_tag_size {
+    ($($size:ident),*) => {
+        $( impl EaxTagSize for $size {} )*
+    }
+}
+
+impl_tag_size!(U4, U5, U6, U7, U8, U9, U10, U11, U12, U13, U14, U15, U16);
+
+/// EAX over a block cipher with a 128-bit block size and `TagSize` byte
+/// tags. Use `from_cipher`, or one of the AES aliases with `NewAead`.
+#[derive(Clone, Copy)]
+pub struct Eax<C, TagSize = U16> {
+    cipher: C,
+    /// Keyed CMAC state, cloned for each of the three tweaks
+    cmac: Cmac<C>,
+    tag_size: PhantomData<TagSize>,
+}
+
+pub type Aes128Eax = Eax<Aes128>;
+pub type Aes192Eax = Eax<Aes192>;
+pub type Aes256Eax = Eax<Aes256>;
+
+impl<C, TagSize> Eax<C, TagSize>
+    where C: BlockCipher<BlockSize = U16> + BlockEncryptorX8 + Clone,
+          TagSize: EaxTagSize
+{
+    /// Sets up EAX with an already keyed `cipher`
+    pub fn from_cipher(cipher: C) -> Eax<C, TagSize> {
+        Eax {
+            cmac: Cmac::new(cipher.clone()),
+            cipher: cipher,
+            tag_size: PhantomData,
+        }
+    }
+
+    /// OMAC tweaked by `t`, i.e. CMAC of the block `[t]_128` followed by
+    /// `data`
+    fn omac(&self, t: u8, data: &[u8]) -> [u8; 16] {
+        let mut tweak = [0u8; 16];
+        tweak[15] = t;
+        let mut cmac = self.cmac.clone();
+        cmac.input(&tweak);
+        cmac.input(data);
+        let mut out = [0u8; 16];
+        cmac.raw_result(&mut out);
+        out
+    }
+
+    /// XORs `buf` with the keystream of CTR mode, the counter starts at `n`
+    /// and is incremented as a 128-bit big-endian integer
+    fn ctr(&self, n: &[u8; 16], buf: &mut [u8]) {
+        let counter = GenericArray::from_slice(n);
+        let mut ctr: CtrModeX8<C, Ctr128BE> =
+            CtrModeX8::new(self.cipher.clone(), &counter);
+        let mut out = [0u8; 128];
+        for chunk in buf.chunks_mut(128) {
+            let out = &mut out[..chunk.len()];
+            ctr.process(chunk, out);
+            chunk.copy_from_slice(out);
+        }
+    }
+
+    /// Computes the tag from `N`, the AAD and the ciphertext
+    fn tag(&self, n: &[u8; 16], aad: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+        let h = self.omac(1, aad);
+        let mut tag = self.omac(2, ciphertext);
+        for ((t, n), h) in tag.iter_mut().zip(n.iter()).zip(h.iter()) {
+            *t ^= *n ^ *h;
+        }
+        tag
+    }
+}
+
+macro_rules! impl_new_aead {
+    ($cipher:ident, $key_size:expr) => {
+        impl<TagSize> NewAead for Eax<$cipher, TagSize>
+            where TagSize: EaxTagSize
+        {
+            fn new(key: &[u8]) -> Result<Eax<$cipher, TagSize>, AeadError> {
+                if key.len() != $key_size {
+                    return Err(AeadError::InvalidKeyLength);
+                }
+                Ok(Eax::from_cipher($cipher::new(key)))
+            }
+        }
+    }
+}
+
+impl_new_aead!(Aes128, 16);
+impl_new_aead!(Aes192, 24);
+impl_new_aead!(Aes256, 32);
+
+impl<C, TagSize> Aead for Eax<C, TagSize>
+    where C: BlockCipher<BlockSize = U16> + BlockEncryptorX8 + Clone,
+          TagSize: EaxTagSize
+{
+    type TagSize = TagSize;
+
+    fn encrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8])
+                                 -> Result<Tag<TagSize>, AeadError> {
+        let n = self.omac(0, nonce);
+        self.ctr(&n, buf);
+        let mut tag = GenericArray::new();
+        tag.copy_from_slice(&self.tag(&n, aad, buf)[..TagSize::to_usize()]);
+        Ok(tag)
+    }
+
+    fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8], tag: &[u8])
+                                 -> Result<(), AeadError> {
+        if tag.len() != TagSize::to_usize() {
+            return Err(AeadError::InvalidLength);
+        }
+        let n = self.omac(0, nonce);
+        let calc_tag = self.tag(&n, aad, buf);
+        if fixed_time_eq(&calc_tag[..tag.len()], tag) {
+            self.ctr(&n, buf);
+            Ok(())
+        } else {
+            for b in buf.iter_mut() {
+                *b = 0;
+            }
+            Err(AeadError::AuthenticationFailed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod bench;
@@ -0,0 +1,177 @@
+use aes::Aes128;
+use generic_array::GenericArray;
+use generic_array::typenum::{U4, U8};
+use crypto_symmetric::BlockCipher;
+use crypto_aead::{Aead, NewAead, AeadError};
+use super::{Eax, Aes128Eax};
+
+struct TestVector {
+    pub name: &'static str,
+    pub key: &'static [u8],
+    pub nonce: &'static [u8],
+    pub input: &'static [u8],
+    pub output: &'static [u8],
+    pub aad: &'static [u8],
+    pub tag: &'static [u8],
+}
+
+macro_rules! get_vectors {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            TestVector {
+                name: $name,
+                key: include_bytes!(concat!("data/", $name, ".key.bin")),
+                nonce: include_bytes!(concat!("data/", $name, ".nonce.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                output: include_bytes!(concat!("data/", $name, ".output.bin")),
+                aad: include_bytes!(concat!("data/", $name, ".aad.bin")),
+                tag: include_bytes!(concat!("data/", $name, ".tag.bin")),
+            },
+        )*]
+    };
+}
+
+const MAX_MSG_LEN: usize = 32;
+
+// Test vectors from appendix A of the EAX paper
+const TEST_VECTORS: [TestVector; 10] = get_vectors!("1", "2", "3", "4", "5",
+    "6", "7", "8", "9", "10");
+
+#[test]
+fn eax_encrypt() {
+    for tv in TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let eax = Aes128Eax::new(tv.key).unwrap();
+        let mut buf = [0u8; MAX_MSG_LEN];
+        buf[..msg_len].copy_from_slice(tv.input);
+        let tag = eax.encrypt_in_place_detached(tv.nonce, tv.aad,
+                                                &mut buf[..msg_len]).unwrap();
+        assert_eq!(&buf[..msg_len], tv.output);
+        assert_eq!(&tag[..], tv.tag);
+
+        // Truncated tags are prefixes of the full one
+        let eax = Eax::<Aes128, U8>::new(tv.key).unwrap();
+        let mut output = [0u8; MAX_MSG_LEN];
+        let mut short_tag = [0u8; 8];
+        eax.encrypt(tv.nonce, tv.aad, tv.input, &mut output[..msg_len],
+                    &mut short_tag).unwrap();
+        assert_eq!(&output[..msg_len], tv.output);
+        assert_eq!(&short_tag[..], &tv.tag[..8]);
+    }
+}
+
+#[test]
+fn eax_decrypt() {
+    for tv in TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let eax: Aes128Eax = Eax::from_cipher(Aes128::new(tv.key));
+        let mut buf = [0u8; MAX_MSG_LEN];
+        buf[..msg_len].copy_from_slice(tv.output);
+        assert_eq!(eax.decrypt_in_place_detached(tv.nonce, tv.aad,
+                                                 &mut buf[..msg_len], tv.tag),
+                   Ok(()));
+        assert_eq!(&buf[..msg_len], tv.input);
+
+        let eax = Eax::<Aes128, U4>::from_cipher(Aes128::new(tv.key));
+        buf[..msg_len].copy_from_slice(tv.output);
+        assert_eq!(eax.decrypt_in_place_detached(tv.nonce, tv.aad,
+                                                 &mut buf[..msg_len],
+                                                 &tv.tag[..4]),
+                   Ok(()));
+        assert_eq!(&buf[..msg_len], tv.input);
+    }
+}
+
+#[test]
+fn eax_decrypt_failure() {
+    for tv in TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let eax = Aes128Eax::new(tv.key).unwrap();
+        let mut tag = [0u8; 16];
+        tag.copy_from_slice(tv.tag);
+        tag[15] ^= 0x80;
+        let mut buf = [0u8; MAX_MSG_LEN];
+        buf[..msg_len].copy_from_slice(tv.output);
+        assert_eq!(eax.decrypt_in_place_detached(tv.nonce, tv.aad,
+                                                 &mut buf[..msg_len], &tag),
+                   Err(AeadError::AuthenticationFailed));
+        assert!(buf[..msg_len].iter().all(|&b| b == 0));
+
+        // The AAD and the nonce are authenticated
+        buf[..msg_len].copy_from_slice(tv.output);
+        assert_eq!(eax.decrypt_in_place_detached(tv.nonce, &[],
+                                                 &mut buf[..msg_len], tv.tag),
+                   Err(AeadError::AuthenticationFailed));
+        buf[..msg_len].copy_from_slice(tv.output);
+        assert_eq!(eax.decrypt_in_place_detached(&tv.nonce[1..], tv.aad,
+                                                 &mut buf[..msg_len], tv.tag),
+                   Err(AeadError::AuthenticationFailed));
+    }
+}
+
+#[test]
+fn eax_invalid_lengths() {
+    let tv = &TEST_VECTORS[1];
+    let mut buf = [0u8; 2];
+    assert_eq!(Aes128Eax::new(&tv.key[..15]).err(),
+               Some(AeadError::InvalidKeyLength));
+    let eax = Aes128Eax::new(tv.key).unwrap();
+    for len in [3, 4, 15].iter() {
+        assert_eq!(eax.decrypt_in_place_detached(tv.nonce, tv.aad, &mut buf,
+                                                 &tv.tag[..*len]),
+                   Err(AeadError::InvalidLength));
+    }
+    let mut tag = [0u8; 17];
+    assert_eq!(eax.encrypt(tv.nonce, tv.aad, tv.input, &mut buf, &mut tag),
+               Err(AeadError::InvalidLength));
+    assert_eq!(eax.encrypt(tv.nonce, tv.aad, tv.input, &mut buf,
+                           &mut tag[..8]),
+               Err(AeadError::InvalidLength));
+
+    // Only tags of exactly the configured size are accepted
+    let eax = Eax::<Aes128, U8>::new(tv.key).unwrap();
+    assert_eq!(eax.decrypt_in_place_detached(tv.nonce, tv.aad, &mut buf,
+                                             tv.tag),
+               Err(AeadError::InvalidLength));
+    assert_eq!(eax.encrypt(tv.nonce, tv.aad, tv.input, &mut buf,
+                           &mut tag[..16]),
+               Err(AeadError::InvalidLength));
+}
+
+#[test]
+fn eax_ctr_long_message() {
+    // Compare the 8-block CTR mode with one block at a time, from a counter
+    // which carries over several bytes
+    let cipher = Aes128::new(&[7; 16]);
+    let eax = Eax::<Aes128>::from_cipher(cipher);
+    let mut n = [0xff; 16];
+    n[0] = 0x42;
+    n[15] = 0xfa;
+    let mut buf = [0u8; 300];
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    let mut expected = buf;
+    let mut counter = GenericArray::from_slice(&n);
+    let mut keystream = GenericArray::new();
+    for chunk in expected.chunks_mut(16) {
+        cipher.encrypt_block(&counter, &mut keystream);
+        for (b, k) in chunk.iter_mut().zip(keystream.iter()) {
+            *b ^= *k;
+        }
+        for b in counter.iter_mut().rev() {
+            *b = b.wrapping_add(1);
+            if *b != 0 {
+                break;
+            }
+        }
+    }
+    eax.ctr(&n, &mut buf);
+    assert_eq!(&buf[..], &expected[..]);
+}
+
+#[test]
+fn eax_sync() {
+    fn assert_sync<T: Sync>() {}
+    assert_sync::<Aes128Eax>();
+}
@@ -0,0 +1,324 @@
+//! This is an implementation of the OCB3 authenticated encryption mode, as
+//! specified in RFC 7253 [1], over any block cipher with a 128-bit block size.
+//!
+//! OCB encrypts each full block as `Offset_i ^ E(K, P_i ^ Offset_i)`, so it
+//! needs a single block cipher call per block and the blocks are independent
+//! of each other, which lets them be processed eight at a time. The offsets
+//! are derived from the nonce and the table of values `L_i = dbl^(i+2)(L_*)`
+//! where `L_* = E(K, 0^128)`: moving to block `i` XORs `L_ntz(i)` into the
+//! offset. The table is computed once per key. A final partial block is
+//! XORed with a pad, and the tag is the encryption of the checksum of the
+//! plaintext combined with the hash of the AAD.
+//!
+//! Nonces are 1 to 15 bytes long, 12 bytes being the usual choice, and must
+//! never be reused with the same key. The tag length is part of the type, it
+//! is also mixed into the nonce so tags of different lengths are unrelated.
+//!
+//! [1] - "The OCB Authenticated-Encryption Algorithm"
+//!       <https://tools.ietf.org/html/rfc7253>
+
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate generic_array;
+extern crate aes;
+extern crate cmac;
+extern crate crypto_aead;
+extern crate crypto_ops;
+extern crate crypto_symmetric;
+
+use core::marker::PhantomData;
+use generic_array::{GenericArray, ArrayLength};
+use generic_array::typenum::{U1, U2, U3, U4, U5, U6, U7, U8, U9, U10, U11,
+                             U12, U13, U14, U15, U16};
+use aes::{Aes128, Aes192, Aes256};
+use cmac::dbl;
+use crypto_aead::{Aead, NewAead, AeadError, Tag};
+use crypto_ops::fixed_time_eq;
+use crypto_symmetric::{BlockCipher, BlockEncryptorX8, BlockDecryptorX8};
+
+/// Tag sizes allowed by OCB, 1 to 16 bytes
+pub trait OcbTagSize: ArrayLength<u8> {}
+
+macro_rules! impl_tag_size {
+    ($($size:ident),*) => {
+        $( impl OcbTagSize for $size {} )*
+    }
+}
+
+impl_tag_size!(U1, U2, U3, U4, U5, U6, U7, U8, U9, U10, U11, U12, U13, U14,
+               U15, U16);
+
+/// Size of the `L_i` table, enough for any block index up to 2^64
+const L_TABLE_SIZE: usize = 64;
+/// Blocks are processed in batches of 8
+const BATCH: usize = 8 * 16;
+
+fn xor(a: &mut [u8], b: &[u8]) {
+    for (a, b) in a.iter_mut().zip(b.iter()) {
+        *a ^= *b;
+    }
+}
+
+/// OCB3 over a block cipher with a 128-bit block size and `TagSize` byte
+/// tags. Use `from_cipher`, or the AES aliases with `NewAead`.
+#[derive(Clone, Copy)]
+pub struct Ocb3<C, TagSize = U16> {
+    cipher: C,
+    l_star: [u8; 16],
+    l_dollar: [u8; 16],
+    l: [[u8; 16]; L_TABLE_SIZE],
+    tag_size: PhantomData<TagSize>,
+}
+
+pub type Aes128Ocb3<TagSize = U16> = Ocb3<Aes128, TagSize>;
+pub type Aes192Ocb3<TagSize = U16> = Ocb3<Aes192, TagSize>;
+pub type Aes256Ocb3<TagSize = U16> = Ocb3<Aes256, TagSize>;
+
+impl<C, TagSize> Ocb3<C, TagSize>
+    where C: BlockCipher<BlockSize = U16> + BlockEncryptorX8 + BlockDecryptorX8,
+          TagSize: OcbTagSize
+{
+    /// Sets up OCB with an already keyed `cipher` and precomputes the `L`
+    /// table
+    pub fn from_cipher(cipher: C) -> Ocb3<C, TagSize> {
+        let mut ocb = Ocb3 {
+            cipher: cipher,
+            l_star: [0u8; 16],
+            l_dollar: [0u8; 16],
+            l: [[0u8; 16]; L_TABLE_SIZE],
+            tag_size: PhantomData,
+        };
+        let mut l_star = [0u8; 16];
+        ocb.encrypt_blocks(&mut l_star);
+        ocb.l_star = l_star;
+        ocb.l_dollar = dbl(&ocb.l_star);
+        ocb.l[0] = dbl(&ocb.l_dollar);
+        for i in 1..L_TABLE_SIZE {
+            ocb.l[i] = dbl(&ocb.l[i - 1]);
+        }
+        ocb
+    }
+
+    /// Encrypts a batch of 8 blocks, or fewer blocks one by one
+    fn encrypt_blocks(&self, buf: &mut [u8]) {
+        if buf.len() == BATCH {
+            let mut out = [0u8; BATCH];
+            self.cipher.encrypt_block_x8(buf, &mut out);
+            buf.copy_from_slice(&out);
+        } else {
+            let mut input = GenericArray::new();
+            let mut output = GenericArray::new();
+            for block in buf.chunks_mut(16) {
+                input.copy_from_slice(block);
+                self.cipher.encrypt_block(&input, &mut output);
+                block.copy_from_slice(&output);
+            }
+        }
+    }
+
+    /// Decrypts a batch of 8 blocks, or fewer blocks one by one
+    fn decrypt_blocks(&self, buf: &mut [u8]) {
+        if buf.len() == BATCH {
+            let mut out = [0u8; BATCH];
+            self.cipher.decrypt_block_x8(buf, &mut out);
+            buf.copy_from_slice(&out);
+        } else {
+            let mut input = GenericArray::new();
+            let mut output = GenericArray::new();
+            for block in buf.chunks_mut(16) {
+                input.copy_from_slice(block);
+                self.cipher.decrypt_block(&input, &mut output);
+                block.copy_from_slice(&output);
+            }
+        }
+    }
+
+    /// Writes the offsets of the next blocks into `offsets`, one per block,
+    /// and leaves `offset` and `index` at the last of them
+    fn next_offsets(&self, offset: &mut [u8; 16], index: &mut u64,
+                    offsets: &mut [u8]) {
+        for o in offsets.chunks_mut(16) {
+            *index += 1;
+            xor(offset, &self.l[index.trailing_zeros() as usize]);
+            o.copy_from_slice(offset);
+        }
+    }
+
+    /// Computes the initial offset from the nonce and the tag size
+    fn offset0(&self, nonce: &[u8]) -> Result<[u8; 16], AeadError> {
+        let n = nonce.len();
+        if n == 0 || n > 15 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+        // Nonce = num2str(TAGLEN mod 128, 7) || 0* || 1 || N
+        let mut block = [0u8; 16];
+        block[0] = (((TagSize::to_usize() * 8) % 128) << 1) as u8;
+        block[15 - n] |= 1;
+        block[16 - n..].copy_from_slice(nonce);
+        let bottom = (block[15] & 0x3f) as usize;
+        block[15] &= 0xc0;
+
+        // Stretch = Ktop || (Ktop[1..64] xor Ktop[9..72]), the offset is
+        // made of its bits bottom + 1 to bottom + 128
+        let mut stretch = [0u8; 24];
+        self.encrypt_blocks(&mut block);
+        stretch[..16].copy_from_slice(&block);
+        for i in 0..8 {
+            stretch[16 + i] = block[i] ^ block[i + 1];
+        }
+        let (bytes, bits) = (bottom / 8, bottom % 8);
+        let mut offset = [0u8; 16];
+        for (i, o) in offset.iter_mut().enumerate() {
+            *o = stretch[i + bytes] << bits;
+            if bits != 0 {
+                *o |= stretch[i + bytes + 1] >> (8 - bits);
+            }
+        }
+        Ok(offset)
+    }
+
+    /// HASH of the associated data
+    fn hash(&self, aad: &[u8]) -> [u8; 16] {
+        let mut sum = [0u8; 16];
+        let mut offset = [0u8; 16];
+        let mut index = 0;
+        let mut offsets = [0u8; BATCH];
+        let mut tmp = [0u8; BATCH];
+
+        let (blocks, rest) = aad.split_at(aad.len() - aad.len() % 16);
+        for chunk in blocks.chunks(BATCH) {
+            let n = chunk.len();
+            self.next_offsets(&mut offset, &mut index, &mut offsets[..n]);
+            tmp[..n].copy_from_slice(chunk);
+            xor(&mut tmp[..n], &offsets[..n]);
+            self.encrypt_blocks(&mut tmp[..n]);
+            for block in tmp[..n].chunks(16) {
+                xor(&mut sum, block);
+            }
+        }
+        if rest.len() > 0 {
+            let mut block = [0u8; 16];
+            block[..rest.len()].copy_from_slice(rest);
+            block[rest.len()] = 0x80;
+            xor(&mut offset, &self.l_star);
+            xor(&mut block, &offset);
+            self.encrypt_blocks(&mut block);
+            xor(&mut sum, &block);
+        }
+        sum
+    }
+
+    /// Encrypts or decrypts `buf` in place and returns the full tag, before
+    /// the hash of the AAD is added. The checksum is always computed over
+    /// the plaintext.
+    fn process(&self, nonce: &[u8], buf: &mut [u8], decrypt: bool)
+               -> Result<[u8; 16], AeadError> {
+        let mut offset = self.offset0(nonce)?;
+        let mut index = 0;
+        let mut checksum = [0u8; 16];
+        let mut offsets = [0u8; BATCH];
+
+        let full = buf.len() - buf.len() % 16;
+        let (blocks, rest) = buf.split_at_mut(full);
+        for chunk in blocks.chunks_mut(BATCH) {
+            let offsets = &mut offsets[..chunk.len()];
+            self.next_offsets(&mut offset, &mut index, offsets);
+            if !decrypt {
+                for block in chunk.chunks(16) {
+                    xor(&mut checksum, block);
+                }
+            }
+            xor(chunk, offsets);
+            if decrypt {
+                self.decrypt_blocks(chunk);
+            } else {
+                self.encrypt_blocks(chunk);
+            }
+            xor(chunk, offsets);
+            if decrypt {
+                for block in chunk.chunks(16) {
+                    xor(&mut checksum, block);
+                }
+            }
+        }
+        if rest.len() > 0 {
+            xor(&mut offset, &self.l_star);
+            let mut pad = offset;
+            self.encrypt_blocks(&mut pad);
+            if !decrypt {
+                xor(&mut checksum, rest);
+            }
+            xor(rest, &pad);
+            if decrypt {
+                xor(&mut checksum, rest);
+            }
+            checksum[rest.len()] ^= 0x80;
+        }
+
+        xor(&mut checksum, &offset);
+        xor(&mut checksum, &self.l_dollar);
+        self.encrypt_blocks(&mut checksum);
+        Ok(checksum)
+    }
+}
+
+macro_rules! impl_new_aead {
+    ($cipher:ident, $key_size:expr) => {
+        impl<TagSize> NewAead for Ocb3<$cipher, TagSize>
+            where TagSize: OcbTagSize
+        {
+            fn new(key: &[u8]) -> Result<Ocb3<$cipher, TagSize>, AeadError> {
+                if key.len() != $key_size {
+                    return Err(AeadError::InvalidKeyLength);
+                }
+                Ok(Ocb3::from_cipher($cipher::new(key)))
+            }
+        }
+    }
+}
+
+impl_new_aead!(Aes128, 16);
+impl_new_aead!(Aes192, 24);
+impl_new_aead!(Aes256, 32);
+
+impl<C, TagSize> Aead for Ocb3<C, TagSize>
+    where C: BlockCipher<BlockSize = U16> + BlockEncryptorX8 + BlockDecryptorX8,
+          TagSize: OcbTagSize
+{
+    type TagSize = TagSize;
+
+    fn encrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8])
+                                 -> Result<Tag<TagSize>, AeadError> {
+        let mut full_tag = self.process(nonce, buf, false)?;
+        xor(&mut full_tag, &self.hash(aad));
+        let mut tag = GenericArray::new();
+        tag.copy_from_slice(&full_tag[..TagSize::to_usize()]);
+        Ok(tag)
+    }
+
+    fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8], tag: &[u8])
+                                 -> Result<(), AeadError> {
+        if tag.len() != TagSize::to_usize() {
+            return Err(AeadError::InvalidLength);
+        }
+        let mut full_tag = self.process(nonce, buf, true)?;
+        xor(&mut full_tag, &self.hash(aad));
+        if fixed_time_eq(&full_tag[..tag.len()], tag) {
+            Ok(())
+        } else {
+            for b in buf.iter_mut() {
+                *b = 0;
+            }
+            Err(AeadError::AuthenticationFailed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod bench;
@@ -0,0 +1,197 @@
+use generic_array::typenum::{U4, U8, U12, U16};
+use aes::Aes128;
+use crypto_aead::{Aead, NewAead, AeadError};
+use super::{Ocb3, Aes128Ocb3, Aes192Ocb3, Aes256Ocb3};
+
+struct TestVector {
+    pub name: &'static str,
+    pub key: &'static [u8],
+    pub nonce: &'static [u8],
+    pub input: &'static [u8],
+    pub output: &'static [u8],
+    pub aad: &'static [u8],
+    pub tag: &'static [u8],
+}
+
+macro_rules! get_vectors {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            TestVector {
+                name: $name,
+                key: include_bytes!(concat!("data/", $name, ".key.bin")),
+                nonce: include_bytes!(concat!("data/", $name, ".nonce.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                output: include_bytes!(concat!("data/", $name, ".output.bin")),
+                aad: include_bytes!(concat!("data/", $name, ".aad.bin")),
+                tag: include_bytes!(concat!("data/", $name, ".tag.bin")),
+            },
+        )*]
+    };
+}
+
+macro_rules! check_vector {
+    ($ocb:ty, $tv:expr) => {{
+        let tv = $tv;
+        let msg_len = tv.input.len();
+        let c = <$ocb>::new(tv.key).unwrap();
+        let mut buf = [0u8; MAX_MSG_LEN];
+        buf[..msg_len].copy_from_slice(tv.input);
+        let tag = c.encrypt_in_place_detached(tv.nonce, tv.aad,
+                                              &mut buf[..msg_len]).unwrap();
+        assert_eq!(&buf[..msg_len], tv.output);
+        assert_eq!(&tag[..], tv.tag);
+
+        assert_eq!(c.decrypt_in_place_detached(tv.nonce, tv.aad,
+                                               &mut buf[..msg_len], tv.tag),
+                   Ok(()));
+        assert_eq!(&buf[..msg_len], tv.input);
+
+        let mut bad_tag = [0u8; 16];
+        bad_tag[..tv.tag.len()].copy_from_slice(tv.tag);
+        bad_tag[0] ^= 1;
+        buf[..msg_len].copy_from_slice(tv.output);
+        assert_eq!(c.decrypt_in_place_detached(tv.nonce, tv.aad,
+                                               &mut buf[..msg_len],
+                                               &bad_tag[..tv.tag.len()]),
+                   Err(AeadError::AuthenticationFailed));
+        assert!(buf[..msg_len].iter().all(|&b| b == 0));
+    }}
+}
+
+const MAX_MSG_LEN: usize = 1000;
+
+// RFC 7253, appendix A: the 16 sample results with 128-bit tags and the
+// one with a 96-bit tag
+const RFC_VECTORS: [TestVector; 17] = get_vectors!("rfc_1", "rfc_2", "rfc_3",
+    "rfc_4", "rfc_5", "rfc_6", "rfc_7", "rfc_8", "rfc_9", "rfc_10", "rfc_11",
+    "rfc_12", "rfc_13", "rfc_14", "rfc_15", "rfc_16", "rfc_17");
+
+// Generated with a reference implementation checked against the RFC. The
+// first one spans several batches of 8 blocks and has a 1-byte nonce.
+const GEN_VECTORS: [TestVector; 3] = get_vectors!("gen_1", "gen_2", "gen_3");
+
+#[test]
+fn ocb3_rfc7253_vectors() {
+    for tv in RFC_VECTORS[..16].iter() {
+        check_vector!(Aes128Ocb3, tv);
+    }
+    check_vector!(Aes128Ocb3<U12>, &RFC_VECTORS[16]);
+}
+
+#[test]
+fn ocb3_generated_vectors() {
+    check_vector!(Aes128Ocb3<U16>, &GEN_VECTORS[0]);
+    check_vector!(Aes256Ocb3<U8>, &GEN_VECTORS[1]);
+    check_vector!(Aes192Ocb3<U4>, &GEN_VECTORS[2]);
+}
+
+/// The iterative test of RFC 7253, appendix A: the tag over the outputs of
+/// 384 encryptions of growing length
+macro_rules! check_iterative {
+    ($ocb:ty, $key_size:expr, $tag_size:expr, $expected:expr) => {{
+        let mut key = [0u8; $key_size];
+        key[$key_size - 1] = 8 * $tag_size;
+        let c = <$ocb>::new(&key).unwrap();
+        let zeros = [0u8; 128];
+        let mut out = [0u8; 22400];
+        let mut pos = 0;
+        let mut nonce = [0u8; 12];
+        let mut counter = 0u16;
+        for i in 0..128 {
+            let s = &zeros[..i];
+            for &(aad, input) in [(s, s), (&[][..], s), (s, &[][..])].iter() {
+                counter += 1;
+                nonce[10] = (counter >> 8) as u8;
+                nonce[11] = counter as u8;
+                let (output, rest) = out[pos..].split_at_mut(input.len());
+                c.encrypt(&nonce, aad, input, output,
+                          &mut rest[..$tag_size]).unwrap();
+                pos += input.len() + $tag_size;
+            }
+        }
+        let mut tag = [0u8; $tag_size];
+        c.encrypt(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0x81], &out[..pos], &[],
+                  &mut [], &mut tag).unwrap();
+        assert_eq!(&tag[..], &$expected[..]);
+    }}
+}
+
+#[test]
+fn ocb3_rfc7253_iterative_128() {
+    check_iterative!(Aes128Ocb3<U16>, 16, 16,
+                     [0x67, 0xe9, 0x44, 0xd2, 0x32, 0x56, 0xc5, 0xe0,
+                      0xb6, 0xc6, 0x1f, 0xa2, 0x2f, 0xdf, 0x1e, 0xa2]);
+    check_iterative!(Aes192Ocb3<U16>, 24, 16,
+                     [0xf6, 0x73, 0xf2, 0xc3, 0xe7, 0x17, 0x4a, 0xae,
+                      0x7b, 0xae, 0x98, 0x6c, 0xa9, 0xf2, 0x9e, 0x17]);
+    check_iterative!(Aes256Ocb3<U16>, 32, 16,
+                     [0xd9, 0x0e, 0xb8, 0xe9, 0xc9, 0x77, 0xc8, 0x8b,
+                      0x79, 0xdd, 0x79, 0x3d, 0x7f, 0xfa, 0x16, 0x1c]);
+}
+
+#[test]
+fn ocb3_rfc7253_iterative_96() {
+    check_iterative!(Aes128Ocb3<U12>, 16, 12,
+                     [0x77, 0xa3, 0xd8, 0xe7, 0x35, 0x89, 0x15, 0x8d,
+                      0x25, 0xd0, 0x12, 0x09]);
+    check_iterative!(Aes192Ocb3<U12>, 24, 12,
+                     [0x05, 0xd5, 0x6e, 0xad, 0x27, 0x52, 0xc8, 0x6b,
+                      0xe6, 0x93, 0x2c, 0x5e]);
+    check_iterative!(Aes256Ocb3<U12>, 32, 12,
+                     [0x54, 0x58, 0x35, 0x9a, 0xc2, 0x3b, 0x0c, 0xba,
+                      0x9e, 0x63, 0x30, 0xdd]);
+}
+
+#[test]
+fn ocb3_rfc7253_iterative_64() {
+    check_iterative!(Aes128Ocb3<U8>, 16, 8,
+                     [0x19, 0x2c, 0x9b, 0x7b, 0xd9, 0x0b, 0xa0, 0x6a]);
+    check_iterative!(Aes192Ocb3<U8>, 24, 8,
+                     [0x00, 0x66, 0xbc, 0x6e, 0x0e, 0xf3, 0x4e, 0x24]);
+    check_iterative!(Aes256Ocb3<U8>, 32, 8,
+                     [0x7d, 0x4e, 0xa5, 0xd4, 0x45, 0x50, 0x1c, 0xbe]);
+}
+
+#[test]
+fn ocb3_from_cipher() {
+    let tv = &RFC_VECTORS[13];
+    let msg_len = tv.input.len();
+    let c: Ocb3<Aes128> = Ocb3::from_cipher(Aes128::new(tv.key));
+    let mut output = [0u8; MAX_MSG_LEN];
+    let mut tag = [0u8; 16];
+    c.encrypt(tv.nonce, tv.aad, tv.input, &mut output[..msg_len], &mut tag)
+        .unwrap();
+    assert_eq!(&output[..msg_len], tv.output);
+    assert_eq!(&tag[..], tv.tag);
+    assert_eq!(c.decrypt(tv.nonce, tv.aad, tv.output, &mut output[..msg_len],
+                         tv.tag),
+               Ok(()));
+    assert_eq!(&output[..msg_len], tv.input);
+}
+
+#[test]
+fn ocb3_invalid_lengths() {
+    let tv = &RFC_VECTORS[4];
+    let msg_len = tv.input.len();
+    let mut buf = [0u8; MAX_MSG_LEN];
+    assert_eq!(Aes128Ocb3::<U16>::new(&tv.key[..8]).err(),
+               Some(AeadError::InvalidKeyLength));
+
+    let c = Aes128Ocb3::<U16>::new(tv.key).unwrap();
+    assert_eq!(c.encrypt_in_place_detached(&[], tv.aad, &mut buf[..msg_len])
+                .err(),
+               Some(AeadError::InvalidNonceLength));
+    assert_eq!(c.encrypt_in_place_detached(&[0; 16], tv.aad,
+                                           &mut buf[..msg_len]).err(),
+               Some(AeadError::InvalidNonceLength));
+    assert_eq!(c.decrypt_in_place_detached(tv.nonce, tv.aad,
+                                           &mut buf[..msg_len],
+                                           &tv.tag[..12]),
+               Err(AeadError::InvalidLength));
+}
+
+#[test]
+fn ocb3_sync() {
+    fn assert_sync<T: Sync>() {}
+    assert_sync::<Aes128Ocb3>();
+}
@@ -106,6 +106,26 @@ impl Salsa20 {
         xsalsa20
     }
 
+    /// XORs the keystream into `buf`, equivalent to `process` with the
+    /// same slice as input and output
+    pub fn process_in_place(&mut self, buf: &mut [u8]) {
+        let len = buf.len();
+        let mut i = 0;
+        while i < len {
+            if self.offset == 64 {
+                self.hash();
+            }
+
+            let count = cmp::min(64 - self.offset, len - i);
+            let keystream = &self.output[self.offset..self.offset+count];
+            for (b, k) in buf[i..i+count].iter_mut().zip(keystream.iter()) {
+                *b ^= *k;
+            }
+            i += count;
+            self.offset += count;
+        }
+    }
+
     fn expand(key: &[u8], nonce: &[u8]) -> SalsaState {
         let constant = match key.len() {
             16 => b"expand 16-byte k",
@@ -69,3 +69,17 @@ fn salsa20_256bit_nacl_vector_2() {
 
     assert_eq!(hash, &buf[..]);
 }
+
+#[test]
+fn xsalsa20_process_in_place() {
+    let tests = get_tests!("xsalsa20_cryptopp");
+    let mut buf = [0u8; 139];
+    for test in tests.iter() {
+        let mut state = Salsa20::new_xsalsa20(test.key, test.nonce);
+        buf.copy_from_slice(test.input);
+        for chunk in buf.chunks_mut(50) {
+            state.process_in_place(chunk);
+        }
+        assert_eq!(test.output, &buf[..]);
+    }
+}
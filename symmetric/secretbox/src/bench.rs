@@ -0,0 +1,38 @@
+use test::Bencher;
+use super::{seal, open, TAG_SIZE};
+
+#[bench]
+pub fn secretbox_10(bh: & mut Bencher) {
+    let input = [1u8; 10];
+    let mut sealed = [0u8; 10 + TAG_SIZE];
+    let mut output = [0u8; 10];
+    bh.iter( || {
+        seal(&[0; 32], &[0; 24], &input, &mut sealed).unwrap();
+        open(&[0; 32], &[0; 24], &sealed, &mut output).unwrap();
+    });
+    bh.bytes = input.len() as u64;
+}
+
+#[bench]
+pub fn secretbox_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let mut sealed = [0u8; 1024 + TAG_SIZE];
+    let mut output = [0u8; 1024];
+    bh.iter( || {
+        seal(&[0; 32], &[0; 24], &input, &mut sealed).unwrap();
+        open(&[0; 32], &[0; 24], &sealed, &mut output).unwrap();
+    });
+    bh.bytes = input.len() as u64;
+}
+
+#[bench]
+pub fn secretbox_64k(bh: & mut Bencher) {
+    let input = [1u8; 65536];
+    let mut sealed = [0u8; 65536 + TAG_SIZE];
+    let mut output = [0u8; 65536];
+    bh.iter( || {
+        seal(&[0; 32], &[0; 24], &input, &mut sealed).unwrap();
+        open(&[0; 32], &[0; 24], &sealed, &mut output).unwrap();
+    });
+    bh.bytes = input.len() as u64;
+}
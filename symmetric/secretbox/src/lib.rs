@@ -0,0 +1,138 @@
+//! This is an implementation of the XSalsa20-Poly1305 authenticated
+//! encryption as used by NaCl's and libsodium's `crypto_secretbox`.
+//!
+//! The first 32 bytes of the XSalsa20 keystream are used as a one-time
+//! Poly1305 key, the message is encrypted with the keystream which follows
+//! and the resulting ciphertext is authenticated. `seal` and `open` use the
+//! "combined" layout of `crypto_secretbox_easy`, in which the 16 byte tag is
+//! prepended to the ciphertext.
+
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate generic_array;
+extern crate crypto_aead;
+extern crate crypto_symmetric;
+extern crate crypto_mac;
+extern crate crypto_ops;
+extern crate salsa20;
+extern crate poly1305;
+
+use generic_array::GenericArray;
+use generic_array::typenum::U16;
+use crypto_aead::{Aead, NewAead, AeadError, Tag};
+use crypto_symmetric::SynchronousStreamCipher;
+use crypto_mac::Mac;
+use crypto_ops::fixed_time_eq;
+use salsa20::Salsa20;
+use poly1305::Poly1305;
+
+/// Size of the authentication tag prepended by `seal`
+pub const TAG_SIZE: usize = 16;
+
+/// XSalsa20-Poly1305 keyed once for any number of boxes. There is no
+/// associated data in this construction, the `aad` argument of the `Aead`
+/// methods must be empty.
+#[derive(Clone, Copy)]
+pub struct XSalsa20Poly1305 {
+    key: [u8; 32],
+}
+
+impl NewAead for XSalsa20Poly1305 {
+    fn new(key: &[u8]) -> Result<XSalsa20Poly1305, AeadError> {
+        if key.len() != 32 {
+            return Err(AeadError::InvalidKeyLength);
+        }
+        let mut k = [0u8; 32];
+        k.copy_from_slice(key);
+        Ok(XSalsa20Poly1305 { key: k })
+    }
+}
+
+impl XSalsa20Poly1305 {
+    /// Sets up the cipher for `nonce` and derives the one-time Poly1305
+    /// key from the first 32 bytes of its keystream
+    fn start(&self, nonce: &[u8], aad: &[u8])
+             -> Result<(Salsa20, Poly1305), AeadError> {
+        if nonce.len() != 24 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+        if aad.len() != 0 {
+            return Err(AeadError::InvalidLength);
+        }
+
+        let mut cipher = Salsa20::new_xsalsa20(&self.key, nonce);
+        let mut mac_key = [0u8; 32];
+        let zero_key = [0u8; 32];
+        cipher.process(&zero_key, &mut mac_key);
+        Ok((cipher, Poly1305::new(&mac_key)))
+    }
+}
+
+impl Aead for XSalsa20Poly1305 {
+    type TagSize = U16;
+
+    fn encrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8])
+                                 -> Result<Tag<U16>, AeadError> {
+        let (mut cipher, mut mac) = self.start(nonce, aad)?;
+        cipher.process_in_place(buf);
+        mac.input(buf);
+        let mut tag = GenericArray::new();
+        mac.raw_result(&mut tag);
+        Ok(tag)
+    }
+
+    fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8], tag: &[u8])
+                                 -> Result<(), AeadError> {
+        if tag.len() != TAG_SIZE {
+            return Err(AeadError::InvalidLength);
+        }
+        let (mut cipher, mut mac) = self.start(nonce, aad)?;
+
+        mac.input(buf);
+        let mut calc_tag = [0u8; TAG_SIZE];
+        mac.raw_result(&mut calc_tag);
+        if fixed_time_eq(&calc_tag, tag) {
+            cipher.process_in_place(buf);
+            Ok(())
+        } else {
+            for b in buf.iter_mut() {
+                *b = 0;
+            }
+            Err(AeadError::AuthenticationFailed)
+        }
+    }
+}
+
+/// Encrypts and authenticates `input`, writing the tag followed by the
+/// ciphertext into `output`, which must be `TAG_SIZE` bytes longer than
+/// `input`. Equivalent to `crypto_secretbox_easy`.
+pub fn seal(key: &[u8], nonce: &[u8], input: &[u8], output: &mut [u8])
+            -> Result<(), AeadError> {
+    if output.len() != input.len() + TAG_SIZE {
+        return Err(AeadError::InvalidLength);
+    }
+    let (tag, ciphertext) = output.split_at_mut(TAG_SIZE);
+    XSalsa20Poly1305::new(key)?.encrypt(nonce, &[], input, ciphertext, tag)
+}
+
+/// Verifies and decrypts a box created by `seal`, writing the plaintext
+/// into `output`, which must be `TAG_SIZE` bytes shorter than `input`.
+/// If authentication fails `output` is zeroed. Equivalent to
+/// `crypto_secretbox_open_easy`.
+pub fn open(key: &[u8], nonce: &[u8], input: &[u8], output: &mut [u8])
+            -> Result<(), AeadError> {
+    if input.len() < TAG_SIZE || output.len() != input.len() - TAG_SIZE {
+        return Err(AeadError::InvalidLength);
+    }
+    let (tag, ciphertext) = input.split_at(TAG_SIZE);
+    XSalsa20Poly1305::new(key)?.decrypt(nonce, &[], ciphertext, output, tag)
+}
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod bench;
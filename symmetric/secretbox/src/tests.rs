@@ -0,0 +1,131 @@
+use super::{seal, open, XSalsa20Poly1305, TAG_SIZE};
+use crypto_aead::{Aead, NewAead, AeadError};
+
+struct TestVector {
+    pub name: &'static str,
+    pub key: &'static [u8],
+    pub nonce: &'static [u8],
+    pub input: &'static [u8],
+    pub output: &'static [u8],
+}
+
+macro_rules! get_vectors {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            TestVector {
+                name: $name,
+                key: include_bytes!(concat!("data/", $name, ".key.bin")),
+                nonce: include_bytes!(concat!("data/", $name, ".nonce.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                output: include_bytes!(concat!("data/", $name, ".output.bin")),
+            },
+        )*]
+    };
+}
+
+const MAX_MSG_LEN: usize = 1000;
+
+// Generated with libsodium 1.0.18 `crypto_secretbox_easy`, the first vector
+// uses the key and nonce from NaCl's secretbox test
+const TEST_VECTORS: [TestVector; 4] = get_vectors!("1", "2", "3", "4");
+
+#[test]
+fn secretbox_seal() {
+    for tv in TEST_VECTORS.iter() {
+        let out_len = tv.input.len() + TAG_SIZE;
+        let mut output = [0u8; MAX_MSG_LEN + TAG_SIZE];
+        seal(tv.key, tv.nonce, tv.input, &mut output[..out_len]).unwrap();
+        assert_eq!(&output[..out_len], tv.output);
+    }
+}
+
+#[test]
+fn secretbox_open() {
+    for tv in TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let mut output = [0u8; MAX_MSG_LEN];
+        assert_eq!(open(tv.key, tv.nonce, tv.output, &mut output[..msg_len]),
+                   Ok(()));
+        assert_eq!(&output[..msg_len], tv.input);
+    }
+}
+
+#[test]
+fn secretbox_open_forged() {
+    let mut forged = [0u8; MAX_MSG_LEN + TAG_SIZE];
+    for tv in TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let out_len = msg_len + TAG_SIZE;
+        let mut output = [0u8; MAX_MSG_LEN];
+        for i in 0..out_len {
+            forged[..out_len].copy_from_slice(tv.output);
+            forged[i] ^= 0x80;
+            for b in output.iter_mut() {
+                *b = 0xff;
+            }
+            assert_eq!(open(tv.key, tv.nonce, &forged[..out_len],
+                            &mut output[..msg_len]),
+                       Err(AeadError::AuthenticationFailed));
+            assert!(output[..msg_len].iter().all(|&b| b == 0));
+        }
+    }
+}
+
+#[test]
+fn xsalsa20poly1305_detached() {
+    for tv in TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let (tag, ciphertext) = tv.output.split_at(TAG_SIZE);
+        let c = XSalsa20Poly1305::new(tv.key).unwrap();
+        let mut output = [0u8; MAX_MSG_LEN];
+        assert_eq!(c.decrypt(tv.nonce, &[], ciphertext, &mut output[..msg_len],
+                             tag),
+                   Ok(()));
+        assert_eq!(&output[..msg_len], tv.input);
+    }
+}
+
+#[test]
+fn xsalsa20poly1305_in_place_detached() {
+    for tv in TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let (tag, ciphertext) = tv.output.split_at(TAG_SIZE);
+        let mut buf = [0u8; MAX_MSG_LEN];
+        buf[..msg_len].copy_from_slice(tv.input);
+        let c = XSalsa20Poly1305::new(tv.key).unwrap();
+        let calc_tag = c.encrypt_in_place_detached(tv.nonce, &[],
+                                                   &mut buf[..msg_len])
+                        .unwrap();
+        assert_eq!(&buf[..msg_len], ciphertext);
+        assert_eq!(&calc_tag[..], tag);
+
+        assert_eq!(c.decrypt_in_place_detached(tv.nonce, &[],
+                                               &mut buf[..msg_len], tag),
+                   Ok(()));
+        assert_eq!(&buf[..msg_len], tv.input);
+    }
+}
+
+#[test]
+fn secretbox_invalid_lengths() {
+    let tv = &TEST_VECTORS[0];
+    let msg_len = tv.input.len();
+    let mut output = [0u8; MAX_MSG_LEN + TAG_SIZE];
+    assert_eq!(seal(&tv.key[..16], tv.nonce, tv.input,
+                    &mut output[..msg_len + TAG_SIZE]),
+               Err(AeadError::InvalidKeyLength));
+    assert_eq!(seal(tv.key, &tv.nonce[..12], tv.input,
+                    &mut output[..msg_len + TAG_SIZE]),
+               Err(AeadError::InvalidNonceLength));
+    assert_eq!(seal(tv.key, tv.nonce, tv.input, &mut output[..msg_len]),
+               Err(AeadError::InvalidLength));
+    assert_eq!(open(tv.key, tv.nonce, &tv.output[..TAG_SIZE - 1], &mut []),
+               Err(AeadError::InvalidLength));
+    assert_eq!(open(tv.key, tv.nonce, tv.output, &mut output[..msg_len + 1]),
+               Err(AeadError::InvalidLength));
+
+    // NaCl boxes have no associated data
+    let c = XSalsa20Poly1305::new(tv.key).unwrap();
+    assert_eq!(c.encrypt_in_place_detached(tv.nonce, &[0], &mut output).err(),
+               Some(AeadError::InvalidLength));
+}
@@ -0,0 +1,44 @@
+use test::Bencher;
+use super::{PushStream, PullStream, ABYTES, TAG_MESSAGE};
+
+#[bench]
+pub fn secretstream_10(bh: & mut Bencher) {
+    let input = [1u8; 10];
+    let mut sealed = [0u8; 10 + ABYTES];
+    let mut output = [0u8; 10];
+    let mut push = PushStream::new(&[0; 32], &[0; 24]).unwrap();
+    let mut pull = PullStream::new(&[0; 32], &[0; 24]).unwrap();
+    bh.iter( || {
+        push.push(&[], &input, &mut sealed, TAG_MESSAGE).unwrap();
+        pull.pull(&[], &sealed, &mut output).unwrap();
+    });
+    bh.bytes = input.len() as u64;
+}
+
+#[bench]
+pub fn secretstream_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let mut sealed = [0u8; 1024 + ABYTES];
+    let mut output = [0u8; 1024];
+    let mut push = PushStream::new(&[0; 32], &[0; 24]).unwrap();
+    let mut pull = PullStream::new(&[0; 32], &[0; 24]).unwrap();
+    bh.iter( || {
+        push.push(&[], &input, &mut sealed, TAG_MESSAGE).unwrap();
+        pull.pull(&[], &sealed, &mut output).unwrap();
+    });
+    bh.bytes = input.len() as u64;
+}
+
+#[bench]
+pub fn secretstream_64k(bh: & mut Bencher) {
+    let input = [1u8; 65536];
+    let mut sealed = [0u8; 65536 + ABYTES];
+    let mut output = [0u8; 65536];
+    let mut push = PushStream::new(&[0; 32], &[0; 24]).unwrap();
+    let mut pull = PullStream::new(&[0; 32], &[0; 24]).unwrap();
+    bh.iter( || {
+        push.push(&[], &input, &mut sealed, TAG_MESSAGE).unwrap();
+        pull.pull(&[], &sealed, &mut output).unwrap();
+    });
+    bh.bytes = input.len() as u64;
+}
@@ -0,0 +1,276 @@
+//! This is an implementation of libsodium's
+//! `crypto_secretstream_xchacha20poly1305`, which encrypts a sequence of
+//! messages under a single key and detects dropped, duplicated, reordered
+//! and truncated messages.
+//!
+//! The sender picks a random 24-byte header. HChaCha20 of the key and the
+//! first 16 bytes of the header gives the stream key, the last 8 bytes
+//! become the initial nonce of ChaCha20 (IETF) together with a 32-bit
+//! counter starting at 1. Every message is encrypted as follows:
+//!
+//! * the first ChaCha20 block is the one-time Poly1305 key,
+//! * the message kind (one of the `TAG_*` constants) is encrypted at the
+//!   start of the second block, which is authenticated in full,
+//! * the message is encrypted from the third block on and authenticated
+//!   much like in RFC 8439, after the padded AAD and the kind block.
+//!
+//! The output is the encrypted kind byte, the ciphertext and the 16-byte
+//! Poly1305 tag, `ABYTES` bytes longer than the message. Afterwards the first
+//! 8 bytes of the tag are XORed into the nonce and the counter is
+//! incremented, so each message depends on all previous ones.
+//!
+//! The state is rekeyed from its own keystream when the counter wraps
+//! around, after a message marked with `TAG_REKEY` (or `TAG_FINAL`), or
+//! whenever both sides call `rekey` at the same point of the stream.
+
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate crypto_aead;
+extern crate crypto_bytes;
+extern crate crypto_mac;
+extern crate crypto_ops;
+extern crate chacha20;
+extern crate poly1305;
+
+pub use crypto_aead::AeadError;
+use crypto_bytes::{read_u32_le, write_u32_le, write_u64_le};
+use crypto_mac::Mac;
+use crypto_ops::fixed_time_eq;
+use chacha20::{ChaCha20, hchacha20};
+use poly1305::Poly1305;
+
+/// Size of the key
+pub const KEY_SIZE: usize = 32;
+/// Size of the header starting a stream
+pub const HEADER_SIZE: usize = 24;
+/// Number of bytes added to every message
+pub const ABYTES: usize = 17;
+
+/// An ordinary message
+pub const TAG_MESSAGE: u8 = 0;
+/// Marks the end of a group of messages, without changing the state
+pub const TAG_PUSH: u8 = 1;
+/// Rekeys the stream after this message
+pub const TAG_REKEY: u8 = 2;
+/// Marks the last message of the stream, it also rekeys
+pub const TAG_FINAL: u8 = TAG_PUSH | TAG_REKEY;
+
+/// Longest message accepted, the ChaCha20 counter must not wrap around
+pub const MESSAGE_MAX: u64 = 64 * ((1 << 32) - 2);
+
+/// Checks that a message is at most `MESSAGE_MAX` bytes and that its
+/// ciphertext is `ABYTES` bytes longer, for both directions
+fn check_lengths(msg_len: usize, ciphertext_len: usize)
+                 -> Result<(), AeadError> {
+    if msg_len as u64 > MESSAGE_MAX ||
+       msg_len.checked_add(ABYTES) != Some(ciphertext_len) {
+        Err(AeadError::InvalidLength)
+    } else {
+        Ok(())
+    }
+}
+
+/// Feeds zero bytes into `mac` until `len` is a multiple of 16
+fn pad16(mac: &mut Poly1305, len: usize) {
+    let rem = len % 16;
+    if rem != 0 {
+        let zeros = [0u8; 16];
+        mac.input(&zeros[..16 - rem]);
+    }
+}
+
+/// Pads the ciphertext the way libsodium does. Its padding length is
+/// `(16 - 64 + len) & 15`, i.e. `len % 16` bytes instead of the RFC 8439
+/// padding to a multiple of 16, and has to be reproduced for compatibility.
+fn pad_ciphertext(mac: &mut Poly1305, len: usize) {
+    let zeros = [0u8; 16];
+    mac.input(&zeros[..len % 16]);
+}
+
+/// Authenticates the lengths of the AAD and of the kind block and
+/// ciphertext, and writes the tag
+fn finish(mut mac: Poly1305, aad_len: usize, msg_len: usize, tag: &mut [u8]) {
+    let mut lens = [0u8; 16];
+    write_u64_le(&mut lens[..8], aad_len as u64);
+    write_u64_le(&mut lens[8..], 64 + msg_len as u64);
+    mac.input(&lens);
+    mac.raw_result(tag);
+}
+
+/// Key and nonce shared by both directions, the nonce is the counter
+/// followed by the 8 bytes derived from the header and previous tags
+#[derive(Clone)]
+struct State {
+    key: [u8; 32],
+    nonce: [u8; 12],
+}
+
+impl State {
+    fn new(key: &[u8], header: &[u8]) -> Result<State, AeadError> {
+        if key.len() != KEY_SIZE {
+            return Err(AeadError::InvalidKeyLength);
+        }
+        if header.len() != HEADER_SIZE {
+            return Err(AeadError::InvalidNonceLength);
+        }
+        let mut state = State {
+            key: hchacha20(key, &header[..16]),
+            nonce: [0u8; 12],
+        };
+        state.nonce[4..].copy_from_slice(&header[16..]);
+        write_u32_le(&mut state.nonce[..4], 1);
+        Ok(state)
+    }
+
+    /// Sets up ChaCha20 and Poly1305 for the next message and authenticates
+    /// the AAD, the keystream is left at the start of the kind block
+    fn start(&self, aad: &[u8]) -> (ChaCha20, Poly1305) {
+        let mut cipher = ChaCha20::new(&self.key, &self.nonce);
+        let mut mac_key = [0u8; 64];
+        cipher.process_in_place(&mut mac_key);
+        let mut mac = Poly1305::new(&mac_key[..32]);
+        mac.input(aad);
+        pad16(&mut mac, aad.len());
+        (cipher, mac)
+    }
+
+    /// Mixes the tag of the message into the nonce and moves to the next
+    /// message
+    fn advance(&mut self, tag: &[u8], kind: u8) {
+        for (n, t) in self.nonce[4..].iter_mut().zip(tag.iter()) {
+            *n ^= *t;
+        }
+        let counter = read_u32_le(&self.nonce[..4]).wrapping_add(1);
+        write_u32_le(&mut self.nonce[..4], counter);
+        if kind & TAG_REKEY != 0 || counter == 0 {
+            self.rekey();
+        }
+    }
+
+    /// Replaces the key and the nonce with the encryption of themselves
+    fn rekey(&mut self) {
+        let mut buf = [0u8; 40];
+        buf[..32].copy_from_slice(&self.key);
+        buf[32..].copy_from_slice(&self.nonce[4..]);
+        ChaCha20::new(&self.key, &self.nonce).process_in_place(&mut buf);
+        self.key.copy_from_slice(&buf[..32]);
+        self.nonce[4..].copy_from_slice(&buf[32..]);
+        write_u32_le(&mut self.nonce[..4], 1);
+    }
+}
+
+/// The sending side of a stream. It is neither `Copy` nor `Clone`: two
+/// copies would encrypt different messages under the same key and nonce.
+pub struct PushStream {
+    state: State,
+    header: [u8; HEADER_SIZE],
+}
+
+impl PushStream {
+    /// Starts a stream. `random` must be `HEADER_SIZE` bytes from a secure
+    /// random number generator, they become the header which has to be sent
+    /// to the receiver before the messages.
+    pub fn new(key: &[u8], random: &[u8]) -> Result<PushStream, AeadError> {
+        let state = State::new(key, random)?;
+        let mut header = [0u8; HEADER_SIZE];
+        header.copy_from_slice(random);
+        Ok(PushStream { state: state, header: header })
+    }
+
+    /// The header which starts the stream
+    pub fn header(&self) -> &[u8; HEADER_SIZE] {
+        &self.header
+    }
+
+    /// Encrypts the next message, `output` must be `ABYTES` bytes longer
+    /// than `input`. `kind` is one of the `TAG_*` constants and is
+    /// returned to the receiver along with the message.
+    pub fn push(&mut self, aad: &[u8], input: &[u8], output: &mut [u8],
+                kind: u8) -> Result<(), AeadError> {
+        check_lengths(input.len(), output.len())?;
+        let (mut cipher, mut mac) = self.state.start(aad);
+
+        let mut block = [0u8; 64];
+        block[0] = kind;
+        cipher.process_in_place(&mut block);
+        mac.input(&block);
+        output[0] = block[0];
+
+        let (ciphertext, tag) = output[1..].split_at_mut(input.len());
+        ciphertext.copy_from_slice(input);
+        cipher.process_in_place(ciphertext);
+        mac.input(ciphertext);
+        pad_ciphertext(&mut mac, ciphertext.len());
+        finish(mac, aad.len(), ciphertext.len(), tag);
+
+        self.state.advance(tag, kind);
+        Ok(())
+    }
+
+    /// Derives a new key from the current state. The receiver has to call
+    /// `rekey` after the same message.
+    pub fn rekey(&mut self) {
+        self.state.rekey();
+    }
+}
+
+/// The receiving side of a stream
+#[derive(Clone)]
+pub struct PullStream {
+    state: State,
+}
+
+impl PullStream {
+    /// Starts reading the stream which begins with `header`
+    pub fn new(key: &[u8], header: &[u8]) -> Result<PullStream, AeadError> {
+        Ok(PullStream { state: State::new(key, header)? })
+    }
+
+    /// Decrypts the next message into `output`, which must be `ABYTES`
+    /// bytes shorter than `input`, and returns its kind. On failure `output`
+    /// is zeroed and the state is unchanged.
+    ///
+    /// A stream which ends without a message of kind `TAG_FINAL` has been
+    /// truncated.
+    pub fn pull(&mut self, aad: &[u8], input: &[u8], output: &mut [u8])
+                -> Result<u8, AeadError> {
+        check_lengths(output.len(), input.len())?;
+        let (mut cipher, mut mac) = self.state.start(aad);
+
+        let mut block = [0u8; 64];
+        block[0] = input[0];
+        cipher.process_in_place(&mut block);
+        let kind = block[0];
+        block[0] = input[0];
+        mac.input(&block);
+
+        let (ciphertext, tag) = input[1..].split_at(output.len());
+        mac.input(ciphertext);
+        pad_ciphertext(&mut mac, ciphertext.len());
+        let mut calc_tag = [0u8; 16];
+        finish(mac, aad.len(), ciphertext.len(), &mut calc_tag);
+        if !fixed_time_eq(&calc_tag, tag) {
+            for b in output.iter_mut() {
+                *b = 0;
+            }
+            return Err(AeadError::AuthenticationFailed);
+        }
+
+        output.copy_from_slice(ciphertext);
+        cipher.process_in_place(output);
+        self.state.advance(tag, kind);
+        Ok(kind)
+    }
+
+    /// Derives a new key, matching a `rekey` call of the sender
+    pub fn rekey(&mut self) {
+        self.state.rekey();
+    }
+}
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(test)]
+mod bench;
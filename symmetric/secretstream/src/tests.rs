@@ -0,0 +1,164 @@
+use super::{PushStream, PullStream, AeadError, ABYTES, TAG_MESSAGE, TAG_PUSH,
+            TAG_REKEY, TAG_FINAL, MESSAGE_MAX, check_lengths};
+
+struct TestVector {
+    pub name: &'static str,
+    pub input: &'static [u8],
+    pub aad: &'static [u8],
+    pub output: &'static [u8],
+    pub kind: u8,
+    /// Both sides call `rekey` before this message
+    pub rekey: bool,
+}
+
+macro_rules! get_vectors {
+    ( $( ($name:expr, $kind:expr, $rekey:expr) ),*  ) => {
+        [$(
+            TestVector {
+                name: $name,
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                aad: include_bytes!(concat!("data/", $name, ".aad.bin")),
+                output: include_bytes!(concat!("data/", $name, ".output.bin")),
+                kind: $kind,
+                rekey: $rekey,
+            },
+        )*]
+    };
+}
+
+const MAX_MSG_LEN: usize = 250;
+
+// A single stream generated with libsodium 1.0.18
+// `crypto_secretstream_xchacha20poly1305_push`, the header comes from
+// `crypto_secretstream_xchacha20poly1305_init_push`
+const KEY: &'static [u8] = include_bytes!("data/key.bin");
+const HEADER: &'static [u8] = include_bytes!("data/header.bin");
+
+const TEST_VECTORS: [TestVector; 6] = get_vectors!(
+    ("1", TAG_MESSAGE, false), ("2", TAG_MESSAGE, false), ("3", TAG_PUSH, false),
+    ("4", TAG_REKEY, false), ("5", TAG_MESSAGE, true), ("6", TAG_FINAL, false));
+
+#[test]
+fn secretstream_push() {
+    let mut stream = PushStream::new(KEY, HEADER).unwrap();
+    assert_eq!(&stream.header()[..], HEADER);
+    let mut output = [0u8; MAX_MSG_LEN + ABYTES];
+    for tv in TEST_VECTORS.iter() {
+        let out_len = tv.input.len() + ABYTES;
+        if tv.rekey {
+            stream.rekey();
+        }
+        stream.push(tv.aad, tv.input, &mut output[..out_len], tv.kind)
+              .unwrap();
+        assert_eq!(&output[..out_len], tv.output);
+    }
+}
+
+#[test]
+fn secretstream_pull() {
+    let mut stream = PullStream::new(KEY, HEADER).unwrap();
+    let mut output = [0u8; MAX_MSG_LEN];
+    for tv in TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        if tv.rekey {
+            stream.rekey();
+        }
+        assert_eq!(stream.pull(tv.aad, tv.output, &mut output[..msg_len]),
+                   Ok(tv.kind));
+        assert_eq!(&output[..msg_len], tv.input);
+    }
+}
+
+#[test]
+fn secretstream_pull_forged() {
+    let mut forged = [0u8; MAX_MSG_LEN + ABYTES];
+    let mut output = [0u8; MAX_MSG_LEN];
+    let mut stream = PullStream::new(KEY, HEADER).unwrap();
+    for tv in TEST_VECTORS.iter() {
+        let msg_len = tv.input.len();
+        let out_len = msg_len + ABYTES;
+        if tv.rekey {
+            stream.rekey();
+        }
+        for i in 0..out_len {
+            forged[..out_len].copy_from_slice(tv.output);
+            forged[i] ^= 0x80;
+            for b in output.iter_mut() {
+                *b = 0xff;
+            }
+            assert_eq!(stream.pull(tv.aad, &forged[..out_len],
+                                   &mut output[..msg_len]),
+                       Err(AeadError::AuthenticationFailed));
+            assert!(output[..msg_len].iter().all(|&b| b == 0));
+        }
+
+        // A failure leaves the state untouched
+        assert_eq!(stream.pull(tv.aad, tv.output, &mut output[..msg_len]),
+                   Ok(tv.kind));
+    }
+}
+
+#[test]
+fn secretstream_out_of_order() {
+    let mut output = [0u8; MAX_MSG_LEN];
+    let pull = |stream: &mut PullStream, tv: &TestVector, output: &mut [u8]| {
+        stream.pull(tv.aad, tv.output, &mut output[..tv.input.len()])
+    };
+
+    // Dropped message
+    let mut stream = PullStream::new(KEY, HEADER).unwrap();
+    assert_eq!(pull(&mut stream, &TEST_VECTORS[1], &mut output),
+               Err(AeadError::AuthenticationFailed));
+
+    // Replayed message
+    assert_eq!(pull(&mut stream, &TEST_VECTORS[0], &mut output),
+               Ok(TAG_MESSAGE));
+    assert_eq!(pull(&mut stream, &TEST_VECTORS[0], &mut output),
+               Err(AeadError::AuthenticationFailed));
+
+    // Different AAD
+    let tv = &TEST_VECTORS[1];
+    assert_eq!(stream.pull(&tv.aad[1..], tv.output,
+                           &mut output[..tv.input.len()]),
+               Err(AeadError::AuthenticationFailed));
+
+    // Missing explicit rekey
+    for tv in TEST_VECTORS[1..4].iter() {
+        assert_eq!(pull(&mut stream, tv, &mut output), Ok(tv.kind));
+    }
+    assert_eq!(pull(&mut stream, &TEST_VECTORS[4], &mut output),
+               Err(AeadError::AuthenticationFailed));
+
+    // A stream from another header
+    let mut header = [0u8; 24];
+    header.copy_from_slice(HEADER);
+    header[23] ^= 1;
+    let mut stream = PullStream::new(KEY, &header).unwrap();
+    assert_eq!(pull(&mut stream, &TEST_VECTORS[0], &mut output),
+               Err(AeadError::AuthenticationFailed));
+}
+
+#[test]
+fn secretstream_invalid_lengths() {
+    assert_eq!(PushStream::new(&KEY[..16], HEADER).err(),
+               Some(AeadError::InvalidKeyLength));
+    assert_eq!(PullStream::new(KEY, &HEADER[..16]).err(),
+               Some(AeadError::InvalidNonceLength));
+
+    let mut output = [0u8; ABYTES + 1];
+    let mut stream = PushStream::new(KEY, HEADER).unwrap();
+    assert_eq!(stream.push(&[], &[0], &mut output[..ABYTES], TAG_MESSAGE),
+               Err(AeadError::InvalidLength));
+    let mut stream = PullStream::new(KEY, HEADER).unwrap();
+    assert_eq!(stream.pull(&[], &output[..ABYTES - 1], &mut []),
+               Err(AeadError::InvalidLength));
+    assert_eq!(stream.pull(&[], &output, &mut []),
+               Err(AeadError::InvalidLength));
+
+    // Longer messages would wrap the ChaCha20 counter, in either direction
+    let max = MESSAGE_MAX as usize;
+    assert_eq!(check_lengths(max, max + ABYTES), Ok(()));
+    assert_eq!(check_lengths(max + 1, max + 1 + ABYTES),
+               Err(AeadError::InvalidLength));
+    assert_eq!(check_lengths(!0, ABYTES - 1), Err(AeadError::InvalidLength));
+}
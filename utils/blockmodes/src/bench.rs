@@ -0,0 +1,77 @@
+use test::Bencher;
+use generic_array::GenericArray;
+use crypto_buffers::{RefReadBuffer, RefWriteBuffer};
+use crypto_symmetric::Encryptor;
+use aes::Aes128;
+use super::{CbcEncryptor, Cfb, Ofb, CtrMode, CtrModeX8, Xts, NoPadding};
+
+#[bench]
+pub fn aes128_cbc_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let mut output = [0u8; 1024];
+    let iv = GenericArray::new();
+    let mut e = CbcEncryptor::new(Aes128::new(&[0; 16]), NoPadding, &iv);
+    bh.iter( || {
+        e.reset(&iv);
+        let mut rb = RefReadBuffer::new(&input);
+        let mut wb = RefWriteBuffer::new(&mut output);
+        e.encrypt(&mut rb, &mut wb, true).unwrap();
+    });
+    bh.bytes = 1024u64;
+}
+
+#[bench]
+pub fn aes128_cfb_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let mut output = [0u8; 1024];
+    let mut e = Cfb::new(Aes128::new(&[0; 16]), &GenericArray::new());
+    bh.iter( || {
+        let mut rb = RefReadBuffer::new(&input);
+        let mut wb = RefWriteBuffer::new(&mut output);
+        e.encrypt(&mut rb, &mut wb, true).unwrap();
+    });
+    bh.bytes = 1024u64;
+}
+
+#[bench]
+pub fn aes128_ofb_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let mut output = [0u8; 1024];
+    let mut c = Ofb::new(Aes128::new(&[0; 16]), &GenericArray::new());
+    bh.iter( || {
+        c.process(&input, &mut output);
+    });
+    bh.bytes = 1024u64;
+}
+
+#[bench]
+pub fn aes128_ctr_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let mut output = [0u8; 1024];
+    let mut c = CtrMode::new(Aes128::new(&[0; 16]), &GenericArray::new());
+    bh.iter( || {
+        c.process(&input, &mut output);
+    });
+    bh.bytes = 1024u64;
+}
+
+#[bench]
+pub fn aes128_ctr_x8_1k(bh: & mut Bencher) {
+    let input = [1u8; 1024];
+    let mut output = [0u8; 1024];
+    let mut c = CtrModeX8::new(Aes128::new(&[0; 16]), &GenericArray::new());
+    bh.iter( || {
+        c.process(&input, &mut output);
+    });
+    bh.bytes = 1024u64;
+}
+
+#[bench]
+pub fn aes128_xts_512(bh: & mut Bencher) {
+    let mut sector = [1u8; 512];
+    let xts = Xts::new(Aes128::new(&[0; 16]), Aes128::new(&[1; 16]));
+    bh.iter( || {
+        xts.encrypt_sector(7, &mut sector).unwrap();
+    });
+    bh.bytes = 512u64;
+}
@@ -1,35 +1,51 @@
-#![no_std]
-
-// TODO - Optimize the XORs
-// TODO - Maybe use macros to specialize BlockEngine for encryption or decryption?
-// TODO - I think padding could be done better. Maybe macros for BlockEngine
-// would help this too.
+//! Block cipher modes of operation over any `crypto_symmetric::BlockCipher`.
+//!
+//! ECB, CBC and PCBC are driven by a `BlockEngine`, which buffers partial
+//! blocks and handles padding through a `PaddingProcessor`. The CFB variants
+//! encrypt and decrypt inputs of any length through `Encryptor` and
+//! `Decryptor`, while OFB and CTR turn the cipher into a
+//! `SynchronousStreamCipher`. The counter of CTR can be any of the
+//! `CtrFlavor`s and its keystream is seekable. CBC with ciphertext stealing
+//! and XTS encrypt whole messages, or disk sectors, in place. All buffers are
+//! sized by the block size of the cipher and live inline, so the modes need
+//! neither `std` nor an allocator.
 
-extern crate crypto_symmetric;
-extern crate crypto_bytes;
+#![no_std]
+#![feature(test)]
+extern crate test;
+extern crate generic_array;
 extern crate crypto_buffers;
+extern crate crypto_symmetric;
+#[cfg(test)]
+extern crate aes;
 
-use std::cmp;
-use std::iter::repeat;
-
-use buffer::{ReadBuffer, WriteBuffer, BufferResult, RefReadBuffer, RefWriteBuffer,
-    OwnedReadBuffer, OwnedWriteBuffer};
-use buffer::BufferResult::{BufferUnderflow, BufferOverflow};
-use crypto_bytes::copy_memory;
-use crypto_symmetric::{BlockEncryptor, BlockEncryptorX8, BlockDecryptor,
-    Encryptor, Decryptor, SynchronousStreamCipher, SymmetricCipherError,
-    symm_enc_or_dec};
+// TODO - Optimize the XORs
+// TODO - I think padding could be done better
+
+use core::{cmp, mem};
+use core::marker::PhantomData;
+
+use generic_array::ArrayLength;
+use generic_array::typenum::{Unsigned, U16};
+use crypto_buffers::{ReadBuffer, WriteBuffer, BufferResult, RefReadBuffer,
+                     RefWriteBuffer};
+use crypto_buffers::BufferResult::{BufferUnderflow, BufferOverflow};
+use crypto_symmetric::{Block, Block128, BlockCipher, BlockEncryptorX8,
+                       Encryptor,
+                       Decryptor, SynchronousStreamCipher,
+                       SymmetricCipherError, symm_enc_or_dec};
 use crypto_symmetric::SymmetricCipherError::{InvalidPadding, InvalidLength};
 
-/// The `BlockProcessor` trait is used to implement modes that require processing
-/// complete blocks of data. The methods of this trait are called by the
-/// `BlockEngine` which is in charge of properly buffering input data.
+/// The `BlockProcessor` trait is used to implement modes that require
+/// processing complete blocks of data. The methods of this trait are called
+/// by the `BlockEngine` which is in charge of properly buffering input data.
+/// Modes which chain blocks keep the chaining value themselves.
 trait BlockProcessor {
-    /// Process a block of data. The `in_hist` and `out_hist` parameters represent
-    /// the input and output when the last block was processed. These values are
-    /// necessary for certain modes.
-    fn process_block(&mut self, in_hist: &[u8], out_hist: &[u8], input: &[u8],
-                     output: &mut [u8]);
+    type BlockSize: ArrayLength<u8>;
+
+    /// Process a block of data, `input` and `output` are exactly one block
+    /// long
+    fn process_block(&mut self, input: &[u8], output: &mut [u8]);
 }
 
 /// A PaddingProcessor handles adding or removing padding
@@ -42,10 +58,16 @@ pub trait PaddingProcessor {
     /// Remove padding from the last block of output data
     /// If false is returned, the processing fails
     fn strip_output<R: ReadBuffer>(&mut self, output_buffer: &mut R) -> bool;
+
+    /// Whether a message whose length is a multiple of the block size gets
+    /// a whole block of padding. If not, `pad_input()` is only called on a
+    /// partial last block.
+    fn pads_full_blocks(&self) -> bool { true }
 }
 
-/// The `BlockEngine` is implemented as a state machine with the following states.
-/// See comments in the `BlockEngine` code for more information on the states.
+/// The `BlockEngine` is implemented as a state machine with the following
+/// states. See comments in the `BlockEngine` code for more information on
+/// the states.
 #[derive(Clone, Copy)]
 enum BlockEngineState {
     FastMode,
@@ -57,34 +79,59 @@ enum BlockEngineState {
     Error(SymmetricCipherError)
 }
 
+/// A buffer of one block. `len` bytes are valid, and the first `pos` of them
+/// have already been consumed.
+struct Scratch<N: ArrayLength<u8>> {
+    data: Block<N>,
+    pos: usize,
+    len: usize,
+}
+
+impl<N: ArrayLength<u8>> Scratch<N> {
+    fn new() -> Scratch<N> {
+        Scratch { data: Block::new(), pos: 0, len: 0 }
+    }
+
+    fn is_full(&self) -> bool { self.len == self.data.len() }
+
+    fn is_empty(&self) -> bool { self.pos == self.len }
+
+    fn reset(&mut self) {
+        self.pos = 0;
+        self.len = 0;
+    }
+
+    /// Appends as much of `input` as fits
+    fn fill<R: ReadBuffer>(&mut self, input: &mut R) {
+        let count = cmp::min(input.remaining(), self.data.len() - self.len);
+        self.data[self.len..self.len + count]
+            .copy_from_slice(input.take_next(count));
+        self.len += count;
+    }
+
+    /// Writes as much of the unconsumed data to `output` as fits
+    fn drain<W: WriteBuffer>(&mut self, output: &mut W) {
+        let count = cmp::min(output.remaining(), self.len - self.pos);
+        output.take_next(count)
+            .copy_from_slice(&self.data[self.pos..self.pos + count]);
+        self.pos += count;
+    }
+}
+
 /// `BlockEngine` buffers input and output data and handles sending complete
-/// block of data to the `Processor` object. Additionally, `BlockEngine` handles
-/// logic necessary to add or remove padding by calling the appropriate methods
-/// on the `Processor` object.
-struct BlockEngine<P, X> {
-    /// The block sized expected by the Processor
-    block_size: usize,
-
-    /// `in_hist` and `out_hist` keep track of data that was input to and output
-    /// from the last invocation of the `process_block()` method of the `Processor`.
-    /// Depending on the mode, these may be empty vectors if history is not needed.
-    in_hist: Vec<u8>,
-    out_hist: Vec<u8>,
-
-    /// If some input data is supplied, but not a complete blocks worth, it is
-    /// stored in this buffer until enough arrives that it can be passed to the
-    // `process_block()` method of the `Processor`.
-    in_scratch: OwnedWriteBuffer,
+/// block of data to the `Processor` object. Additionally, `BlockEngine`
+/// handles logic necessary to add or remove padding by calling the
+/// appropriate methods on the `Processor` object.
+struct BlockEngine<P: BlockProcessor, X> {
+    /// If some input data is supplied, but not a complete blocks worth, it
+    /// is stored in this buffer until enough arrives that it can be passed
+    /// to the `process_block()` method of the `Processor`.
+    in_scratch: Scratch<P::BlockSize>,
 
     /// If input data is processed but there isn't enough space in the output
-    /// buffer to store it, it is written into out_write_scratch. `OwnedWriteBuffer`'s
-    /// may be converted into `OwnedReaderBuffers` without re-allocating, so,
-    /// after being written, out_write_scratch is turned into `out_read_scratch`.
-    /// After that, if is written to the output as more output becomes available.
-    /// The main point is - only `out_write_scratch` or `out_read_scratch` contains
-    ///  a value at any given time; never both.
-    out_write_scratch: Option<OwnedWriteBuffer>,
-    out_read_scratch: Option<OwnedReadBuffer>,
+    /// buffer to store it, it is written into `out_scratch` and then written
+    /// to the output as more output becomes available.
+    out_scratch: Scratch<P::BlockSize>,
 
     /// The processor that implements the particular block mode.
     processor: P,
@@ -96,117 +143,36 @@ struct BlockEngine<P, X> {
     state: BlockEngineState
 }
 
-fn update_history(in_hist: &mut [u8], out_hist: &mut [u8], last_in: &[u8], last_out: &[u8]) {
-    let in_hist_len = in_hist.len();
-    if in_hist_len > 0 {
-        copy_memory(
-            &last_in[last_in.len() - in_hist_len..],
-            in_hist);
-    }
-    let out_hist_len = out_hist.len();
-    if out_hist_len > 0 {
-        copy_memory(
-            &last_out[last_out.len() - out_hist_len..],
-            out_hist);
-    }
-}
-
-impl <P: BlockProcessor, X: PaddingProcessor> BlockEngine<P, X> {
-    /// Create a new `BlockProcessor` instance with the given processor and
-    /// `block_size`. No history will be saved.
-    fn new(processor: P, padding: X, block_size: usize) -> BlockEngine<P, X> {
+impl<P: BlockProcessor, X: PaddingProcessor> BlockEngine<P, X> {
+    /// Create a new `BlockEngine` instance with the given processor and
+    /// padding
+    fn new(processor: P, padding: X) -> BlockEngine<P, X> {
         BlockEngine {
-            block_size: block_size,
-            in_hist: Vec::new(),
-            out_hist: Vec::new(),
-            in_scratch: OwnedWriteBuffer::new(repeat(0).take(block_size).collect()),
-            out_write_scratch: Some(OwnedWriteBuffer::new(repeat(0).take(block_size).collect())),
-            out_read_scratch: None,
+            in_scratch: Scratch::new(),
+            out_scratch: Scratch::new(),
             processor: processor,
             padding: padding,
             state: BlockEngineState::FastMode
         }
     }
 
-    /// Create a new `BlockProcessor` instance with the given processor, `block_size`,
-    /// and initial input and output history.
-    fn new_with_history(
-            processor: P,
-            padding: X,
-            block_size: usize,
-            in_hist: Vec<u8>,
-            out_hist: Vec<u8>) -> BlockEngine<P, X> {
-        BlockEngine {
-            in_hist: in_hist,
-            out_hist: out_hist,
-            ..BlockEngine::new(processor, padding, block_size)
-        }
-    }
+    fn block_size(&self) -> usize { P::BlockSize::to_usize() }
 
-    /// This implements the `FastMode` state. Ideally, the encryption or decryption
-    /// operation should do the bulk of its work in FastMode. Significantly,
-    /// `FastMode` avoids doing copies as much as possible. The `FastMode` state
-    /// does not handle the final block of data.
+    /// This implements the `FastMode` state. Ideally, the encryption or
+    /// decryption operation should do the bulk of its work in FastMode,
+    /// which processes blocks directly from the input to the output without
+    /// copying them. The `FastMode` state does not handle the final block of
+    /// data.
     fn fast_mode<R: ReadBuffer, W: WriteBuffer>(&mut self, input: &mut R,
-                                                output: &mut W) -> BlockEngineState {
-        fn has_next<R: ReadBuffer, W: WriteBuffer>(input: &mut R, output: &mut W,
-                                                   block_size: usize) -> bool {
-            // Not the greater than - very important since this method must never
-            // process the last block.
-            let enough_input = input.remaining() > block_size;
-            let enough_output = output.remaining() >= block_size;
-            enough_input && enough_output
-        };
-        fn split_at<'a>(vec: &'a [u8], at: usize) -> (&'a [u8], &'a [u8]) {
-            (&vec[..at], &vec[at..])
-        }
-
-        // First block processing. We have to retrieve the history information
-        // from self.in_hist and self.out_hist.
-        if !has_next(input, output, self.block_size) {
-            if input.is_empty() {
-                return BlockEngineState::FastMode;
-            } else {
-                return BlockEngineState::NeedInput;
-            }
-        } else {
-            let next_in = input.take_next(self.block_size);
-            let next_out = output.take_next(self.block_size);
-            self.processor.process_block(
-                &self.in_hist[..],
-                &self.out_hist[..],
-                next_in,
-                next_out);
-        }
-
-        // Process all remaing blocks. We can pull the history out of the buffers
-        // without having to do any copies
-        let next_in_size = self.in_hist.len() + self.block_size;
-        let next_out_size = self.out_hist.len() + self.block_size;
-        while has_next(input, output, self.block_size) {
-            input.rewind(self.in_hist.len());
-            let (in_hist, next_in) = split_at(input.take_next(next_in_size), self.in_hist.len());
-            output.rewind(self.out_hist.len());
-            let (out_hist, next_out) = output.take_next(next_out_size).split_at_mut(
-                self.out_hist.len());
-            self.processor.process_block(
-                in_hist,
-                out_hist,
-                next_in,
-                next_out);
-        }
-
-        // Save the history and then transition to the next state
-        {
-            input.rewind(self.in_hist.len());
-            let last_in = input.take_next(self.in_hist.len());
-            output.rewind(self.out_hist.len());
-            let last_out = output.take_next(self.out_hist.len());
-            update_history(
-                &mut self.in_hist,
-                &mut self.out_hist,
-                last_in,
-                last_out);
+                                                output: &mut W)
+                                                -> BlockEngineState {
+        let block_size = self.block_size();
+        // Note the greater than - very important since this method must
+        // never process the last block.
+        while input.remaining() > block_size &&
+              output.remaining() >= block_size {
+            self.processor.process_block(input.take_next(block_size),
+                                         output.take_next(block_size));
         }
         if input.is_empty() {
             BlockEngineState::FastMode
@@ -215,181 +181,188 @@ impl <P: BlockProcessor, X: PaddingProcessor> BlockEngine<P, X> {
         }
     }
 
-    /// This method implements the BlockEngine state machine.
-    fn process<R: ReadBuffer, W: WriteBuffer>(&mut self, input: &mut R,
-                                        output: &mut W, eof: bool)
-                                        -> Result<BufferResult, SymmetricCipherError> {
-        // Process a block of data from in_scratch and write the result to
-        // out_write_scratch. Finally, convert out_write_scratch into out_read_scratch.
-        fn process_scratch<P: BlockProcessor, X: PaddingProcessor>(me: &mut BlockEngine<P, X>) {
-            let mut rin = me.in_scratch.take_read_buffer();
-            let mut wout = me.out_write_scratch.take().unwrap();
-
-            {
-                let next_in = rin.take_remaining();
-                let next_out = wout.take_remaining();
-                me.processor.process_block(
-                    &me.in_hist[..],
-                    &me.out_hist[..],
-                    next_in,
-                    next_out);
-                update_history(
-                    &mut me.in_hist,
-                    &mut me.out_hist,
-                    next_in,
-                    next_out);
-            }
+    /// Process the block in `in_scratch` and put the result in
+    /// `out_scratch`, which must have been consumed
+    fn process_scratch(&mut self) {
+        self.processor.process_block(&self.in_scratch.data,
+                                     &mut self.out_scratch.data);
+        self.in_scratch.reset();
+        self.out_scratch.pos = 0;
+        self.out_scratch.len = self.block_size();
+    }
+
+    /// Adds padding to `in_scratch`, which is neither full nor empty
+    /// afterwards if the mode does not accept partial blocks
+    fn pad_scratch(&mut self) {
+        let len = self.in_scratch.len;
+        if len == 0 && !self.padding.pads_full_blocks() {
+            return;
+        }
+        let mut wb = RefWriteBuffer::new(&mut self.in_scratch.data[len..]);
+        self.padding.pad_input(&mut wb);
+        self.in_scratch.len += wb.position();
+    }
 
-            let rb = wout.into_read_buffer();
-            me.out_read_scratch = Some(rb);
+    /// Removes the padding from `out_scratch`
+    fn strip_scratch(&mut self) -> BlockEngineState {
+        let stripped = {
+            let mut rb = RefReadBuffer::new(&self.out_scratch.data);
+            if self.padding.strip_output(&mut rb) {
+                Some(rb.capacity())
+            } else {
+                None
+            }
         };
+        match stripped {
+            Some(len) => {
+                self.out_scratch.len = len;
+                BlockEngineState::Finished
+            }
+            None => BlockEngineState::Error(InvalidPadding),
+        }
+    }
 
+    /// This method implements the BlockEngine state machine.
+    fn process<R: ReadBuffer, W: WriteBuffer>(&mut self, input: &mut R,
+                                              output: &mut W, eof: bool)
+        -> Result<BufferResult, SymmetricCipherError> {
         loop {
             match self.state {
-                // FastMode tries to process as much data as possible while minimizing copies.
-                // FastMode doesn't make use of the scratch buffers and only updates the history
-                // just before exiting.
+                // FastMode tries to process as much data as possible while
+                // minimizing copies. FastMode doesn't make use of the
+                // scratch buffers.
                 BlockEngineState::FastMode => {
                     self.state = self.fast_mode(input, output);
-                    match self.state {
-                        BlockEngineState::FastMode => {
-                            // If FastMode completes but stays in the FastMode state, it means that
-                            // we've run out of input data.
+                    if let BlockEngineState::FastMode = self.state {
+                        // If FastMode completes but stays in the FastMode
+                        // state, it means that we've run out of input data.
+                        // At the end of the input the last block is still to
+                        // be padded, which NeedInput leads to.
+                        if !eof {
                             return Ok(BufferUnderflow);
                         }
-                        _ => {}
+                        self.state = BlockEngineState::NeedInput;
                     }
                 }
 
-                // The NeedInput mode is entered when there isn't enough data to run in FastMode
-                // anymore. Input data is buffered in in_scratch until there is a full block or eof
-                // occurs. IF eof doesn't occur, the data is processed and then we go to the
-                // NeedOutput state. Otherwise, we go to the LastInput state. This state always
-                // writes all available data into in_scratch before transitioning to the next state.
+                // The NeedInput mode is entered when there isn't enough data
+                // to run in FastMode anymore. Input data is buffered in
+                // in_scratch until there is a full block or eof occurs. If
+                // eof doesn't occur, the data is processed and then we go to
+                // the NeedOutput state. Otherwise, we go to the LastInput
+                // state. This state always writes all available data into
+                // in_scratch before transitioning to the next state.
                 BlockEngineState::NeedInput => {
-                    input.push_to(&mut self.in_scratch);
+                    self.in_scratch.fill(input);
                     if !input.is_empty() {
-                        // !is_empty() guarantees two things - in_scratch is full and its not the
-                        // last block. This state must never process the last block.
-                        process_scratch(self);
+                        // !is_empty() guarantees two things - in_scratch is
+                        // full and its not the last block. This state must
+                        // never process the last block.
+                        self.process_scratch();
                         self.state = BlockEngineState::NeedOutput;
+                    } else if eof {
+                        self.state = BlockEngineState::LastInput;
                     } else {
-                        if eof {
-                            self.state = BlockEngineState::LastInput;
-                        } else {
-                            return Ok(BufferUnderflow);
-                        }
+                        return Ok(BufferUnderflow);
                     }
                 }
 
-                // The NeedOutput state just writes buffered processed data to the output stream
-                // until all of it has been written.
+                // The NeedOutput state just writes buffered processed data to
+                // the output stream until all of it has been written.
                 BlockEngineState::NeedOutput => {
-                    let mut rout = self.out_read_scratch.take().unwrap();
-                    rout.push_to(output);
-                    if rout.is_empty() {
-                        self.out_write_scratch = Some(rout.into_write_buffer());
+                    self.out_scratch.drain(output);
+                    if self.out_scratch.is_empty() {
                         self.state = BlockEngineState::FastMode;
                     } else {
-                        self.out_read_scratch = Some(rout);
                         return Ok(BufferOverflow);
                     }
                 }
 
-                // None of the other states are allowed to process the last block of data since
-                // last block handling is a little tricky due to modes have special needs regarding
-                // padding. When the last block of data is detected, this state is transitioned to
-                // for handling.
+                // None of the other states are allowed to process the last
+                // block of data since last block handling is a little tricky
+                // due to modes have special needs regarding padding. When the
+                // last block of data is detected, this state is transitioned
+                // to for handling.
                 BlockEngineState::LastInput => {
-                    // We we arrive in this state, we know that all input data that is going to be
-                    // supplied has been suplied and that that data has been written to in_scratch
-                    // by the NeedInput state. Furthermore, we know that one of three things must be
-                    // true about in_scratch:
-                    // 1) It is empty. This only occurs if the input is zero length. We can do last
-                    //    block processing by executing the pad_input() method of the processor
-                    //    which may either pad out to a full block or leave it empty, process the
-                    //    data if it was padded out to a full block, and then pass it to
-                    //    strip_output().
-                    // 2) It is partially filled. This will occur if the input data was not a
-                    //    multiple of the block size. Processing proceeds identically to case #1.
-                    // 3) It is full. This case occurs when the input data was a multiple of the
-                    //    block size. This case is a little trickier, since, depending on the mode,
-                    //    we might actually have 2 blocks worth of data to process - the last user
-                    //    supplied block (currently in in_scratch) and then another block that could
-                    //    be added as padding. Processing proceeds by first processing the data in
-                    //    in_scratch and writing it to out_scratch. Then, the now-empty in_scratch
-                    //    buffer is passed to pad_input() which may leave it empty or write a block
-                    //    of padding to it. If no padding is added, processing proceeds as in cases
-                    //    #1 and #2. However, if padding is added, now have data in in_scratch and
-                    //    also in out_scratch meaning that we can't immediately process the padding
-                    //    data since we have nowhere to put it. So, we transition to the LastInput2
-                    //    state which will first write out the last non-padding block, then process
-                    //    the padding block (in in_scratch) and write it to the now-empty
+                    // When we arrive in this state, we know that all input
+                    // data that is going to be supplied has been supplied and
+                    // that that data has been written to in_scratch by the
+                    // NeedInput state. Furthermore, we know that one of three
+                    // things must be true about in_scratch:
+                    // 1) It is empty. This only occurs if the input is zero
+                    //    length. We can do last block processing by executing
+                    //    the pad_input() method of the processor which may
+                    //    either pad out to a full block or leave it empty,
+                    //    process the data if it was padded out to a full
+                    //    block, and then pass it to strip_output().
+                    // 2) It is partially filled. This will occur if the input
+                    //    data was not a multiple of the block size.
+                    //    Processing proceeds identically to case #1.
+                    // 3) It is full. This case occurs when the input data was
+                    //    a multiple of the block size. This case is a little
+                    //    trickier, since, depending on the mode, we might
+                    //    actually have 2 blocks worth of data to process -
+                    //    the last user supplied block (currently in
+                    //    in_scratch) and then another block that could be
+                    //    added as padding. Processing proceeds by first
+                    //    processing the data in in_scratch and writing it to
+                    //    out_scratch. Then, the now-empty in_scratch buffer
+                    //    is passed to pad_input() which may leave it empty or
+                    //    write a block of padding to it. If no padding is
+                    //    added, processing proceeds as in cases #1 and #2.
+                    //    However, if padding is added, we now have data in
+                    //    in_scratch and also in out_scratch meaning that we
+                    //    can't immediately process the padding data since we
+                    //    have nowhere to put it. So, we transition to the
+                    //    LastInput2 state which will first write out the last
+                    //    non-padding block, then process the padding block
+                    //    (in in_scratch) and write it to the now-empty
                     //    out_scratch.
                     if !self.in_scratch.is_full() {
-                        self.padding.pad_input(&mut self.in_scratch);
+                        self.pad_scratch();
                         if self.in_scratch.is_full() {
-                            process_scratch(self);
-                            if self.padding.strip_output(self.out_read_scratch.as_mut().unwrap()) {
-                                self.state = BlockEngineState::Finished;
-                            } else {
-                                self.state = BlockEngineState::Error(InvalidPadding);
-                            }
-                        } else if self.in_scratch.is_empty() {
+                            self.process_scratch();
+                            self.state = self.strip_scratch();
+                        } else if self.in_scratch.len == 0 {
                             self.state = BlockEngineState::Finished;
                         } else {
                             self.state = BlockEngineState::Error(InvalidLength);
                         }
                     } else {
-                        process_scratch(self);
-                        self.padding.pad_input(&mut self.in_scratch);
+                        self.process_scratch();
+                        self.pad_scratch();
                         if self.in_scratch.is_full() {
                             self.state = BlockEngineState::LastInput2;
-                        } else if self.in_scratch.is_empty() {
-                            if self.padding.strip_output(self.out_read_scratch.as_mut().unwrap()) {
-                                self.state = BlockEngineState::Finished;
-                            } else {
-                                self.state = BlockEngineState::Error(InvalidPadding);
-                            }
+                        } else if self.in_scratch.len == 0 {
+                            self.state = self.strip_scratch();
                         } else {
                             self.state = BlockEngineState::Error(InvalidLength);
                         }
                     }
                 }
 
-                // See the comments on LastInput for more details. This state handles final blocks
-                // of data in the case that the input was a multiple of the block size and the mode
-                // decided to add a full extra block of padding.
+                // See the comments on LastInput for more details. This state
+                // handles final blocks of data in the case that the input was
+                // a multiple of the block size and the mode decided to add a
+                // full extra block of padding.
                 BlockEngineState::LastInput2 => {
-                    let mut rout = self.out_read_scratch.take().unwrap();
-                    rout.push_to(output);
-                    if rout.is_empty() {
-                        self.out_write_scratch = Some(rout.into_write_buffer());
-                        process_scratch(self);
-                        if self.padding.strip_output(self.out_read_scratch.as_mut().unwrap()) {
-                            self.state = BlockEngineState::Finished;
-                        } else {
-                            self.state = BlockEngineState::Error(InvalidPadding);
-                        }
+                    self.out_scratch.drain(output);
+                    if self.out_scratch.is_empty() {
+                        self.process_scratch();
+                        self.state = self.strip_scratch();
                     } else {
-                        self.out_read_scratch = Some(rout);
                         return Ok(BufferOverflow);
                     }
                 }
 
-                // The Finished mode just writes the data in out_scratch to the output until there
-                // is no more data left.
+                // The Finished mode just writes the data in out_scratch to
+                // the output until there is no more data left.
                 BlockEngineState::Finished => {
-                    match self.out_read_scratch {
-                        Some(ref mut rout) => {
-                            rout.push_to(output);
-                            if rout.is_empty() {
-                                return Ok(BufferUnderflow);
-                            } else {
-                                return Ok(BufferOverflow);
-                            }
-                        }
-                        None => { return Ok(BufferUnderflow); }
+                    self.out_scratch.drain(output);
+                    if self.out_scratch.is_empty() {
+                        return Ok(BufferUnderflow);
+                    } else {
+                        return Ok(BufferOverflow);
                     }
                 }
 
@@ -400,21 +373,11 @@ impl <P: BlockProcessor, X: PaddingProcessor> BlockEngine<P, X> {
             }
         }
     }
+
     fn reset(&mut self) {
         self.state = BlockEngineState::FastMode;
         self.in_scratch.reset();
-        if self.out_read_scratch.is_some() {
-            let ors = self.out_read_scratch.take().unwrap();
-            let ows = ors.into_write_buffer();
-            self.out_write_scratch = Some(ows);
-        } else {
-            self.out_write_scratch.as_mut().unwrap().reset();
-        }
-    }
-    fn reset_with_history(&mut self, in_hist: &[u8], out_hist: &[u8]) {
-        self.reset();
-        copy_memory(in_hist, &mut self.in_hist);
-        copy_memory(out_hist, &mut self.out_hist);
+        self.out_scratch.reset();
     }
 }
 
@@ -431,13 +394,14 @@ impl PaddingProcessor for NoPadding {
 #[derive(Clone, Copy)]
 pub struct PkcsPadding;
 
-// This class implements both encryption padding, where padding is added, and decryption padding,
-// where padding is stripped. Since BlockEngine doesn't know if its an Encryption or Decryption
-// operation, it will call both methods if given a chance. So, this class can't be passed directly
-// to BlockEngine. Instead, it must be wrapped with EncPadding or DecPadding which will ensure that
-// only the propper methods are called. The client of the library, however, doesn't have to
-// distinguish encryption padding handling from decryption padding handline, which is the whole
-// point.
+// This class implements both encryption padding, where padding is added, and
+// decryption padding, where padding is stripped. Since BlockEngine doesn't
+// know if its an Encryption or Decryption operation, it will call both
+// methods if given a chance. So, this class can't be passed directly to
+// BlockEngine. Instead, it must be wrapped with EncPadding or DecPadding
+// which will ensure that only the proper methods are called. The client of
+// the library, however, doesn't have to distinguish encryption padding
+// handling from decryption padding handling, which is the whole point.
 impl PaddingProcessor for PkcsPadding {
     fn pad_input<W: WriteBuffer>(&mut self, input_buffer: &mut W) {
         let rem = input_buffer.remaining();
@@ -451,6 +415,9 @@ impl PaddingProcessor for PkcsPadding {
         {
             let data = output_buffer.peek_remaining();
             last_byte = *data.last().unwrap();
+            if last_byte == 0 || last_byte as usize > data.len() {
+                return false;
+            }
             for &x in data.iter().rev().take(last_byte as usize) {
                 if x != last_byte {
                     return false;
@@ -462,58 +429,193 @@ impl PaddingProcessor for PkcsPadding {
     }
 }
 
+/// Returns the padding length from the last byte of `data`, as in the
+/// PKCS, ANSI X9.23 and ISO 10126 paddings
+fn padding_len(data: &[u8]) -> Option<usize> {
+    let last_byte = *data.last().unwrap() as usize;
+    if last_byte == 0 || last_byte > data.len() {
+        None
+    } else {
+        Some(last_byte)
+    }
+}
+
+/// ANSI X9.23 padding: zeros, then a byte holding the padding length
+#[derive(Clone, Copy)]
+pub struct AnsiX923Padding;
+
+impl PaddingProcessor for AnsiX923Padding {
+    fn pad_input<W: WriteBuffer>(&mut self, input_buffer: &mut W) {
+        let rem = input_buffer.remaining();
+        assert!(rem != 0 && rem <= 255);
+        let padding = input_buffer.take_remaining();
+        for v in padding.iter_mut() {
+            *v = 0;
+        }
+        padding[rem - 1] = rem as u8;
+    }
+    fn strip_output<R: ReadBuffer>(&mut self, output_buffer: &mut R) -> bool {
+        let len = {
+            let data = output_buffer.peek_remaining();
+            let n = data.len();
+            match padding_len(data) {
+                Some(len) if data[n - len..n - 1].iter().all(|&x| x == 0) => {
+                    len
+                }
+                _ => return false
+            }
+        };
+        output_buffer.truncate(len);
+        true
+    }
+}
+
+/// ISO 10126 padding: random bytes, then a byte holding the padding length.
+///
+/// The random bytes are written by `fill`. Only the length is checked when
+/// the padding is stripped, so decryption does not use it.
+#[derive(Clone, Copy)]
+pub struct Iso10126Padding<F> {
+    fill: F
+}
+
+impl<F: FnMut(&mut [u8])> Iso10126Padding<F> {
+    pub fn new(fill: F) -> Iso10126Padding<F> {
+        Iso10126Padding { fill: fill }
+    }
+}
+
+impl<F: FnMut(&mut [u8])> PaddingProcessor for Iso10126Padding<F> {
+    fn pad_input<W: WriteBuffer>(&mut self, input_buffer: &mut W) {
+        let rem = input_buffer.remaining();
+        assert!(rem != 0 && rem <= 255);
+        let padding = input_buffer.take_remaining();
+        (self.fill)(&mut padding[..rem - 1]);
+        padding[rem - 1] = rem as u8;
+    }
+    fn strip_output<R: ReadBuffer>(&mut self, output_buffer: &mut R) -> bool {
+        match padding_len(output_buffer.peek_remaining()) {
+            Some(len) => {
+                output_buffer.truncate(len);
+                true
+            }
+            None => false
+        }
+    }
+}
+
+/// ISO/IEC 7816-4 padding: a 0x80 byte followed by zeros, which is also
+/// method 2 of ISO/IEC 9797-1
+#[derive(Clone, Copy)]
+pub struct Iso7816Padding;
+
+impl PaddingProcessor for Iso7816Padding {
+    fn pad_input<W: WriteBuffer>(&mut self, input_buffer: &mut W) {
+        let padding = input_buffer.take_remaining();
+        for v in padding.iter_mut() {
+            *v = 0;
+        }
+        padding[0] = 0x80;
+    }
+    fn strip_output<R: ReadBuffer>(&mut self, output_buffer: &mut R) -> bool {
+        let len = {
+            let data = output_buffer.peek_remaining();
+            match data.iter().rposition(|&x| x != 0) {
+                Some(i) if data[i] == 0x80 => data.len() - i,
+                _ => return false
+            }
+        };
+        output_buffer.truncate(len);
+        true
+    }
+}
+
+/// Zero padding: a partial last block is completed with zeros, and no
+/// padding is added to a message which is a multiple of the block size.
+///
+/// All the trailing zeros of the last block are stripped, so this padding
+/// is only reversible for messages which do not end with a zero byte.
+#[derive(Clone, Copy)]
+pub struct ZeroPadding;
+
+impl PaddingProcessor for ZeroPadding {
+    fn pad_input<W: WriteBuffer>(&mut self, input_buffer: &mut W) {
+        for v in input_buffer.take_remaining().iter_mut() {
+            *v = 0;
+        }
+    }
+    fn strip_output<R: ReadBuffer>(&mut self, output_buffer: &mut R) -> bool {
+        let len = {
+            let data = output_buffer.peek_remaining();
+            data.iter().rev().take_while(|&&x| x == 0).count()
+        };
+        output_buffer.truncate(len);
+        true
+    }
+    fn pads_full_blocks(&self) -> bool { false }
+}
+
 /// Wraps a PaddingProcessor so that only pad_input() will actually be called.
 pub struct EncPadding<X> {
     padding: X
 }
 
-impl <X: PaddingProcessor> EncPadding<X> {
+impl<X: PaddingProcessor> EncPadding<X> {
     fn wrap(p: X) -> EncPadding<X> { EncPadding { padding: p } }
 }
 
-impl <X: PaddingProcessor> PaddingProcessor for EncPadding<X> {
-    fn pad_input<W: WriteBuffer>(&mut self, a: &mut W) { self.padding.pad_input(a); }
+impl<X: PaddingProcessor> PaddingProcessor for EncPadding<X> {
+    fn pad_input<W: WriteBuffer>(&mut self, a: &mut W) {
+        self.padding.pad_input(a);
+    }
     fn strip_output<R: ReadBuffer>(&mut self, _: &mut R) -> bool { true }
+    fn pads_full_blocks(&self) -> bool { self.padding.pads_full_blocks() }
 }
 
-/// Wraps a PaddingProcessor so that only strip_output() will actually be called.
+/// Wraps a PaddingProcessor so that only strip_output() will actually be
+/// called.
 pub struct DecPadding<X> {
     padding: X
 }
 
-impl <X: PaddingProcessor> DecPadding<X> {
+impl<X: PaddingProcessor> DecPadding<X> {
     fn wrap(p: X) -> DecPadding<X> { DecPadding { padding: p } }
 }
 
-impl <X: PaddingProcessor> PaddingProcessor for DecPadding<X> {
+impl<X: PaddingProcessor> PaddingProcessor for DecPadding<X> {
     fn pad_input<W: WriteBuffer>(&mut self, _: &mut W) { }
-    fn strip_output<R: ReadBuffer>(&mut self, a: &mut R) -> bool { self.padding.strip_output(a) }
+    fn strip_output<R: ReadBuffer>(&mut self, a: &mut R) -> bool {
+        self.padding.strip_output(a)
+    }
+    fn pads_full_blocks(&self) -> bool { self.padding.pads_full_blocks() }
 }
 
-struct EcbEncryptorProcessor<T> {
-    algo: T
+struct EcbEncryptorProcessor<C> {
+    cipher: C
 }
 
-impl <T: BlockEncryptor> BlockProcessor for EcbEncryptorProcessor<T> {
-    fn process_block(&mut self, _: &[u8], _: &[u8], input: &[u8], output: &mut [u8]) {
-        self.algo.encrypt_block(input, output);
+impl<C: BlockCipher> BlockProcessor for EcbEncryptorProcessor<C> {
+    type BlockSize = C::BlockSize;
+
+    fn process_block(&mut self, input: &[u8], output: &mut [u8]) {
+        let mut out = Block::new();
+        self.cipher.encrypt_block(&Block::from_slice(input), &mut out);
+        output.copy_from_slice(&out);
     }
 }
 
 /// ECB Encryption mode
-pub struct EcbEncryptor<T, X> {
-    block_engine: BlockEngine<EcbEncryptorProcessor<T>, X>
+pub struct EcbEncryptor<C: BlockCipher, X> {
+    block_engine: BlockEngine<EcbEncryptorProcessor<C>, X>
 }
 
-impl <T: BlockEncryptor, X: PaddingProcessor> EcbEncryptor<T, X> {
+impl<C: BlockCipher, X: PaddingProcessor> EcbEncryptor<C, X> {
     /// Create a new ECB encryption mode object
-    pub fn new(algo: T, padding: X) -> EcbEncryptor<T, EncPadding<X>> {
-        let block_size = algo.block_size();
-        let processor = EcbEncryptorProcessor {
-            algo: algo
-        };
+    pub fn new(cipher: C, padding: X) -> EcbEncryptor<C, EncPadding<X>> {
+        let processor = EcbEncryptorProcessor { cipher: cipher };
         EcbEncryptor {
-            block_engine: BlockEngine::new(processor, EncPadding::wrap(padding), block_size)
+            block_engine: BlockEngine::new(processor,
+                                           EncPadding::wrap(padding))
         }
     }
     pub fn reset(&mut self) {
@@ -521,37 +623,40 @@ impl <T: BlockEncryptor, X: PaddingProcessor> EcbEncryptor<T, X> {
     }
 }
 
-impl <T: BlockEncryptor, X: PaddingProcessor> Encryptor for EcbEncryptor<T, X> {
-    fn encrypt(&mut self, input: &mut RefReadBuffer, output: &mut RefWriteBuffer, eof: bool)
-            -> Result<BufferResult, SymmetricCipherError> {
+impl<C: BlockCipher, X: PaddingProcessor> Encryptor for EcbEncryptor<C, X> {
+    fn encrypt(&mut self, input: &mut RefReadBuffer,
+               output: &mut RefWriteBuffer, eof: bool)
+               -> Result<BufferResult, SymmetricCipherError> {
         self.block_engine.process(input, output, eof)
     }
 }
 
-struct EcbDecryptorProcessor<T> {
-    algo: T
+struct EcbDecryptorProcessor<C> {
+    cipher: C
 }
 
-impl <T: BlockDecryptor> BlockProcessor for EcbDecryptorProcessor<T> {
-    fn process_block(&mut self, _: &[u8], _: &[u8], input: &[u8], output: &mut [u8]) {
-        self.algo.decrypt_block(input, output);
+impl<C: BlockCipher> BlockProcessor for EcbDecryptorProcessor<C> {
+    type BlockSize = C::BlockSize;
+
+    fn process_block(&mut self, input: &[u8], output: &mut [u8]) {
+        let mut out = Block::new();
+        self.cipher.decrypt_block(&Block::from_slice(input), &mut out);
+        output.copy_from_slice(&out);
     }
 }
 
 /// ECB Decryption mode
-pub struct EcbDecryptor<T, X> {
-    block_engine: BlockEngine<EcbDecryptorProcessor<T>, X>
+pub struct EcbDecryptor<C: BlockCipher, X> {
+    block_engine: BlockEngine<EcbDecryptorProcessor<C>, X>
 }
 
-impl <T: BlockDecryptor, X: PaddingProcessor> EcbDecryptor<T, X> {
+impl<C: BlockCipher, X: PaddingProcessor> EcbDecryptor<C, X> {
     /// Create a new ECB decryption mode object
-    pub fn new(algo: T, padding: X) -> EcbDecryptor<T, DecPadding<X>> {
-        let block_size = algo.block_size();
-        let processor = EcbDecryptorProcessor {
-            algo: algo
-        };
+    pub fn new(cipher: C, padding: X) -> EcbDecryptor<C, DecPadding<X>> {
+        let processor = EcbDecryptorProcessor { cipher: cipher };
         EcbDecryptor {
-            block_engine: BlockEngine::new(processor, DecPadding::wrap(padding), block_size)
+            block_engine: BlockEngine::new(processor,
+                                           DecPadding::wrap(padding))
         }
     }
     pub fn reset(&mut self) {
@@ -559,260 +664,1131 @@ impl <T: BlockDecryptor, X: PaddingProcessor> EcbDecryptor<T, X> {
     }
 }
 
-impl <T: BlockDecryptor, X: PaddingProcessor> Decryptor for EcbDecryptor<T, X> {
-    fn decrypt(&mut self, input: &mut RefReadBuffer, output: &mut RefWriteBuffer, eof: bool)
-            -> Result<BufferResult, SymmetricCipherError> {
+impl<C: BlockCipher, X: PaddingProcessor> Decryptor for EcbDecryptor<C, X> {
+    fn decrypt(&mut self, input: &mut RefReadBuffer,
+               output: &mut RefWriteBuffer, eof: bool)
+               -> Result<BufferResult, SymmetricCipherError> {
         self.block_engine.process(input, output, eof)
     }
 }
 
-struct CbcEncryptorProcessor<T> {
-    algo: T,
-    temp: Vec<u8>
+struct CbcEncryptorProcessor<C: BlockCipher> {
+    cipher: C,
+    /// The previous ciphertext block, or the IV
+    iv: Block<C::BlockSize>
 }
 
-impl <T: BlockEncryptor> BlockProcessor for CbcEncryptorProcessor<T> {
-    fn process_block(&mut self, _: &[u8], out_hist: &[u8], input: &[u8], output: &mut [u8]) {
-        for ((&x, &y), o) in input.iter().zip(out_hist.iter()).zip(self.temp.iter_mut()) {
-            *o = x ^ y;
+impl<C: BlockCipher> BlockProcessor for CbcEncryptorProcessor<C> {
+    type BlockSize = C::BlockSize;
+
+    fn process_block(&mut self, input: &[u8], output: &mut [u8]) {
+        for (x, &y) in self.iv.iter_mut().zip(input.iter()) {
+            *x ^= y;
         }
-        self.algo.encrypt_block(&self.temp[..], output);
+        let mut out = Block::new();
+        self.cipher.encrypt_block(&self.iv, &mut out);
+        output.copy_from_slice(&out);
+        self.iv = out;
     }
 }
 
 /// CBC encryption mode
-pub struct CbcEncryptor<T, X> {
-    block_engine: BlockEngine<CbcEncryptorProcessor<T>, X>
+pub struct CbcEncryptor<C: BlockCipher, X> {
+    block_engine: BlockEngine<CbcEncryptorProcessor<C>, X>
 }
 
-impl <T: BlockEncryptor, X: PaddingProcessor> CbcEncryptor<T, X> {
+impl<C: BlockCipher, X: PaddingProcessor> CbcEncryptor<C, X> {
     /// Create a new CBC encryption mode object
-    pub fn new(algo: T, padding: X, iv: Vec<u8>) -> CbcEncryptor<T, EncPadding<X>> {
-        let block_size = algo.block_size();
+    pub fn new(cipher: C, padding: X, iv: &Block<C::BlockSize>)
+               -> CbcEncryptor<C, EncPadding<X>> {
         let processor = CbcEncryptorProcessor {
-            algo: algo,
-            temp: repeat(0).take(block_size).collect()
+            cipher: cipher,
+            iv: iv.clone()
         };
         CbcEncryptor {
-            block_engine: BlockEngine::new_with_history(
-                processor,
-                EncPadding::wrap(padding),
-                block_size,
-                Vec::new(),
-                iv)
+            block_engine: BlockEngine::new(processor,
+                                           EncPadding::wrap(padding))
         }
     }
-    pub fn reset(&mut self, iv: &[u8]) {
-        self.block_engine.reset_with_history(&[], iv);
+    pub fn reset(&mut self, iv: &Block<C::BlockSize>) {
+        self.block_engine.reset();
+        self.block_engine.processor.iv = iv.clone();
     }
 }
 
-impl <T: BlockEncryptor, X: PaddingProcessor> Encryptor for CbcEncryptor<T, X> {
-    fn encrypt(&mut self, input: &mut RefReadBuffer, output: &mut RefWriteBuffer, eof: bool)
-            -> Result<BufferResult, SymmetricCipherError> {
+impl<C: BlockCipher, X: PaddingProcessor> Encryptor for CbcEncryptor<C, X> {
+    fn encrypt(&mut self, input: &mut RefReadBuffer,
+               output: &mut RefWriteBuffer, eof: bool)
+               -> Result<BufferResult, SymmetricCipherError> {
         self.block_engine.process(input, output, eof)
     }
 }
 
-struct CbcDecryptorProcessor<T> {
-    algo: T,
-    temp: Vec<u8>
+struct CbcDecryptorProcessor<C: BlockCipher> {
+    cipher: C,
+    /// The previous ciphertext block, or the IV
+    iv: Block<C::BlockSize>
 }
 
-impl <T: BlockDecryptor> BlockProcessor for CbcDecryptorProcessor<T> {
-    fn process_block(&mut self, in_hist: &[u8], _: &[u8], input: &[u8], output: &mut [u8]) {
-        self.algo.decrypt_block(input, &mut self.temp);
-        for ((&x, &y), o) in self.temp.iter().zip(in_hist.iter()).zip(output.iter_mut()) {
+impl<C: BlockCipher> BlockProcessor for CbcDecryptorProcessor<C> {
+    type BlockSize = C::BlockSize;
+
+    fn process_block(&mut self, input: &[u8], output: &mut [u8]) {
+        let block = Block::from_slice(input);
+        let mut out = Block::new();
+        self.cipher.decrypt_block(&block, &mut out);
+        for ((o, &x), &y) in output.iter_mut().zip(out.iter())
+                                   .zip(self.iv.iter()) {
             *o = x ^ y;
         }
+        self.iv = block;
     }
 }
 
 /// CBC decryption mode
-pub struct CbcDecryptor<T, X> {
-    block_engine: BlockEngine<CbcDecryptorProcessor<T>, X>
+pub struct CbcDecryptor<C: BlockCipher, X> {
+    block_engine: BlockEngine<CbcDecryptorProcessor<C>, X>
 }
 
-impl <T: BlockDecryptor, X: PaddingProcessor> CbcDecryptor<T, X> {
+impl<C: BlockCipher, X: PaddingProcessor> CbcDecryptor<C, X> {
     /// Create a new CBC decryption mode object
-    pub fn new(algo: T, padding: X, iv: Vec<u8>) -> CbcDecryptor<T, DecPadding<X>> {
-        let block_size = algo.block_size();
+    pub fn new(cipher: C, padding: X, iv: &Block<C::BlockSize>)
+               -> CbcDecryptor<C, DecPadding<X>> {
         let processor = CbcDecryptorProcessor {
-            algo: algo,
-            temp: repeat(0).take(block_size).collect()
+            cipher: cipher,
+            iv: iv.clone()
         };
         CbcDecryptor {
-            block_engine: BlockEngine::new_with_history(
-                processor,
-                DecPadding::wrap(padding),
-                block_size,
-                iv,
-                Vec::new())
+            block_engine: BlockEngine::new(processor,
+                                           DecPadding::wrap(padding))
+        }
+    }
+    pub fn reset(&mut self, iv: &Block<C::BlockSize>) {
+        self.block_engine.reset();
+        self.block_engine.processor.iv = iv.clone();
+    }
+}
+
+impl<C: BlockCipher, X: PaddingProcessor> Decryptor for CbcDecryptor<C, X> {
+    fn decrypt(&mut self, input: &mut RefReadBuffer,
+               output: &mut RefWriteBuffer, eof: bool)
+               -> Result<BufferResult, SymmetricCipherError> {
+        self.block_engine.process(input, output, eof)
+    }
+}
+
+struct PcbcEncryptorProcessor<C: BlockCipher> {
+    cipher: C,
+    /// The XOR of the previous plaintext and ciphertext blocks, or the IV
+    iv: Block<C::BlockSize>
+}
+
+impl<C: BlockCipher> BlockProcessor for PcbcEncryptorProcessor<C> {
+    type BlockSize = C::BlockSize;
+
+    fn process_block(&mut self, input: &[u8], output: &mut [u8]) {
+        for (x, &y) in self.iv.iter_mut().zip(input.iter()) {
+            *x ^= y;
+        }
+        let mut out = Block::new();
+        self.cipher.encrypt_block(&self.iv, &mut out);
+        output.copy_from_slice(&out);
+        for ((x, &y), &z) in self.iv.iter_mut().zip(input.iter())
+                                    .zip(out.iter()) {
+            *x = y ^ z;
         }
     }
-    pub fn reset(&mut self, iv: &[u8]) {
-        self.block_engine.reset_with_history(iv, &[]);
+}
+
+/// PCBC encryption mode, in which every ciphertext block depends on all the
+/// previous plaintext blocks
+pub struct PcbcEncryptor<C: BlockCipher, X> {
+    block_engine: BlockEngine<PcbcEncryptorProcessor<C>, X>
+}
+
+impl<C: BlockCipher, X: PaddingProcessor> PcbcEncryptor<C, X> {
+    /// Create a new PCBC encryption mode object
+    pub fn new(cipher: C, padding: X, iv: &Block<C::BlockSize>)
+               -> PcbcEncryptor<C, EncPadding<X>> {
+        let processor = PcbcEncryptorProcessor {
+            cipher: cipher,
+            iv: iv.clone()
+        };
+        PcbcEncryptor {
+            block_engine: BlockEngine::new(processor,
+                                           EncPadding::wrap(padding))
+        }
+    }
+    pub fn reset(&mut self, iv: &Block<C::BlockSize>) {
+        self.block_engine.reset();
+        self.block_engine.processor.iv = iv.clone();
     }
 }
 
-impl <T: BlockDecryptor, X: PaddingProcessor> Decryptor for CbcDecryptor<T, X> {
-    fn decrypt(&mut self, input: &mut RefReadBuffer, output: &mut RefWriteBuffer, eof: bool)
-            -> Result<BufferResult, SymmetricCipherError> {
+impl<C: BlockCipher, X: PaddingProcessor> Encryptor for PcbcEncryptor<C, X> {
+    fn encrypt(&mut self, input: &mut RefReadBuffer,
+               output: &mut RefWriteBuffer, eof: bool)
+               -> Result<BufferResult, SymmetricCipherError> {
         self.block_engine.process(input, output, eof)
     }
 }
 
-fn add_ctr(ctr: &mut [u8], mut ammount: u8) {
-    for i in ctr.iter_mut().rev() {
-        let prev = *i;
-        *i = i.wrapping_add(ammount);
-        if *i >= prev {
+struct PcbcDecryptorProcessor<C: BlockCipher> {
+    cipher: C,
+    /// The XOR of the previous plaintext and ciphertext blocks, or the IV
+    iv: Block<C::BlockSize>
+}
+
+impl<C: BlockCipher> BlockProcessor for PcbcDecryptorProcessor<C> {
+    type BlockSize = C::BlockSize;
+
+    fn process_block(&mut self, input: &[u8], output: &mut [u8]) {
+        let mut out = Block::new();
+        self.cipher.decrypt_block(&Block::from_slice(input), &mut out);
+        for (((o, x), &y), &c) in output.iter_mut().zip(self.iv.iter_mut())
+                                        .zip(out.iter()).zip(input.iter()) {
+            *o = *x ^ y;
+            *x = *o ^ c;
+        }
+    }
+}
+
+/// PCBC decryption mode
+pub struct PcbcDecryptor<C: BlockCipher, X> {
+    block_engine: BlockEngine<PcbcDecryptorProcessor<C>, X>
+}
+
+impl<C: BlockCipher, X: PaddingProcessor> PcbcDecryptor<C, X> {
+    /// Create a new PCBC decryption mode object
+    pub fn new(cipher: C, padding: X, iv: &Block<C::BlockSize>)
+               -> PcbcDecryptor<C, DecPadding<X>> {
+        let processor = PcbcDecryptorProcessor {
+            cipher: cipher,
+            iv: iv.clone()
+        };
+        PcbcDecryptor {
+            block_engine: BlockEngine::new(processor,
+                                           DecPadding::wrap(padding))
+        }
+    }
+    pub fn reset(&mut self, iv: &Block<C::BlockSize>) {
+        self.block_engine.reset();
+        self.block_engine.processor.iv = iv.clone();
+    }
+}
+
+impl<C: BlockCipher, X: PaddingProcessor> Decryptor for PcbcDecryptor<C, X> {
+    fn decrypt(&mut self, input: &mut RefReadBuffer,
+               output: &mut RefWriteBuffer, eof: bool)
+               -> Result<BufferResult, SymmetricCipherError> {
+        self.block_engine.process(input, output, eof)
+    }
+}
+
+/// The ciphertext stealing variants of the addendum to NIST SP 800-38A,
+/// which only differ in the order of the last two ciphertext blocks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CtsVariant {
+    /// The truncated next to last block comes first, so the ciphertext is
+    /// the CBC ciphertext with some bytes removed
+    Cs1,
+    /// The last two blocks are swapped if the last one is partial
+    Cs2,
+    /// The last two blocks are always swapped, as in Kerberos (RFC 3962)
+    Cs3
+}
+
+/// CBC mode with ciphertext stealing, which encrypts a message of at least
+/// one block in place without padding it.
+///
+/// The last partial block is padded with the end of the encryption of the
+/// previous block, which is then truncated. Messages of exactly one block are
+/// encrypted as in CBC mode.
+pub struct CbcCts<C> {
+    cipher: C,
+    variant: CtsVariant
+}
+
+impl<C: BlockCipher> CbcCts<C> {
+    /// Create a new CBC-CS object
+    pub fn new(cipher: C, variant: CtsVariant) -> CbcCts<C> {
+        CbcCts { cipher: cipher, variant: variant }
+    }
+
+    /// Encrypt `data` in place
+    pub fn encrypt(&self, iv: &Block<C::BlockSize>, data: &mut [u8])
+                   -> Result<(), SymmetricCipherError> {
+        let block_size = C::BlockSize::to_usize();
+        if data.len() < block_size {
+            return Err(InvalidLength);
+        }
+        let last = self.last_len(data.len());
+        let head = data.len() - last - block_size;
+        let mut prev = iv.clone();
+        let mut x = Block::new();
+        for block in data[..head + block_size].chunks_mut(block_size) {
+            for ((x, &b), &p) in x.iter_mut().zip(block.iter())
+                                  .zip(prev.iter()) {
+                *x = b ^ p;
+            }
+            self.cipher.encrypt_block(&x, &mut prev);
+            block.copy_from_slice(&prev);
+        }
+        if last == 0 {
+            return Ok(());
+        }
+
+        // The last block is padded with zeros before being XORed with the
+        // previous ciphertext block, which thus provides the stolen bytes
+        let (prev_block, last_block) = data[head..].split_at_mut(block_size);
+        for (x, &p) in x.iter_mut().zip(prev.iter()) {
+            *x = p;
+        }
+        for (x, &b) in x.iter_mut().zip(last_block.iter()) {
+            *x ^= b;
+        }
+        self.cipher.encrypt_block(&x, &mut prev);
+        last_block.copy_from_slice(&prev_block[..last]);
+        prev_block.copy_from_slice(&prev);
+        if self.variant == CtsVariant::Cs1 ||
+           (self.variant == CtsVariant::Cs2 && last == block_size) {
+            data[head..].rotate_right(last);
+        }
+        Ok(())
+    }
+
+    /// Decrypt `data` in place
+    pub fn decrypt(&self, iv: &Block<C::BlockSize>, data: &mut [u8])
+                   -> Result<(), SymmetricCipherError> {
+        let block_size = C::BlockSize::to_usize();
+        if data.len() < block_size {
+            return Err(InvalidLength);
+        }
+        let last = self.last_len(data.len());
+        // Unless the message is a single block, the last two blocks are
+        // decrypted separately
+        let head = match last {
+            0 => data.len(),
+            _ => data.len() - last - block_size
+        };
+        let mut prev = iv.clone();
+        let mut y = Block::new();
+        for block in data[..head].chunks_mut(block_size) {
+            let c = Block::from_slice(block);
+            self.cipher.decrypt_block(&c, &mut y);
+            for ((b, &y), &p) in block.iter_mut().zip(y.iter())
+                                      .zip(prev.iter()) {
+                *b = y ^ p;
+            }
+            prev = c;
+        }
+        if last == 0 {
+            return Ok(());
+        }
+
+        // Bring the last two blocks in the CS3 order: the last full
+        // ciphertext block, then the truncated one
+        if self.variant == CtsVariant::Cs1 ||
+           (self.variant == CtsVariant::Cs2 && last == block_size) {
+            data[head..].rotate_left(last);
+        }
+        let (last_block, stolen) = data[head..].split_at_mut(block_size);
+        let c = Block::from_slice(last_block);
+        self.cipher.decrypt_block(&c, &mut y);
+        // The end of the decryption is the stolen end of the truncated
+        // ciphertext block, since the plaintext was padded with zeros
+        let mut c = y.clone();
+        c[..last].copy_from_slice(stolen);
+        for (s, &y) in stolen.iter_mut().zip(y.iter()) {
+            *s ^= y;
+        }
+        self.cipher.decrypt_block(&c, &mut y);
+        for ((b, &y), &p) in last_block.iter_mut().zip(y.iter())
+                                       .zip(prev.iter()) {
+            *b = y ^ p;
+        }
+        Ok(())
+    }
+
+    /// Returns the length of the last block, or 0 if the message is a
+    /// single block and encrypted as in CBC mode
+    fn last_len(&self, len: usize) -> usize {
+        let block_size = C::BlockSize::to_usize();
+        if len == block_size {
+            0
+        } else {
+            (len - 1) % block_size + 1
+        }
+    }
+}
+
+/// Implements `Encryptor` and `Decryptor` for a mode whose `process` method
+/// encrypts or decrypts a slice of any length
+macro_rules! impl_feedback_mode {
+    ($name:ident) => {
+        impl<C: BlockCipher> Encryptor for $name<C> {
+            fn encrypt(&mut self, input: &mut RefReadBuffer,
+                       output: &mut RefWriteBuffer, _: bool)
+                       -> Result<BufferResult, SymmetricCipherError> {
+                let count = cmp::min(input.remaining(), output.remaining());
+                self.process(input.take_next(count), output.take_next(count),
+                             false);
+                if input.is_empty() {
+                    Ok(BufferUnderflow)
+                } else {
+                    Ok(BufferOverflow)
+                }
+            }
+        }
+
+        impl<C: BlockCipher> Decryptor for $name<C> {
+            fn decrypt(&mut self, input: &mut RefReadBuffer,
+                       output: &mut RefWriteBuffer, _: bool)
+                       -> Result<BufferResult, SymmetricCipherError> {
+                let count = cmp::min(input.remaining(), output.remaining());
+                self.process(input.take_next(count), output.take_next(count),
+                             true);
+                if input.is_empty() {
+                    Ok(BufferUnderflow)
+                } else {
+                    Ok(BufferOverflow)
+                }
+            }
+        }
+    }
+}
+
+/// CFB mode with segments of a whole block, e.g. CFB-128 for AES. The input
+/// does not have to be a multiple of the block size, the last segment may be
+/// partial as in OpenPGP.
+pub struct Cfb<C: BlockCipher> {
+    cipher: C,
+    /// The previous ciphertext block, or the IV
+    iv: Block<C::BlockSize>,
+    /// Encryption of the previous ciphertext block, of which `pos` bytes
+    /// are used
+    bytes: Block<C::BlockSize>,
+    pos: usize
+}
+
+impl<C: BlockCipher> Cfb<C> {
+    /// Create a new CFB object
+    pub fn new(cipher: C, iv: &Block<C::BlockSize>) -> Cfb<C> {
+        Cfb {
+            cipher: cipher,
+            iv: iv.clone(),
+            bytes: Block::new(),
+            pos: C::BlockSize::to_usize()
+        }
+    }
+    pub fn reset(&mut self, iv: &Block<C::BlockSize>) {
+        self.iv = iv.clone();
+        self.pos = self.bytes.len();
+    }
+    fn process(&mut self, input: &[u8], output: &mut [u8], decrypt: bool) {
+        assert!(input.len() == output.len());
+        for (&x, o) in input.iter().zip(output.iter_mut()) {
+            if self.pos == self.bytes.len() {
+                self.cipher.encrypt_block(&self.iv, &mut self.bytes);
+                self.pos = 0;
+            }
+            *o = x ^ self.bytes[self.pos];
+            // The ciphertext is shifted into the register
+            self.iv[self.pos] = if decrypt { x } else { *o };
+            self.pos += 1;
+        }
+    }
+}
+
+impl_feedback_mode!(Cfb);
+
+/// CFB mode with 8-bit segments, which needs a block cipher call per byte
+pub struct Cfb8<C: BlockCipher> {
+    cipher: C,
+    /// The shift register, initially the IV
+    iv: Block<C::BlockSize>
+}
+
+impl<C: BlockCipher> Cfb8<C> {
+    /// Create a new CFB-8 object
+    pub fn new(cipher: C, iv: &Block<C::BlockSize>) -> Cfb8<C> {
+        Cfb8 { cipher: cipher, iv: iv.clone() }
+    }
+    pub fn reset(&mut self, iv: &Block<C::BlockSize>) {
+        self.iv = iv.clone();
+    }
+    fn process(&mut self, input: &[u8], output: &mut [u8], decrypt: bool) {
+        assert!(input.len() == output.len());
+        let mut bytes = Block::new();
+        let last = self.iv.len() - 1;
+        for (&x, o) in input.iter().zip(output.iter_mut()) {
+            self.cipher.encrypt_block(&self.iv, &mut bytes);
+            *o = x ^ bytes[0];
+            self.iv.rotate_left(1);
+            self.iv[last] = if decrypt { x } else { *o };
+        }
+    }
+}
+
+impl_feedback_mode!(Cfb8);
+
+/// CFB mode with 1-bit segments, which needs a block cipher call per bit.
+/// The bits of every byte are processed from the most significant one.
+pub struct Cfb1<C: BlockCipher> {
+    cipher: C,
+    /// The shift register, initially the IV
+    iv: Block<C::BlockSize>
+}
+
+impl<C: BlockCipher> Cfb1<C> {
+    /// Create a new CFB-1 object
+    pub fn new(cipher: C, iv: &Block<C::BlockSize>) -> Cfb1<C> {
+        Cfb1 { cipher: cipher, iv: iv.clone() }
+    }
+    pub fn reset(&mut self, iv: &Block<C::BlockSize>) {
+        self.iv = iv.clone();
+    }
+    fn process(&mut self, input: &[u8], output: &mut [u8], decrypt: bool) {
+        assert!(input.len() == output.len());
+        let mut bytes = Block::new();
+        let last = self.iv.len() - 1;
+        for (&x, o) in input.iter().zip(output.iter_mut()) {
+            *o = 0;
+            for i in (0..8).rev() {
+                self.cipher.encrypt_block(&self.iv, &mut bytes);
+                let bit_in = (x >> i) & 1;
+                let bit_out = bit_in ^ (bytes[0] >> 7);
+                *o |= bit_out << i;
+                for j in 0..last {
+                    self.iv[j] = (self.iv[j] << 1) | (self.iv[j + 1] >> 7);
+                }
+                self.iv[last] = (self.iv[last] << 1) |
+                                if decrypt { bit_in } else { bit_out };
+            }
+        }
+    }
+}
+
+impl_feedback_mode!(Cfb1);
+
+/// OFB mode, the cipher is repeatedly applied to the IV to produce the
+/// keystream
+pub struct Ofb<C: BlockCipher> {
+    cipher: C,
+    /// The last keystream block, or the IV, of which `pos` bytes are used
+    bytes: Block<C::BlockSize>,
+    pos: usize
+}
+
+impl<C: BlockCipher> Ofb<C> {
+    /// Create a new OFB object
+    pub fn new(cipher: C, iv: &Block<C::BlockSize>) -> Ofb<C> {
+        Ofb {
+            cipher: cipher,
+            bytes: iv.clone(),
+            pos: C::BlockSize::to_usize()
+        }
+    }
+    pub fn reset(&mut self, iv: &Block<C::BlockSize>) {
+        self.bytes = iv.clone();
+        self.pos = self.bytes.len();
+    }
+    fn process(&mut self, input: &[u8], output: &mut [u8]) {
+        assert!(input.len() == output.len());
+        for (&x, o) in input.iter().zip(output.iter_mut()) {
+            if self.pos == self.bytes.len() {
+                let prev = self.bytes.clone();
+                self.cipher.encrypt_block(&prev, &mut self.bytes);
+                self.pos = 0;
+            }
+            *o = x ^ self.bytes[self.pos];
+            self.pos += 1;
+        }
+    }
+}
+
+impl<C: BlockCipher> SynchronousStreamCipher for Ofb<C> {
+    fn process(&mut self, input: &[u8], output: &mut [u8]) {
+        self.process(input, output);
+    }
+}
+
+impl<C: BlockCipher> Encryptor for Ofb<C> {
+    fn encrypt(&mut self, input: &mut RefReadBuffer,
+               output: &mut RefWriteBuffer, _: bool)
+               -> Result<BufferResult, SymmetricCipherError> {
+        symm_enc_or_dec(self, input, output)
+    }
+}
+
+impl<C: BlockCipher> Decryptor for Ofb<C> {
+    fn decrypt(&mut self, input: &mut RefReadBuffer,
+               output: &mut RefWriteBuffer, _: bool)
+               -> Result<BufferResult, SymmetricCipherError> {
+        symm_enc_or_dec(self, input, output)
+    }
+}
+
+/// Adds `n` to the big-endian number `ctr`, modulo its size
+fn add_ctr(ctr: &mut [u8], n: u64) {
+    let mut carry = n;
+    for b in ctr.iter_mut().rev() {
+        if carry == 0 {
             break;
         }
-        ammount = 1;
+        let sum = *b as u64 + (carry & 0xff);
+        *b = sum as u8;
+        carry = (carry >> 8) + (sum >> 8);
+    }
+}
+
+/// Adds `n` to the counter `ctr` of at most 64 bits, stored in big-endian or
+/// little-endian order. If the counter overflows it is left unchanged and
+/// false is returned.
+fn add_ctr_checked(ctr: &mut [u8], n: u64, little_endian: bool) -> bool {
+    let len = ctr.len();
+    let index = |i: usize| if little_endian { len - 1 - i } else { i };
+    let mut value = 0u64;
+    for i in 0..len {
+        value = (value << 8) | ctr[index(i)] as u64;
+    }
+    let max = !0u64 >> (64 - 8 * len);
+    match value.checked_add(n) {
+        Some(value) if value <= max => {
+            for i in 0..len {
+                ctr[index(i)] = (value >> (8 * (len - 1 - i))) as u8;
+            }
+            true
+        }
+        _ => false
+    }
+}
+
+/// The way CTR mode derives the counter block of every block of keystream
+/// from the initial counter block
+pub trait CtrFlavor {
+    /// Add `n` to the counter in `block`. Returns false if the counter
+    /// overflows, in which case the keystream would repeat.
+    fn add(block: &mut [u8], n: u64) -> bool;
+}
+
+/// The whole block, of at most 128 bits, is a big-endian counter. It wraps
+/// around, since a stream is too short to repeat the keystream.
+pub struct Ctr128BE;
+
+impl CtrFlavor for Ctr128BE {
+    fn add(block: &mut [u8], n: u64) -> bool {
+        add_ctr(block, n);
+        true
     }
 }
 
-/// CTR Mode
-pub struct CtrMode<A> {
-    algo: A,
-    ctr: Vec<u8>,
-    bytes: OwnedReadBuffer
+/// The last 32 bits of the block are a big-endian counter, as in GCM
+pub struct Ctr32BE;
+
+impl CtrFlavor for Ctr32BE {
+    fn add(block: &mut [u8], n: u64) -> bool {
+        let len = block.len();
+        add_ctr_checked(&mut block[len - 4..], n, false)
+    }
 }
 
-impl <A: BlockEncryptor> CtrMode<A> {
-    /// Create a new CTR object
-    pub fn new(algo: A, ctr: Vec<u8>) -> CtrMode<A> {
-        let block_size = algo.block_size();
+/// The first 32 bits of the block are a little-endian counter, as in
+/// AES-GCM-SIV
+pub struct Ctr32LE;
+
+impl CtrFlavor for Ctr32LE {
+    fn add(block: &mut [u8], n: u64) -> bool {
+        add_ctr_checked(&mut block[..4], n, true)
+    }
+}
+
+/// The last 64 bits of the block are a big-endian counter
+pub struct Ctr64BE;
+
+impl CtrFlavor for Ctr64BE {
+    fn add(block: &mut [u8], n: u64) -> bool {
+        let len = block.len();
+        add_ctr_checked(&mut block[len - 8..], n, false)
+    }
+}
+
+/// The first 64 bits of the block are a little-endian counter
+pub struct Ctr64LE;
+
+impl CtrFlavor for Ctr64LE {
+    fn add(block: &mut [u8], n: u64) -> bool {
+        add_ctr_checked(&mut block[..8], n, true)
+    }
+}
+
+/// Checks that `len` bytes of keystream can be produced from byte `pos` of
+/// the stream starting at the counter block `ctr`, without overflowing the
+/// counter or the position
+fn ctr_available<F: CtrFlavor>(ctr: &[u8], pos: u64, len: usize,
+                               block_size: usize) -> bool {
+    if len == 0 {
+        return true;
+    }
+    let end = match pos.checked_add(len as u64) {
+        Some(end) => end,
+        None => return false
+    };
+    let mut last = [0u8; MAX_BLOCK_SIZE];
+    let last = &mut last[..block_size];
+    last.copy_from_slice(ctr);
+    F::add(last, (end - 1) / block_size as u64)
+}
+
+/// Advances `ctr` to the counter of the block containing byte `offset`.
+/// At a block boundary only the previous block has to exist: its successor
+/// is only used once more keystream is requested, which `ctr_available`
+/// rejects, so like after processing the last block the counter is left
+/// unchanged if it overflows.
+fn seek_ctr<F: CtrFlavor>(ctr: &mut [u8], offset: u64, block_size: usize)
+                          -> bool {
+    let blocks = offset / block_size as u64;
+    let rem = offset % block_size as u64;
+    if rem == 0 && blocks > 0 {
+        if !F::add(ctr, blocks - 1) {
+            return false;
+        }
+        F::add(ctr, 1);
+        true
+    } else {
+        F::add(ctr, blocks)
+    }
+}
+
+/// CTR Mode. How the counter is stored in the block depends on the flavour
+/// `F`, by default the whole block is a big-endian counter.
+///
+/// The position in the keystream can be changed with `seek()`. Producing
+/// keystream beyond the end of the counter range is an error: `encrypt()`
+/// and `decrypt()` fail with `InvalidLength` and `process()` panics.
+pub struct CtrMode<C: BlockCipher, F = Ctr128BE> {
+    cipher: C,
+    /// The counter block at position 0
+    ctr0: Block<C::BlockSize>,
+    /// The counter block of the next block of keystream
+    ctr: Block<C::BlockSize>,
+    /// Keystream of the previous counter, of which `pos` bytes are used
+    bytes: Block<C::BlockSize>,
+    pos: usize,
+    /// Position in the keystream, in bytes
+    offset: u64,
+    flavor: PhantomData<F>
+}
+
+impl<C: BlockCipher> CtrMode<C> {
+    /// Create a new CTR object, with the whole block as a counter
+    pub fn new(cipher: C, ctr: &Block<C::BlockSize>) -> CtrMode<C> {
+        CtrMode::with_flavor(cipher, ctr)
+    }
+}
+
+impl<C: BlockCipher, F: CtrFlavor> CtrMode<C, F> {
+    /// Create a new CTR object with the counter flavour `F`
+    pub fn with_flavor(cipher: C, ctr: &Block<C::BlockSize>)
+                       -> CtrMode<C, F> {
+        assert!(C::BlockSize::to_usize() <= MAX_BLOCK_SIZE);
         CtrMode {
-            algo: algo,
-            ctr: ctr,
-            bytes: OwnedReadBuffer::new_with_len(repeat(0).take(block_size).collect(), 0)
+            cipher: cipher,
+            ctr0: ctr.clone(),
+            ctr: ctr.clone(),
+            bytes: Block::new(),
+            pos: C::BlockSize::to_usize(),
+            offset: 0,
+            flavor: PhantomData
         }
     }
-    pub fn reset(&mut self, ctr: &[u8]) {
-        copy_memory(ctr, &mut self.ctr);
-        self.bytes.reset();
+    pub fn reset(&mut self, ctr: &Block<C::BlockSize>) {
+        self.ctr0 = ctr.clone();
+        self.ctr = ctr.clone();
+        self.pos = self.bytes.len();
+        self.offset = 0;
     }
+
+    /// Move to byte `offset` of the keystream. Fails with `InvalidLength`
+    /// if the counter would overflow before reaching it. The end of the
+    /// counter range can be reached, producing keystream from there fails.
+    pub fn seek(&mut self, offset: u64) -> Result<(), SymmetricCipherError> {
+        let block_size = C::BlockSize::to_usize();
+        let mut ctr = self.ctr0.clone();
+        if !seek_ctr::<F>(&mut ctr, offset, block_size) {
+            return Err(InvalidLength);
+        }
+        self.ctr = ctr;
+        self.pos = block_size;
+        self.offset = offset;
+        let rem = (offset % block_size as u64) as usize;
+        if rem > 0 {
+            self.next_block();
+            self.pos = rem;
+        }
+        Ok(())
+    }
+
+    /// Returns the position in the keystream, in bytes
+    pub fn current_pos(&self) -> u64 {
+        self.offset
+    }
+
+    fn available(&self, len: usize) -> bool {
+        ctr_available::<F>(&self.ctr0, self.offset, len, self.bytes.len())
+    }
+
+    fn next_block(&mut self) {
+        self.cipher.encrypt_block(&self.ctr, &mut self.bytes);
+        // The last counter may overflow, but is never used
+        F::add(&mut self.ctr, 1);
+        self.pos = 0;
+    }
+
     fn process(&mut self, input: &[u8], output: &mut [u8]) {
         assert!(input.len() == output.len());
+        assert!(self.available(input.len()), "CTR counter overflow");
         let len = input.len();
         let mut i = 0;
         while i < len {
-            if self.bytes.is_empty() {
-                let mut wb = self.bytes.borrow_write_buffer();
-                self.algo.encrypt_block(&self.ctr[..], wb.take_remaining());
-                add_ctr(&mut self.ctr, 1);
+            if self.pos == self.bytes.len() {
+                self.next_block();
             }
-            let count = cmp::min(self.bytes.remaining(), len - i);
-            let bytes_it = self.bytes.take_next(count).iter();
+            let count = cmp::min(self.bytes.len() - self.pos, len - i);
+            let bytes_it = self.bytes[self.pos..self.pos + count].iter();
             let in_it = input[i..].iter();
             let out_it = output[i..].iter_mut();
             for ((&x, &y), o) in bytes_it.zip(in_it).zip(out_it) {
                 *o = x ^ y;
             }
+            self.pos += count;
             i += count;
         }
+        self.offset += len as u64;
+    }
+
+    fn process_buffers(&mut self, input: &mut RefReadBuffer,
+                       output: &mut RefWriteBuffer)
+                       -> Result<BufferResult, SymmetricCipherError> {
+        if !self.available(cmp::min(input.remaining(), output.remaining())) {
+            return Err(InvalidLength);
+        }
+        symm_enc_or_dec(self, input, output)
     }
 }
 
-impl <A: BlockEncryptor> SynchronousStreamCipher for CtrMode<A> {
+impl<C: BlockCipher, F: CtrFlavor> SynchronousStreamCipher for CtrMode<C, F> {
     fn process(&mut self, input: &[u8], output: &mut [u8]) {
         self.process(input, output);
     }
 }
 
-impl <A: BlockEncryptor> Encryptor for CtrMode<A> {
-    fn encrypt(&mut self, input: &mut RefReadBuffer, output: &mut RefWriteBuffer, _: bool)
-            -> Result<BufferResult, SymmetricCipherError> {
-        symm_enc_or_dec(self, input, output)
+impl<C: BlockCipher, F: CtrFlavor> Encryptor for CtrMode<C, F> {
+    fn encrypt(&mut self, input: &mut RefReadBuffer,
+               output: &mut RefWriteBuffer, _: bool)
+               -> Result<BufferResult, SymmetricCipherError> {
+        self.process_buffers(input, output)
     }
 }
 
-impl <A: BlockEncryptor> Decryptor for CtrMode<A> {
-    fn decrypt(&mut self, input: &mut RefReadBuffer, output: &mut RefWriteBuffer, _: bool)
-            -> Result<BufferResult, SymmetricCipherError> {
-        symm_enc_or_dec(self, input, output)
+impl<C: BlockCipher, F: CtrFlavor> Decryptor for CtrMode<C, F> {
+    fn decrypt(&mut self, input: &mut RefReadBuffer,
+               output: &mut RefWriteBuffer, _: bool)
+               -> Result<BufferResult, SymmetricCipherError> {
+        self.process_buffers(input, output)
     }
 }
 
-/// CTR Mode that operates on 8 blocks at a time
-pub struct CtrModeX8<A> {
-    algo: A,
-    ctr_x8: Vec<u8>,
-    bytes: OwnedReadBuffer
+/// Largest block size supported by `CtrMode` and `CtrModeX8`
+const MAX_BLOCK_SIZE: usize = 16;
+
+/// CTR Mode that operates on 8 blocks at a time, for ciphers with blocks of
+/// up to 128 bits. It supports the same counter flavours and seeking as
+/// `CtrMode`.
+pub struct CtrModeX8<C, F = Ctr128BE> {
+    cipher: C,
+    /// The counter block at position 0
+    ctr0: [u8; MAX_BLOCK_SIZE],
+    ctr_x8: [u8; 8 * MAX_BLOCK_SIZE],
+    bytes: [u8; 8 * MAX_BLOCK_SIZE],
+    pos: usize,
+    len: usize,
+    /// Position in the keystream, in bytes
+    offset: u64,
+    flavor: PhantomData<F>
 }
 
-fn construct_ctr_x8(in_ctr: &[u8], out_ctr_x8: &mut [u8]) {
+/// Fills `out_ctr_x8` with 8 consecutive counter blocks starting at
+/// `in_ctr`. Counters beyond an overflow are never used, so the result of
+/// the additions is ignored.
+fn construct_ctr_x8<F: CtrFlavor>(in_ctr: &[u8], out_ctr_x8: &mut [u8]) {
     for (i, ctr_i) in out_ctr_x8.chunks_mut(in_ctr.len()).enumerate() {
-        copy_memory(in_ctr, ctr_i);
-        add_ctr(ctr_i, i as u8);
+        ctr_i.copy_from_slice(in_ctr);
+        F::add(ctr_i, i as u64);
+    }
+}
+
+impl<C: BlockCipher + BlockEncryptorX8> CtrModeX8<C> {
+    /// Create a new CTR object that operates on 8 blocks at a time, with
+    /// the whole block as a counter
+    pub fn new(cipher: C, ctr: &Block<C::BlockSize>) -> CtrModeX8<C> {
+        CtrModeX8::with_flavor(cipher, ctr)
     }
 }
 
-impl <A: BlockEncryptorX8> CtrModeX8<A> {
-    /// Create a new CTR object that operates on 8 blocks at a time
-    pub fn new(algo: A, ctr: &[u8]) -> CtrModeX8<A> {
-        let block_size = algo.block_size();
-        let mut ctr_x8: Vec<u8> = repeat(0).take(block_size * 8).collect();
-        construct_ctr_x8(ctr, &mut ctr_x8);
+impl<C: BlockCipher + BlockEncryptorX8, F: CtrFlavor> CtrModeX8<C, F> {
+    /// Create a new CTR object that operates on 8 blocks at a time, with
+    /// the counter flavour `F`
+    pub fn with_flavor(cipher: C, ctr: &Block<C::BlockSize>)
+                       -> CtrModeX8<C, F> {
+        let block_size = C::BlockSize::to_usize();
+        assert!(block_size <= MAX_BLOCK_SIZE);
+        let len = 8 * block_size;
+        let mut ctr0 = [0u8; MAX_BLOCK_SIZE];
+        ctr0[..block_size].copy_from_slice(ctr);
+        let mut ctr_x8 = [0u8; 8 * MAX_BLOCK_SIZE];
+        construct_ctr_x8::<F>(ctr, &mut ctr_x8[..len]);
         CtrModeX8 {
-            algo: algo,
+            cipher: cipher,
+            ctr0: ctr0,
             ctr_x8: ctr_x8,
-            bytes: OwnedReadBuffer::new_with_len(repeat(0).take(block_size * 8).collect(), 0)
+            bytes: [0u8; 8 * MAX_BLOCK_SIZE],
+            pos: len,
+            len: len,
+            offset: 0,
+            flavor: PhantomData
+        }
+    }
+    pub fn reset(&mut self, ctr: &Block<C::BlockSize>) {
+        self.ctr0[..ctr.len()].copy_from_slice(ctr);
+        construct_ctr_x8::<F>(ctr, &mut self.ctr_x8[..self.len]);
+        self.pos = self.len;
+        self.offset = 0;
+    }
+
+    /// Move to byte `offset` of the keystream. Fails with `InvalidLength`
+    /// if the counter would overflow before reaching it. The end of the
+    /// counter range can be reached, producing keystream from there fails.
+    pub fn seek(&mut self, offset: u64) -> Result<(), SymmetricCipherError> {
+        let block_size = C::BlockSize::to_usize();
+        let mut ctr = [0u8; MAX_BLOCK_SIZE];
+        let ctr = &mut ctr[..block_size];
+        ctr.copy_from_slice(&self.ctr0[..block_size]);
+        if !seek_ctr::<F>(ctr, offset, block_size) {
+            return Err(InvalidLength);
         }
+        construct_ctr_x8::<F>(ctr, &mut self.ctr_x8[..self.len]);
+        self.pos = self.len;
+        self.offset = offset;
+        let rem = (offset % block_size as u64) as usize;
+        if rem > 0 {
+            self.next_blocks();
+            self.pos = rem;
+        }
+        Ok(())
+    }
+
+    /// Returns the position in the keystream, in bytes
+    pub fn current_pos(&self) -> u64 {
+        self.offset
     }
-    pub fn reset(&mut self, ctr: &[u8]) {
-        construct_ctr_x8(ctr, &mut self.ctr_x8);
-        self.bytes.reset();
+
+    fn available(&self, len: usize) -> bool {
+        let block_size = C::BlockSize::to_usize();
+        ctr_available::<F>(&self.ctr0[..block_size], self.offset, len,
+                           block_size)
+    }
+
+    fn next_blocks(&mut self) {
+        let block_size = C::BlockSize::to_usize();
+        self.cipher.encrypt_block_x8(&self.ctr_x8[..self.len],
+                                     &mut self.bytes[..self.len]);
+        for ctr_i in self.ctr_x8[..self.len].chunks_mut(block_size) {
+            F::add(ctr_i, 8);
+        }
+        self.pos = 0;
     }
+
     fn process(&mut self, input: &[u8], output: &mut [u8]) {
         // TODO - Can some of this be combined with regular CtrMode?
         assert!(input.len() == output.len());
+        assert!(self.available(input.len()), "CTR counter overflow");
         let len = input.len();
         let mut i = 0;
         while i < len {
-            if self.bytes.is_empty() {
-                let mut wb = self.bytes.borrow_write_buffer();
-                self.algo.encrypt_block_x8(&self.ctr_x8[..], wb.take_remaining());
-                for ctr_i in &mut self.ctr_x8.chunks_mut(self.algo.block_size()) {
-                    add_ctr(ctr_i, 8);
-                }
+            if self.pos == self.len {
+                self.next_blocks();
             }
-            let count = cmp::min(self.bytes.remaining(), len - i);
-            let bytes_it = self.bytes.take_next(count).iter();
+            let count = cmp::min(self.len - self.pos, len - i);
+            let bytes_it = self.bytes[self.pos..self.pos + count].iter();
             let in_it = input[i..].iter();
-            let out_it = &mut output[i..];
-            for ((&x, &y), o) in bytes_it.zip(in_it).zip(out_it.iter_mut()) {
+            let out_it = output[i..].iter_mut();
+            for ((&x, &y), o) in bytes_it.zip(in_it).zip(out_it) {
                 *o = x ^ y;
             }
+            self.pos += count;
             i += count;
         }
+        self.offset += len as u64;
+    }
+
+    fn process_buffers(&mut self, input: &mut RefReadBuffer,
+                       output: &mut RefWriteBuffer)
+                       -> Result<BufferResult, SymmetricCipherError> {
+        if !self.available(cmp::min(input.remaining(), output.remaining())) {
+            return Err(InvalidLength);
+        }
+        symm_enc_or_dec(self, input, output)
     }
 }
 
-impl <A: BlockEncryptorX8> SynchronousStreamCipher for CtrModeX8<A> {
+impl<C: BlockCipher + BlockEncryptorX8, F: CtrFlavor> SynchronousStreamCipher
+    for CtrModeX8<C, F> {
     fn process(&mut self, input: &[u8], output: &mut [u8]) {
         self.process(input, output);
     }
 }
 
-impl <A: BlockEncryptorX8> Encryptor for CtrModeX8<A> {
-    fn encrypt(&mut self, input: &mut RefReadBuffer, output: &mut RefWriteBuffer, _: bool)
-            -> Result<BufferResult, SymmetricCipherError> {
-        symm_enc_or_dec(self, input, output)
+impl<C: BlockCipher + BlockEncryptorX8, F: CtrFlavor> Encryptor
+    for CtrModeX8<C, F> {
+    fn encrypt(&mut self, input: &mut RefReadBuffer,
+               output: &mut RefWriteBuffer, _: bool)
+               -> Result<BufferResult, SymmetricCipherError> {
+        self.process_buffers(input, output)
     }
 }
 
-impl <A: BlockEncryptorX8> Decryptor for CtrModeX8<A> {
-    fn decrypt(&mut self, input: &mut RefReadBuffer, output: &mut RefWriteBuffer, _: bool)
-            -> Result<BufferResult, SymmetricCipherError> {
-        symm_enc_or_dec(self, input, output)
+impl<C: BlockCipher + BlockEncryptorX8, F: CtrFlavor> Decryptor
+    for CtrModeX8<C, F> {
+    fn decrypt(&mut self, input: &mut RefReadBuffer,
+               output: &mut RefWriteBuffer, _: bool)
+               -> Result<BufferResult, SymmetricCipherError> {
+        self.process_buffers(input, output)
+    }
+}
+
+/// Largest data unit allowed by IEEE 1619, in bytes
+const XTS_MAX_LEN: usize = 16 << 20;
+
+/// Multiplies an XTS tweak, a little-endian element of GF(2^128), by `x`
+fn xts_mul_alpha(t: &mut [u8]) {
+    let mut carry = 0;
+    for b in t.iter_mut() {
+        let next = *b >> 7;
+        *b = (*b << 1) | carry;
+        carry = next;
+    }
+    t[0] ^= 0x87 & 0u8.wrapping_sub(carry);
+}
+
+/// XTS mode (IEEE 1619, NIST SP 800-38E) for the encryption of storage
+/// sectors, as used by dm-crypt and BitLocker.
+///
+/// Every sector is encrypted independently and in place under a tweak
+/// derived from its number, so the ciphertext has the same length as the
+/// plaintext. Sectors must be at least one block long, a final partial
+/// block is handled with ciphertext stealing.
+pub struct Xts<C> {
+    cipher: C,
+    tweak_cipher: C
+}
+
+impl<C: BlockCipher<BlockSize = U16>> Xts<C> {
+    /// Create a new XTS object. `cipher` encrypts the data and
+    /// `tweak_cipher` the tweaks, for XTS-AES-128 they are keyed with the
+    /// first and the second half of the 256-bit key.
+    pub fn new(cipher: C, tweak_cipher: C) -> Xts<C> {
+        Xts { cipher: cipher, tweak_cipher: tweak_cipher }
+    }
+
+    /// Encrypt the sector `sector_no`, the tweak is the sector number in
+    /// little-endian as with the `plain64` IV of dm-crypt
+    pub fn encrypt_sector(&self, sector_no: u64, data: &mut [u8])
+                          -> Result<(), SymmetricCipherError> {
+        self.process(&sector_tweak(sector_no), data, false)
+    }
+
+    /// Decrypt the sector `sector_no`
+    pub fn decrypt_sector(&self, sector_no: u64, data: &mut [u8])
+                          -> Result<(), SymmetricCipherError> {
+        self.process(&sector_tweak(sector_no), data, true)
+    }
+
+    /// Encrypt a data unit under an arbitrary 128-bit tweak
+    pub fn encrypt_with_tweak(&self, tweak: &Block128, data: &mut [u8])
+                              -> Result<(), SymmetricCipherError> {
+        self.process(tweak, data, false)
+    }
+
+    /// Decrypt a data unit under an arbitrary 128-bit tweak
+    pub fn decrypt_with_tweak(&self, tweak: &Block128, data: &mut [u8])
+                              -> Result<(), SymmetricCipherError> {
+        self.process(tweak, data, true)
+    }
+
+    /// Computes `E(block ^ t) ^ t`, or the same with the decryption
+    fn xex(&self, t: &Block128, block: &mut [u8], decrypt: bool) {
+        let mut x = Block128::from_slice(block);
+        let mut y = Block128::new();
+        for (x, &t) in x.iter_mut().zip(t.iter()) {
+            *x ^= t;
+        }
+        if decrypt {
+            self.cipher.decrypt_block(&x, &mut y);
+        } else {
+            self.cipher.encrypt_block(&x, &mut y);
+        }
+        for (b, (&y, &t)) in block.iter_mut().zip(y.iter().zip(t.iter())) {
+            *b = y ^ t;
+        }
+    }
+
+    fn process(&self, tweak: &Block128, data: &mut [u8], decrypt: bool)
+               -> Result<(), SymmetricCipherError> {
+        if data.len() < 16 || data.len() > XTS_MAX_LEN {
+            return Err(InvalidLength);
+        }
+        let mut t = Block128::new();
+        self.tweak_cipher.encrypt_block(tweak, &mut t);
+
+        // With ciphertext stealing, the last full block and the partial
+        // block are processed together
+        let rem = data.len() % 16;
+        let full = if rem == 0 { data.len() } else { data.len() - 16 - rem };
+        let (body, tail) = data.split_at_mut(full);
+        for block in body.chunks_mut(16) {
+            self.xex(&t, block, decrypt);
+            xts_mul_alpha(&mut t);
+        }
+        if rem > 0 {
+            let mut next = t;
+            xts_mul_alpha(&mut next);
+            // Decryption undoes the second step first, so uses the tweaks in
+            // the opposite order
+            let (t1, t2) = if decrypt { (&next, &t) } else { (&t, &next) };
+            let (last, partial) = tail.split_at_mut(16);
+            self.xex(t1, last, decrypt);
+            // The head of the processed block becomes the partial block and
+            // its tail is stolen to pad the partial input
+            for (a, b) in last.iter_mut().zip(partial.iter_mut()) {
+                mem::swap(a, b);
+            }
+            self.xex(t2, last, decrypt);
+        }
+        Ok(())
+    }
+}
+
+fn sector_tweak(sector_no: u64) -> Block128 {
+    let mut tweak = Block128::new();
+    for (i, b) in tweak[..8].iter_mut().enumerate() {
+        *b = (sector_no >> (8 * i)) as u8;
     }
+    tweak
 }
 
-// #[cfg(test)]
-// mod tests;
+#[cfg(test)]
+mod tests;
 
-// #[cfg(test)]
-// mod bench;
+#[cfg(test)]
+mod bench;
@@ -0,0 +1,670 @@
+use generic_array::GenericArray;
+use crypto_buffers::{BufferResult, ReadBuffer, WriteBuffer, RefReadBuffer,
+                     RefWriteBuffer};
+use crypto_symmetric::{Encryptor, Decryptor, SymmetricCipherError};
+use aes::{Aes128, Aes192, Aes256};
+use super::{EcbEncryptor, EcbDecryptor, CbcEncryptor, CbcDecryptor,
+            PcbcEncryptor, PcbcDecryptor, Cfb, Cfb8, Cfb1, Ofb, CtrMode,
+            CtrModeX8, Ctr32BE, Ctr32LE, Ctr64BE, Ctr64LE, Xts, CbcCts,
+            CtsVariant, NoPadding, PkcsPadding,
+            AnsiX923Padding, Iso10126Padding, Iso7816Padding, ZeroPadding};
+
+struct TestVector {
+    pub name: &'static str,
+    pub key: &'static [u8],
+    pub iv: &'static [u8],
+    pub input: &'static [u8],
+    pub output: &'static [u8],
+}
+
+macro_rules! get_vectors {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            TestVector {
+                name: $name,
+                key: include_bytes!(concat!("data/", $name, ".key.bin")),
+                iv: include_bytes!(concat!("data/", $name, ".iv.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                output: include_bytes!(concat!("data/", $name, ".output.bin")),
+            },
+        )*]
+    };
+}
+
+struct XtsTestVector {
+    pub name: &'static str,
+    pub key1: &'static [u8],
+    pub key2: &'static [u8],
+    pub sector: &'static [u8],
+    pub input: &'static [u8],
+    pub output: &'static [u8],
+}
+
+macro_rules! get_xts_vectors {
+    ( $( $name:expr ),*  ) => {
+        [$(
+            XtsTestVector {
+                name: $name,
+                key1: include_bytes!(concat!("data/", $name, ".key1.bin")),
+                key2: include_bytes!(concat!("data/", $name, ".key2.bin")),
+                sector: include_bytes!(concat!("data/", $name, ".sector.bin")),
+                input: include_bytes!(concat!("data/", $name, ".input.bin")),
+                output: include_bytes!(concat!("data/", $name, ".output.bin")),
+            },
+        )*]
+    };
+}
+
+const MAX_MSG_LEN: usize = 64;
+
+// Input and output buffer sizes used to feed the modes, from byte by byte
+// to everything at once
+const STEPS: [(usize, usize); 6] = [(1, 1), (3, 5), (16, 16), (17, 15),
+                                    (33, 64), (64, 64)];
+
+/// Runs `input` through an encryptor or decryptor, giving it at most
+/// `in_step` bytes of input and `out_step` bytes of output space per call,
+/// and returns the length of the output
+fn run<F>(mut f: F, input: &[u8], output: &mut [u8], in_step: usize,
+          out_step: usize) -> Result<usize, SymmetricCipherError>
+    where F: FnMut(&mut RefReadBuffer, &mut RefWriteBuffer, bool)
+                   -> Result<BufferResult, SymmetricCipherError>
+{
+    let (mut in_pos, mut out_pos) = (0, 0);
+    loop {
+        let in_end = cmp_min(in_pos + in_step, input.len());
+        let out_end = cmp_min(out_pos + out_step, output.len());
+        let eof = in_end == input.len();
+        let mut rb = RefReadBuffer::new(&input[in_pos..in_end]);
+        let mut wb = RefWriteBuffer::new(&mut output[out_pos..out_end]);
+        let result = f(&mut rb, &mut wb, eof)?;
+        in_pos += rb.position();
+        out_pos += wb.position();
+        if let BufferResult::BufferUnderflow = result {
+            if eof && rb.is_empty() {
+                return Ok(out_pos);
+            }
+        }
+    }
+}
+
+fn cmp_min(a: usize, b: usize) -> usize {
+    if a < b { a } else { b }
+}
+
+macro_rules! check_ecb {
+    ($cipher:ident, $tv:expr, $padding:expr) => {{
+        let tv = $tv;
+        let mut buf = [0u8; MAX_MSG_LEN + 16];
+        for &(in_step, out_step) in STEPS.iter() {
+            let mut e = EcbEncryptor::new($cipher::new(tv.key), $padding);
+            let n = run(|r, w, eof| e.encrypt(r, w, eof), tv.input,
+                        &mut buf, in_step, out_step).unwrap();
+            assert_eq!(&buf[..n], tv.output);
+
+            let mut d = EcbDecryptor::new($cipher::new(tv.key), $padding);
+            let n = run(|r, w, eof| d.decrypt(r, w, eof), tv.output,
+                        &mut buf, in_step, out_step).unwrap();
+            assert_eq!(&buf[..n], tv.input);
+        }
+    }}
+}
+
+macro_rules! check_chained {
+    ($enc:ident, $dec:ident, $cipher:ident, $tv:expr, $padding:expr) => {{
+        let tv = $tv;
+        let iv = GenericArray::from_slice(tv.iv);
+        let mut buf = [0u8; MAX_MSG_LEN + 16];
+        for &(in_step, out_step) in STEPS.iter() {
+            let mut e = $enc::new($cipher::new(tv.key), $padding, &iv);
+            let n = run(|r, w, eof| e.encrypt(r, w, eof), tv.input,
+                        &mut buf, in_step, out_step).unwrap();
+            assert_eq!(&buf[..n], tv.output);
+
+            let mut d = $dec::new($cipher::new(tv.key), $padding, &iv);
+            let n = run(|r, w, eof| d.decrypt(r, w, eof), tv.output,
+                        &mut buf, in_step, out_step).unwrap();
+            assert_eq!(&buf[..n], tv.input);
+        }
+    }}
+}
+
+macro_rules! check_feedback {
+    ($mode:ident, $cipher:ident, $tv:expr) => {{
+        let tv = $tv;
+        let iv = GenericArray::from_slice(tv.iv);
+        let mut buf = [0u8; MAX_MSG_LEN];
+        for &(in_step, out_step) in STEPS.iter() {
+            let mut e = $mode::new($cipher::new(tv.key), &iv);
+            let n = run(|r, w, eof| e.encrypt(r, w, eof), tv.input,
+                        &mut buf, in_step, out_step).unwrap();
+            assert_eq!(&buf[..n], tv.output);
+
+            let mut d = $mode::new($cipher::new(tv.key), &iv);
+            let n = run(|r, w, eof| d.decrypt(r, w, eof), tv.output,
+                        &mut buf, in_step, out_step).unwrap();
+            assert_eq!(&buf[..n], tv.input);
+        }
+    }}
+}
+
+macro_rules! check_ctr {
+    ($cipher:ident, $tv:expr) => {{
+        let tv = $tv;
+        let ctr = GenericArray::from_slice(tv.iv);
+        let mut buf = [0u8; MAX_MSG_LEN];
+        let n = tv.input.len();
+        for &(step, _) in STEPS.iter() {
+            let mut c = CtrMode::new($cipher::new(tv.key), &ctr);
+            let mut c8 = CtrModeX8::new($cipher::new(tv.key), &ctr);
+            for (input, output) in tv.input.chunks(step)
+                                     .zip(buf.chunks_mut(step)) {
+                c.process(input, output);
+            }
+            assert_eq!(&buf[..n], tv.output);
+            for (input, output) in tv.input.chunks(step)
+                                     .zip(buf.chunks_mut(step)) {
+                c8.process(input, output);
+            }
+            assert_eq!(&buf[..n], tv.output);
+        }
+
+        // Through the Decryptor interface
+        let mut c = CtrMode::new($cipher::new(tv.key), &ctr);
+        let n = run(|r, w, eof| c.decrypt(r, w, eof), tv.output, &mut buf,
+                    7, 9).unwrap();
+        assert_eq!(&buf[..n], tv.input);
+    }}
+}
+
+macro_rules! check_ctr_flavor {
+    ($flavor:ident, $tv:expr) => {{
+        let tv = $tv;
+        let ctr = GenericArray::from_slice(tv.iv);
+        let mut buf = [0u8; MAX_MSG_LEN];
+        let n = tv.input.len();
+        for &(step, _) in STEPS.iter() {
+            let mut c = CtrMode::<_, $flavor>::with_flavor(Aes128::new(tv.key),
+                                                           &ctr);
+            let mut c8 = CtrModeX8::<_, $flavor>::with_flavor(
+                Aes128::new(tv.key), &ctr);
+            for (input, output) in tv.input.chunks(step)
+                                     .zip(buf.chunks_mut(step)) {
+                c.process(input, output);
+            }
+            assert_eq!(&buf[..n], tv.output);
+            for (input, output) in tv.input.chunks(step)
+                                     .zip(buf.chunks_mut(step)) {
+                c8.process(input, output);
+            }
+            assert_eq!(&buf[..n], tv.output);
+        }
+
+        // Every position can be reached from any other
+        let mut c = CtrMode::<_, $flavor>::with_flavor(Aes128::new(tv.key),
+                                                       &ctr);
+        let mut c8 = CtrModeX8::<_, $flavor>::with_flavor(Aes128::new(tv.key),
+                                                          &ctr);
+        for i in 0..n + 1 {
+            let offset = (i * 37) % (n + 1);
+            c.seek(offset as u64).unwrap();
+            c.process(&tv.input[offset..], &mut buf[offset..n]);
+            assert_eq!(c.current_pos(), n as u64);
+            assert_eq!(&buf[offset..n], &tv.output[offset..]);
+            c8.seek(offset as u64).unwrap();
+            c8.process(&tv.input[offset..], &mut buf[offset..n]);
+            assert_eq!(c8.current_pos(), n as u64);
+            assert_eq!(&buf[offset..n], &tv.output[offset..]);
+        }
+    }}
+}
+
+// NIST SP 800-38A, appendices F.1, F.2 and F.5
+const ECB_VECTORS: [TestVector; 3] = get_vectors!("ecb_aes128", "ecb_aes192",
+                                                  "ecb_aes256");
+const CBC_VECTORS: [TestVector; 3] = get_vectors!("cbc_aes128", "cbc_aes192",
+                                                  "cbc_aes256");
+const CTR_VECTORS: [TestVector; 3] = get_vectors!("ctr_aes128", "ctr_aes192",
+                                                  "ctr_aes256");
+
+// PKCS#7 padded AES-128-CBC of 37, 32 and 0 byte messages, and AES-128-CTR
+// with a counter which wraps around, generated with the `cryptography`
+// Python package
+const PKCS_VECTORS: [TestVector; 3] = get_vectors!("cbc_pkcs_1",
+                                                   "cbc_pkcs_2",
+                                                   "cbc_pkcs_3");
+const CTR_WRAP_VECTOR: [TestVector; 1] = get_vectors!("ctr_wrap");
+
+// The SP 800-38A plaintext with AES-128 and counters of 32 and 64 bits,
+// which carry between their bytes
+const CTR_FLAVOR_VECTORS: [TestVector; 4] = get_vectors!("ctr32be", "ctr32le",
+                                                         "ctr64be", "ctr64le");
+
+// NIST SP 800-38A, appendices F.3 and F.4: CFB-1 over 16 bits, CFB-8 over 18
+// bytes, CFB-128 and OFB
+const CFB1_VECTORS: [TestVector; 3] = get_vectors!("cfb1_aes128",
+                                                   "cfb1_aes192",
+                                                   "cfb1_aes256");
+const CFB8_VECTORS: [TestVector; 3] = get_vectors!("cfb8_aes128",
+                                                   "cfb8_aes192",
+                                                   "cfb8_aes256");
+const CFB_VECTORS: [TestVector; 3] = get_vectors!("cfb_aes128", "cfb_aes192",
+                                                  "cfb_aes256");
+const OFB_VECTORS: [TestVector; 3] = get_vectors!("ofb_aes128", "ofb_aes192",
+                                                  "ofb_aes256");
+
+// AES-128 with messages which end in a partial block, CFB-1 over 7 bytes
+// and PCBC, unpadded and with PKCS#7 padding. CFB-1 and PCBC were computed
+// from the block cipher in Python, the others with the `cryptography`
+// package.
+const PARTIAL_VECTORS: [TestVector; 3] = get_vectors!("cfb_partial",
+                                                      "ofb_partial",
+                                                      "cfb1_long");
+const PCBC_VECTORS: [TestVector; 2] = get_vectors!("pcbc_aes128",
+                                                   "pcbc_pkcs");
+
+// AES-128 CBC with the SP 800-38A key and IV, on messages of 21 and 32
+// bytes. The ISO 10126 padding bytes are all 0xa5.
+const X923_VECTORS: [TestVector; 2] = get_vectors!("cbc_x923_1",
+                                                   "cbc_x923_2");
+const ISO10126_VECTORS: [TestVector; 2] = get_vectors!("cbc_iso10126_1",
+                                                       "cbc_iso10126_2");
+const ISO7816_VECTORS: [TestVector; 2] = get_vectors!("cbc_iso7816_1",
+                                                      "cbc_iso7816_2");
+const ZERO_VECTORS: [TestVector; 2] = get_vectors!("cbc_zero_1",
+                                                   "cbc_zero_2");
+
+// RFC 3962, appendix B, for CS3. CS1 and CS2 reorder the same ciphertexts.
+const CS1_VECTORS: [TestVector; 4] = get_vectors!("cbc_cs1_16", "cbc_cs1_17",
+                                                  "cbc_cs1_32", "cbc_cs1_47");
+const CS2_VECTORS: [TestVector; 4] = get_vectors!("cbc_cs2_16", "cbc_cs2_17",
+                                                  "cbc_cs2_32", "cbc_cs2_47");
+const CS3_VECTORS: [TestVector; 6] = get_vectors!("cbc_cs3_17", "cbc_cs3_31",
+                                                  "cbc_cs3_32", "cbc_cs3_47",
+                                                  "cbc_cs3_48", "cbc_cs3_64");
+
+#[test]
+fn ecb_sp800_38a() {
+    check_ecb!(Aes128, &ECB_VECTORS[0], NoPadding);
+    check_ecb!(Aes192, &ECB_VECTORS[1], NoPadding);
+    check_ecb!(Aes256, &ECB_VECTORS[2], NoPadding);
+}
+
+#[test]
+fn cbc_sp800_38a() {
+    check_chained!(CbcEncryptor, CbcDecryptor, Aes128, &CBC_VECTORS[0],
+                   NoPadding);
+    check_chained!(CbcEncryptor, CbcDecryptor, Aes192, &CBC_VECTORS[1],
+                   NoPadding);
+    check_chained!(CbcEncryptor, CbcDecryptor, Aes256, &CBC_VECTORS[2],
+                   NoPadding);
+}
+
+#[test]
+fn ctr_sp800_38a() {
+    check_ctr!(Aes128, &CTR_VECTORS[0]);
+    check_ctr!(Aes192, &CTR_VECTORS[1]);
+    check_ctr!(Aes256, &CTR_VECTORS[2]);
+    check_ctr!(Aes128, &CTR_WRAP_VECTOR[0]);
+}
+
+#[test]
+fn ctr_flavors() {
+    check_ctr_flavor!(Ctr32BE, &CTR_FLAVOR_VECTORS[0]);
+    check_ctr_flavor!(Ctr32LE, &CTR_FLAVOR_VECTORS[1]);
+    check_ctr_flavor!(Ctr64BE, &CTR_FLAVOR_VECTORS[2]);
+    check_ctr_flavor!(Ctr64LE, &CTR_FLAVOR_VECTORS[3]);
+}
+
+#[test]
+fn ctr_seek() {
+    // The whole block counter wraps around
+    for tv in CTR_VECTORS[..1].iter().chain(CTR_WRAP_VECTOR.iter()) {
+        let ctr = GenericArray::from_slice(tv.iv);
+        let mut buf = [0u8; MAX_MSG_LEN];
+        let n = tv.input.len();
+        let mut c = CtrMode::new(Aes128::new(tv.key), &ctr);
+        let mut c8 = CtrModeX8::new(Aes128::new(tv.key), &ctr);
+        for offset in (0..n + 1).rev() {
+            c.seek(offset as u64).unwrap();
+            c.process(&tv.input[offset..], &mut buf[offset..n]);
+            assert_eq!(&buf[offset..n], &tv.output[offset..]);
+            c8.seek(offset as u64).unwrap();
+            c8.process(&tv.input[offset..], &mut buf[offset..n]);
+            assert_eq!(&buf[offset..n], &tv.output[offset..]);
+        }
+    }
+}
+
+/// A 32-bit counter with 2 blocks left
+fn ctr32_near_overflow() -> CtrMode<Aes128, Ctr32BE> {
+    let mut ctr = GenericArray::new();
+    for b in ctr[12..].iter_mut() {
+        *b = 0xff;
+    }
+    ctr[15] = 0xfe;
+    CtrMode::with_flavor(Aes128::new(&[0; 16]), &ctr)
+}
+
+#[test]
+fn ctr_overflow() {
+    let input = [0u8; 33];
+    let mut output = [0u8; 33];
+    let mut c = ctr32_near_overflow();
+    match run(|r, w, eof| c.encrypt(r, w, eof), &input, &mut output, 33, 33) {
+        Err(SymmetricCipherError::InvalidLength) => (),
+        _ => panic!("expected InvalidLength"),
+    }
+    assert_eq!(c.current_pos(), 0);
+    match c.seek(33) {
+        Err(SymmetricCipherError::InvalidLength) => (),
+        _ => panic!("expected InvalidLength"),
+    }
+
+    // The end of the counter range can be reached, but no keystream follows
+    c.seek(32).unwrap();
+    assert_eq!(c.current_pos(), 32);
+    match run(|r, w, eof| c.encrypt(r, w, eof), &input[..1], &mut output[..1],
+              1, 1) {
+        Err(SymmetricCipherError::InvalidLength) => (),
+        _ => panic!("expected InvalidLength"),
+    }
+
+    // The last bytes before the overflow can be used, and seeking to the
+    // current position afterwards keeps the stream where it is
+    let mut last = [0u8; 2];
+    c.seek(30).unwrap();
+    c.process(&input[..2], &mut last);
+    c.seek(31).unwrap();
+    c.process(&input[..1], &mut output[..1]);
+    assert_eq!(output[0], last[1]);
+    let pos = c.current_pos();
+    assert_eq!(pos, 32);
+    c.seek(pos).unwrap();
+    assert_eq!(c.current_pos(), 32);
+    c.seek(31).unwrap();
+    c.process(&input[..1], &mut output[..1]);
+    assert_eq!(output[0], last[1]);
+    c.reset(&GenericArray::new());
+    c.seek(32).unwrap();
+
+    // Likewise with 8 blocks at a time
+    let mut ctr = GenericArray::new();
+    ctr[12..].copy_from_slice(&[0xff, 0xff, 0xff, 0xfe]);
+    let mut c8 = CtrModeX8::<_, Ctr32BE>::with_flavor(Aes128::new(&[0; 16]),
+                                                     &ctr);
+    match c8.seek(33) {
+        Err(SymmetricCipherError::InvalidLength) => (),
+        _ => panic!("expected InvalidLength"),
+    }
+    c8.seek(32).unwrap();
+    match run(|r, w, eof| c8.encrypt(r, w, eof), &input[..1],
+              &mut output[..1], 1, 1) {
+        Err(SymmetricCipherError::InvalidLength) => (),
+        _ => panic!("expected InvalidLength"),
+    }
+    c8.seek(30).unwrap();
+    c8.process(&input[..2], &mut output[..2]);
+    assert_eq!(&output[..2], &last[..]);
+    let pos = c8.current_pos();
+    c8.seek(pos).unwrap();
+    assert_eq!(c8.current_pos(), 32);
+}
+
+#[test]
+#[should_panic(expected = "CTR counter overflow")]
+fn ctr_overflow_panics() {
+    let input = [0u8; 33];
+    let mut output = [0u8; 33];
+    ctr32_near_overflow().process(&input, &mut output);
+}
+
+#[test]
+fn cbc_pkcs_padding() {
+    for tv in PKCS_VECTORS.iter() {
+        check_chained!(CbcEncryptor, CbcDecryptor, Aes128, tv, PkcsPadding);
+    }
+}
+
+fn fill_a5(buf: &mut [u8]) {
+    for b in buf.iter_mut() {
+        *b = 0xa5;
+    }
+}
+
+#[test]
+fn cbc_other_paddings() {
+    for tv in X923_VECTORS.iter() {
+        check_chained!(CbcEncryptor, CbcDecryptor, Aes128, tv,
+                       AnsiX923Padding);
+    }
+    for tv in ISO10126_VECTORS.iter() {
+        check_chained!(CbcEncryptor, CbcDecryptor, Aes128, tv,
+                       Iso10126Padding::new(fill_a5));
+    }
+    for tv in ISO7816_VECTORS.iter() {
+        check_chained!(CbcEncryptor, CbcDecryptor, Aes128, tv,
+                       Iso7816Padding);
+    }
+    for tv in ZERO_VECTORS.iter() {
+        check_chained!(CbcEncryptor, CbcDecryptor, Aes128, tv, ZeroPadding);
+    }
+}
+
+macro_rules! check_cts {
+    ($variant:expr, $tv:expr) => {{
+        let tv = $tv;
+        let iv = GenericArray::from_slice(tv.iv);
+        let cts = CbcCts::new(Aes128::new(tv.key), $variant);
+        let mut buf = [0u8; MAX_MSG_LEN];
+        let data = &mut buf[..tv.input.len()];
+        data.copy_from_slice(tv.input);
+        cts.encrypt(&iv, data).unwrap();
+        assert_eq!(&data[..], tv.output);
+        cts.decrypt(&iv, data).unwrap();
+        assert_eq!(&data[..], tv.input);
+    }}
+}
+
+#[test]
+fn cbc_ciphertext_stealing() {
+    for tv in CS1_VECTORS.iter() {
+        check_cts!(CtsVariant::Cs1, tv);
+    }
+    for tv in CS2_VECTORS.iter() {
+        check_cts!(CtsVariant::Cs2, tv);
+    }
+    for tv in CS3_VECTORS.iter() {
+        check_cts!(CtsVariant::Cs3, tv);
+    }
+
+    // Messages shorter than a block can't be encrypted
+    let tv = &CS3_VECTORS[0];
+    let cts = CbcCts::new(Aes128::new(tv.key), CtsVariant::Cs3);
+    let mut buf = [0u8; 15];
+    match cts.encrypt(&GenericArray::from_slice(tv.iv), &mut buf) {
+        Err(SymmetricCipherError::InvalidLength) => (),
+        _ => panic!("expected InvalidLength"),
+    }
+}
+
+#[test]
+fn cfb_sp800_38a() {
+    check_feedback!(Cfb1, Aes128, &CFB1_VECTORS[0]);
+    check_feedback!(Cfb1, Aes192, &CFB1_VECTORS[1]);
+    check_feedback!(Cfb1, Aes256, &CFB1_VECTORS[2]);
+    check_feedback!(Cfb8, Aes128, &CFB8_VECTORS[0]);
+    check_feedback!(Cfb8, Aes192, &CFB8_VECTORS[1]);
+    check_feedback!(Cfb8, Aes256, &CFB8_VECTORS[2]);
+    check_feedback!(Cfb, Aes128, &CFB_VECTORS[0]);
+    check_feedback!(Cfb, Aes192, &CFB_VECTORS[1]);
+    check_feedback!(Cfb, Aes256, &CFB_VECTORS[2]);
+}
+
+#[test]
+fn ofb_sp800_38a() {
+    check_feedback!(Ofb, Aes128, &OFB_VECTORS[0]);
+    check_feedback!(Ofb, Aes192, &OFB_VECTORS[1]);
+    check_feedback!(Ofb, Aes256, &OFB_VECTORS[2]);
+}
+
+#[test]
+fn feedback_partial_blocks() {
+    check_feedback!(Cfb, Aes128, &PARTIAL_VECTORS[0]);
+    check_feedback!(Ofb, Aes128, &PARTIAL_VECTORS[1]);
+    check_feedback!(Cfb1, Aes128, &PARTIAL_VECTORS[2]);
+
+    // OFB is also a synchronous stream cipher
+    let tv = &PARTIAL_VECTORS[1];
+    let mut buf = [0u8; MAX_MSG_LEN];
+    let n = tv.input.len();
+    let mut c = Ofb::new(Aes128::new(tv.key), &GenericArray::from_slice(tv.iv));
+    for (input, output) in tv.input.chunks(7).zip(buf[..n].chunks_mut(7)) {
+        c.process(input, output);
+    }
+    assert_eq!(&buf[..n], tv.output);
+}
+
+#[test]
+fn pcbc() {
+    check_chained!(PcbcEncryptor, PcbcDecryptor, Aes128, &PCBC_VECTORS[0],
+                   NoPadding);
+    check_chained!(PcbcEncryptor, PcbcDecryptor, Aes128, &PCBC_VECTORS[1],
+                   PkcsPadding);
+
+    // An error in a ciphertext block corrupts all the following plaintext
+    // blocks, unlike CBC
+    let tv = &PCBC_VECTORS[0];
+    let mut ciphertext = [0u8; MAX_MSG_LEN];
+    let mut buf = [0u8; MAX_MSG_LEN];
+    ciphertext.copy_from_slice(tv.output);
+    ciphertext[20] ^= 1;
+    let iv = GenericArray::from_slice(tv.iv);
+    let mut d = PcbcDecryptor::new(Aes128::new(tv.key), NoPadding, &iv);
+    run(|r, w, eof| d.decrypt(r, w, eof), &ciphertext, &mut buf, 64, 64)
+        .unwrap();
+    assert_eq!(&buf[..16], &tv.input[..16]);
+    for (a, b) in buf[16..].chunks(16).zip(tv.input[16..].chunks(16)) {
+        assert!(a != b);
+    }
+}
+
+macro_rules! check_xts {
+    ($cipher:ident, $tv:expr) => {{
+        let tv = $tv;
+        let mut sector_no = 0;
+        for (i, &b) in tv.sector.iter().enumerate() {
+            sector_no |= (b as u64) << (8 * i);
+        }
+        let xts = Xts::new($cipher::new(tv.key1), $cipher::new(tv.key2));
+        let mut buf = [0u8; 512];
+        let data = &mut buf[..tv.input.len()];
+        data.copy_from_slice(tv.input);
+        xts.encrypt_sector(sector_no, data).unwrap();
+        assert_eq!(&data[..], tv.output);
+        xts.decrypt_sector(sector_no, data).unwrap();
+        assert_eq!(&data[..], tv.input);
+    }}
+}
+
+// IEEE 1619-2007, annex B. Vectors 15 to 18 end with a partial block.
+const XTS_AES128_VECTORS: [XtsTestVector; 8] = get_xts_vectors!(
+    "xts_1", "xts_2", "xts_3", "xts_4", "xts_15", "xts_16", "xts_17", "xts_18");
+const XTS_AES256_VECTORS: [XtsTestVector; 1] = get_xts_vectors!("xts_10");
+
+#[test]
+fn xts_ieee1619() {
+    for tv in XTS_AES128_VECTORS.iter() {
+        check_xts!(Aes128, tv);
+    }
+    for tv in XTS_AES256_VECTORS.iter() {
+        check_xts!(Aes256, tv);
+    }
+}
+
+#[test]
+fn xts_tweaks() {
+    let tv = &XTS_AES128_VECTORS[4];
+    let xts = Xts::new(Aes128::new(tv.key1), Aes128::new(tv.key2));
+    let mut tweak = GenericArray::new();
+    tweak[..8].copy_from_slice(tv.sector);
+    let mut buf = [0u8; 17];
+    buf.copy_from_slice(tv.input);
+    xts.encrypt_with_tweak(&tweak, &mut buf).unwrap();
+    assert_eq!(&buf[..], tv.output);
+    xts.decrypt_with_tweak(&tweak, &mut buf).unwrap();
+    assert_eq!(&buf[..], tv.input);
+
+    // Sectors shorter than a block can't be encrypted
+    for len in [0, 1, 15].iter() {
+        match xts.encrypt_sector(0, &mut buf[..*len]) {
+            Err(SymmetricCipherError::InvalidLength) => (),
+            _ => panic!("expected InvalidLength")
+        }
+    }
+}
+
+#[test]
+fn cbc_reset() {
+    let tv = &CBC_VECTORS[0];
+    let iv = GenericArray::from_slice(tv.iv);
+    let mut buf = [0u8; MAX_MSG_LEN];
+    let mut e = CbcEncryptor::new(Aes128::new(tv.key), NoPadding, &iv);
+    for _ in 0..2 {
+        let n = run(|r, w, eof| e.encrypt(r, w, eof), tv.input, &mut buf, 5,
+                    7).unwrap();
+        assert_eq!(&buf[..n], tv.output);
+        e.reset(&iv);
+    }
+
+    // Resetting in the middle of a message drops the buffered input
+    let mut d = CbcDecryptor::new(Aes128::new(tv.key), NoPadding, &iv);
+    run(|r, w, eof| d.decrypt(r, w, eof), &tv.output[..20], &mut buf, 20,
+        64).unwrap_err();
+    d.reset(&iv);
+    let n = run(|r, w, eof| d.decrypt(r, w, eof), tv.output, &mut buf, 64,
+                64).unwrap();
+    assert_eq!(&buf[..n], tv.input);
+}
+
+#[test]
+fn block_mode_errors() {
+    // Without padding, the input must be a multiple of the block size
+    let tv = &ECB_VECTORS[0];
+    let mut buf = [0u8; MAX_MSG_LEN];
+    let mut e = EcbEncryptor::new(Aes128::new(tv.key), NoPadding);
+    let res = run(|r, w, eof| e.encrypt(r, w, eof), &tv.input[..40],
+                  &mut buf, 64, 64);
+    match res {
+        Err(SymmetricCipherError::InvalidLength) => (),
+        _ => panic!("expected InvalidLength"),
+    }
+
+    // The last byte of the SP 800-38A plaintext is 0x10, which is not a
+    // valid PKCS#7 padding as the rest of the block differs
+    let tv = &CBC_VECTORS[0];
+    let iv = GenericArray::from_slice(tv.iv);
+    let mut d = CbcDecryptor::new(Aes128::new(tv.key), PkcsPadding, &iv);
+    let res = run(|r, w, eof| d.decrypt(r, w, eof), tv.output, &mut buf, 64,
+                  64);
+    match res {
+        Err(SymmetricCipherError::InvalidPadding) => (),
+        _ => panic!("expected InvalidPadding"),
+    }
+
+    // Neither is it a valid ANSI X9.23 or ISO/IEC 7816-4 padding
+    let mut d = CbcDecryptor::new(Aes128::new(tv.key), AnsiX923Padding, &iv);
+    let res = run(|r, w, eof| d.decrypt(r, w, eof), tv.output, &mut buf, 64,
+                  64);
+    match res {
+        Err(SymmetricCipherError::InvalidPadding) => (),
+        _ => panic!("expected InvalidPadding"),
+    }
+    let mut d = CbcDecryptor::new(Aes128::new(tv.key), Iso7816Padding, &iv);
+    let res = run(|r, w, eof| d.decrypt(r, w, eof), tv.output, &mut buf, 64,
+                  64);
+    match res {
+        Err(SymmetricCipherError::InvalidPadding) => (),
+        _ => panic!("expected InvalidPadding"),
+    }
+}
@@ -0,0 +1,123 @@
+//! Key commitment for any `Aead`, with the "padding fix" of Albertini,
+//! Duong, Gueron, Kölbl, Luykx and Schmieg [1].
+//!
+//! An AEAD only guarantees that a ciphertext cannot be forged without the
+//! key. With GCM, ChaCha20-Poly1305 and most other constructions, someone
+//! who knows two keys can craft a single ciphertext and tag that decrypt
+//! successfully, to different plaintexts, under both of them. This breaks
+//! protocols which try keys one after the other, or where the sender can
+//! choose the key afterwards (e.g. the "invisible salamanders" attack on
+//! message franking).
+//!
+//! `KeyCommitting` prepends `COMMITMENT_LEN` zero bytes to every plaintext
+//! and rejects ciphertexts whose first bytes do not decrypt to zeros. For
+//! a ciphertext to be accepted under two keys, its first 32 bytes must then
+//! be the encryption of zeros under both of them. With the stream cipher
+//! and block cipher based constructions of this project, those bytes are a
+//! pseudorandom function of the key, so finding such a pair of keys is a
+//! collision search on 256 bits. This costs 32 bytes per message, and
+//! unlike CTX it does not need a hash function or access to the internals
+//! of the AEAD.
+//!
+//! [1] - "How to Abuse and Fix Authenticated Encryption Without Key
+//!       Commitment" <https://eprint.iacr.org/2020/1456>
+
+use generic_array::typenum::Unsigned;
+use super::{Aead, NewAead, AeadError, Buffer, Tag};
+
+/// Number of zero bytes added in front of every plaintext
+pub const COMMITMENT_LEN: usize = 32;
+
+/// Wraps an `Aead` so that a ciphertext authenticates under a single key.
+///
+/// The detached methods, and `encrypt` and `decrypt`, work on a buffer which
+/// starts with `COMMITMENT_LEN` bytes reserved for the commitment, followed
+/// by the message. The attached methods add and remove these bytes,
+/// together with the tag.
+#[derive(Clone)]
+pub struct KeyCommitting<A> {
+    aead: A,
+}
+
+impl<A: NewAead> NewAead for KeyCommitting<A> {
+    fn new(key: &[u8]) -> Result<KeyCommitting<A>, AeadError> {
+        Ok(KeyCommitting { aead: A::new(key)? })
+    }
+}
+
+impl<A: Aead> KeyCommitting<A> {
+    pub fn from_aead(aead: A) -> KeyCommitting<A> {
+        KeyCommitting { aead: aead }
+    }
+}
+
+impl<A: Aead> Aead for KeyCommitting<A> {
+    type TagSize = A::TagSize;
+
+    /// Zeroes the first `COMMITMENT_LEN` bytes of `buf` and encrypts it in
+    /// place, returning the tag
+    fn encrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8])
+                                 -> Result<Tag<A::TagSize>, AeadError> {
+        if buf.len() < COMMITMENT_LEN {
+            return Err(AeadError::InvalidLength);
+        }
+        for b in buf[..COMMITMENT_LEN].iter_mut() {
+            *b = 0;
+        }
+        self.aead.encrypt_in_place_detached(nonce, aad, buf)
+    }
+
+    /// Checks `tag`, decrypts `buf` in place and checks that it starts with
+    /// `COMMITMENT_LEN` zero bytes, the message follows them. If either
+    /// check fails `buf` is zeroed.
+    fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8], tag: &[u8])
+                                 -> Result<(), AeadError> {
+        if buf.len() < COMMITMENT_LEN {
+            return Err(AeadError::InvalidLength);
+        }
+        self.aead.decrypt_in_place_detached(nonce, aad, buf, tag)?;
+        let diff = buf[..COMMITMENT_LEN].iter().fold(0, |acc, &b| acc | b);
+        if diff == 0 {
+            Ok(())
+        } else {
+            for b in buf.iter_mut() {
+                *b = 0;
+            }
+            Err(AeadError::AuthenticationFailed)
+        }
+    }
+
+    /// Encrypts the message in `buf`, which is replaced by the commitment,
+    /// the ciphertext and the tag
+    fn encrypt_in_place<B: Buffer + ?Sized>(&self, nonce: &[u8], aad: &[u8],
+                                            buf: &mut B)
+                                            -> Result<(), AeadError> {
+        buf.extend_from_slice(&[0u8; COMMITMENT_LEN])?;
+        buf.as_mut().rotate_right(COMMITMENT_LEN);
+        let tag = self.encrypt_in_place_detached(nonce, aad, buf.as_mut())?;
+        buf.extend_from_slice(&tag)
+    }
+
+    /// Decrypts a buffer produced by `encrypt_in_place` and leaves only the
+    /// message in `buf`. The commitment and the tag are removed whether
+    /// authentication succeeds or not.
+    fn decrypt_in_place<B: Buffer + ?Sized>(&self, nonce: &[u8], aad: &[u8],
+                                            buf: &mut B)
+                                            -> Result<(), AeadError> {
+        let tag_size = A::TagSize::to_usize();
+        if buf.len() < COMMITMENT_LEN + tag_size {
+            return Err(AeadError::InvalidLength);
+        }
+        let len = buf.len() - tag_size;
+        let result = {
+            let (msg, tag) = buf.as_mut().split_at_mut(len);
+            let result = self.decrypt_in_place_detached(nonce, aad, msg, tag);
+            msg.rotate_left(COMMITMENT_LEN);
+            result
+        };
+        buf.truncate(len - COMMITMENT_LEN);
+        result
+    }
+}
@@ -1,9 +1,201 @@
 #![no_std]
+extern crate generic_array;
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
-pub trait AeadEncryptor {
-    fn encrypt(&mut self, input: &[u8], output: &mut [u8], tag: &mut [u8]);
+use generic_array::{GenericArray, ArrayLength};
+use generic_array::typenum::Unsigned;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+pub mod commit;
+pub mod stream;
+
+/// Full length authentication tag of an AEAD
+pub type Tag<N> = GenericArray<u8, N>;
+
+/// Errors reported by AEAD constructions instead of panicking on malformed
+/// input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AeadError {
+    /// The key length is not supported by the construction
+    InvalidKeyLength,
+    /// The key is rejected by the construction, e.g. a public key of small
+    /// order in a Diffie-Hellman based box
+    InvalidKey,
+    /// The nonce length is not supported by the construction
+    InvalidNonceLength,
+    /// The input and output buffers differ in length, or the message, AAD
+    /// or tag length is not supported
+    InvalidLength,
+    /// The authentication tag does not match, the output buffer has been
+    /// zeroed
+    AuthenticationFailed,
+}
+
+/// A growable byte buffer, used by the attached in-place APIs which append
+/// the tag to the ciphertext.
+///
+/// It is implemented for `Vec<u8>` with the `alloc` feature and for the
+/// fixed capacity `ArrayBuffer`. Fixed capacity buffers fail with
+/// `InvalidLength` when they are full.
+pub trait Buffer: AsRef<[u8]> + AsMut<[u8]> {
+    fn len(&self) -> usize {
+        self.as_ref().len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.as_ref().is_empty()
+    }
+
+    fn extend_from_slice(&mut self, other: &[u8]) -> Result<(), AeadError>;
+
+    /// Shortens the buffer to `len` bytes, does nothing if it is already
+    /// shorter
+    fn truncate(&mut self, len: usize);
+}
+
+#[cfg(feature = "alloc")]
+impl Buffer for Vec<u8> {
+    fn extend_from_slice(&mut self, other: &[u8]) -> Result<(), AeadError> {
+        Vec::extend_from_slice(self, other);
+        Ok(())
+    }
+
+    fn truncate(&mut self, len: usize) {
+        Vec::truncate(self, len);
+    }
+}
+
+/// A `Buffer` with a fixed capacity of `N` bytes, for use of the attached
+/// in-place APIs without `alloc`
+pub struct ArrayBuffer<N: ArrayLength<u8>> {
+    data: GenericArray<u8, N>,
+    len: usize,
+}
+
+impl<N: ArrayLength<u8>> ArrayBuffer<N> {
+    pub fn new() -> ArrayBuffer<N> {
+        ArrayBuffer { data: GenericArray::new(), len: 0 }
+    }
+
+    /// Fails with `InvalidLength` if `data` is longer than the capacity
+    pub fn from_slice(data: &[u8]) -> Result<ArrayBuffer<N>, AeadError> {
+        let mut buf = ArrayBuffer::new();
+        buf.extend_from_slice(data)?;
+        Ok(buf)
+    }
+
+    pub fn capacity(&self) -> usize {
+        N::to_usize()
+    }
 }
 
-pub trait AeadDecryptor {
-    fn decrypt(&mut self, input: &[u8], output: &mut [u8], tag: &[u8]) -> bool;
+impl<N: ArrayLength<u8>> Default for ArrayBuffer<N> {
+    fn default() -> ArrayBuffer<N> {
+        ArrayBuffer::new()
+    }
+}
+
+impl<N: ArrayLength<u8>> AsRef<[u8]> for ArrayBuffer<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+impl<N: ArrayLength<u8>> AsMut<[u8]> for ArrayBuffer<N> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.data[..self.len]
+    }
+}
+
+impl<N: ArrayLength<u8>> Buffer for ArrayBuffer<N> {
+    fn extend_from_slice(&mut self, other: &[u8]) -> Result<(), AeadError> {
+        if other.len() > self.capacity() - self.len {
+            return Err(AeadError::InvalidLength);
+        }
+        self.data[self.len..self.len + other.len()].copy_from_slice(other);
+        self.len += other.len();
+        Ok(())
+    }
+
+    fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            self.len = len;
+        }
+    }
+}
+
+/// Constructs an AEAD from its key, the expensive per-key setup is done
+/// once and reused for every message
+pub trait NewAead: Sized {
+    fn new(key: &[u8]) -> Result<Self, AeadError>;
+}
+
+/// A keyed AEAD. The nonce and the associated data are supplied with every
+/// message and the key is never modified, so a single instance can be shared
+/// between threads. A nonce must never be used twice with the same key.
+pub trait Aead {
+    type TagSize: ArrayLength<u8>;
+
+    /// Encrypts `buf` in place and returns the tag
+    fn encrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8])
+                                 -> Result<Tag<Self::TagSize>, AeadError>;
+
+    /// Checks `tag` and decrypts `buf` in place. If authentication fails
+    /// `buf` is zeroed.
+    fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8],
+                                 buf: &mut [u8], tag: &[u8])
+                                 -> Result<(), AeadError>;
+
+    /// Encrypts `input` into `output`, which must have the same length, and
+    /// writes the tag into `tag`, which must be `TagSize` bytes long
+    fn encrypt(&self, nonce: &[u8], aad: &[u8], input: &[u8],
+               output: &mut [u8], tag: &mut [u8]) -> Result<(), AeadError> {
+        let tag_size = Self::TagSize::to_usize();
+        if input.len() != output.len() || tag.len() != tag_size {
+            return Err(AeadError::InvalidLength);
+        }
+        output.copy_from_slice(input);
+        let full_tag = self.encrypt_in_place_detached(nonce, aad, output)?;
+        tag.copy_from_slice(&full_tag);
+        Ok(())
+    }
+
+    /// Decrypts `input` into `output`, which must have the same length
+    fn decrypt(&self, nonce: &[u8], aad: &[u8], input: &[u8],
+               output: &mut [u8], tag: &[u8]) -> Result<(), AeadError> {
+        if input.len() != output.len() {
+            return Err(AeadError::InvalidLength);
+        }
+        output.copy_from_slice(input);
+        self.decrypt_in_place_detached(nonce, aad, output, tag)
+    }
+
+    /// Encrypts the contents of `buf` in place and appends the tag
+    fn encrypt_in_place<B: Buffer + ?Sized>(&self, nonce: &[u8], aad: &[u8],
+                                            buf: &mut B)
+                                            -> Result<(), AeadError> {
+        let tag = self.encrypt_in_place_detached(nonce, aad, buf.as_mut())?;
+        buf.extend_from_slice(&tag)
+    }
+
+    /// Decrypts a buffer produced by `encrypt_in_place`, the tag is removed
+    /// from the end of `buf` whether authentication succeeds or not
+    fn decrypt_in_place<B: Buffer + ?Sized>(&self, nonce: &[u8], aad: &[u8],
+                                            buf: &mut B)
+                                            -> Result<(), AeadError> {
+        let tag_size = Self::TagSize::to_usize();
+        if buf.len() < tag_size {
+            return Err(AeadError::InvalidLength);
+        }
+        let msg_len = buf.len() - tag_size;
+        let result = {
+            let (msg, tag) = buf.as_mut().split_at_mut(msg_len);
+            self.decrypt_in_place_detached(nonce, aad, msg, tag)
+        };
+        buf.truncate(msg_len);
+        result
+    }
 }
@@ -0,0 +1,210 @@
+//! The STREAM online authenticated encryption construction of Hoang,
+//! Reyhanitabar, Rogaway and Vizár [1], layered over any `Aead`.
+//!
+//! A long message is split into segments which are encrypted one at a time.
+//! The nonce of segment `i` is the caller-supplied prefix followed by `i` as
+//! a 32-bit big-endian integer and a flag byte which is 1 for the last
+//! segment and 0 otherwise:
+//!
+//! ```text
+//! nonce = prefix || be32(i) || last
+//! ```
+//!
+//! The prefix is therefore 5 bytes shorter than the nonce of the underlying
+//! AEAD, e.g. 7 bytes for ChaCha20-Poly1305 (IETF) and AES-GCM. Since every
+//! segment is bound to its position and the last one is marked, a decryptor
+//! rejects reordered, dropped, duplicated or truncated segments. A prefix
+//! must never be used twice with the same key.
+//!
+//! Segments are processed in order, both sides have to agree on the segment
+//! boundaries. A stream holds at most 2^32 segments.
+//!
+//! [1] - "Online Authenticated-Encryption and its Nonce-Reuse
+//!       Misuse-Resistance" <https://eprint.iacr.org/2015/189>
+
+use generic_array::typenum::Unsigned;
+use super::{Aead, AeadError, Buffer, Tag};
+
+/// Longest nonce supported, the 24 bytes of XChaCha20-Poly1305
+const MAX_NONCE_LEN: usize = 24;
+/// Length of the counter and last-segment flag appended to the prefix
+const SUFFIX_LEN: usize = 5;
+
+/// Nonce sequence shared by the encryptor and the decryptor
+struct Nonces {
+    nonce: [u8; MAX_NONCE_LEN],
+    len: usize,
+    /// Index of the next segment, `None` once the final segment has been
+    /// processed or all 2^32 have been used
+    counter: Option<u32>,
+}
+
+impl Nonces {
+    fn new(prefix: &[u8]) -> Result<Nonces, AeadError> {
+        if prefix.len() + SUFFIX_LEN > MAX_NONCE_LEN {
+            return Err(AeadError::InvalidNonceLength);
+        }
+        let mut nonce = [0u8; MAX_NONCE_LEN];
+        nonce[..prefix.len()].copy_from_slice(prefix);
+        Ok(Nonces {
+            nonce: nonce,
+            len: prefix.len() + SUFFIX_LEN,
+            counter: Some(0),
+        })
+    }
+
+    /// Returns the nonce of the current segment, a finished stream fails
+    /// with `InvalidLength`
+    fn current(&mut self, last: bool) -> Result<&[u8], AeadError> {
+        let counter = self.counter.ok_or(AeadError::InvalidLength)?;
+        let suffix = self.len - SUFFIX_LEN;
+        self.nonce[suffix] = (counter >> 24) as u8;
+        self.nonce[suffix + 1] = (counter >> 16) as u8;
+        self.nonce[suffix + 2] = (counter >> 8) as u8;
+        self.nonce[suffix + 3] = counter as u8;
+        self.nonce[suffix + 4] = last as u8;
+        Ok(&self.nonce[..self.len])
+    }
+
+    fn advance(&mut self, last: bool) {
+        self.counter = match self.counter {
+            Some(c) if !last => c.checked_add(1),
+            _ => None,
+        };
+    }
+}
+
+/// Encrypts a stream segment by segment. The final segment is encrypted by
+/// one of the `encrypt_last` methods, which consume the encryptor.
+pub struct StreamEncryptor<A> {
+    aead: A,
+    nonces: Nonces,
+}
+
+impl<A: Aead> StreamEncryptor<A> {
+    /// Starts a stream with the given nonce prefix, which must be 5 bytes
+    /// shorter than the nonce of `aead`
+    pub fn new(aead: A, prefix: &[u8])
+               -> Result<StreamEncryptor<A>, AeadError> {
+        Ok(StreamEncryptor { aead: aead, nonces: Nonces::new(prefix)? })
+    }
+
+    fn encrypt_segment(&mut self, last: bool, aad: &[u8], buf: &mut [u8])
+                       -> Result<Tag<A::TagSize>, AeadError> {
+        let tag = {
+            let nonce = self.nonces.current(last)?;
+            self.aead.encrypt_in_place_detached(nonce, aad, buf)?
+        };
+        self.nonces.advance(last);
+        Ok(tag)
+    }
+
+    /// Encrypts the next segment in place and returns its tag
+    pub fn encrypt_next_in_place_detached(&mut self, aad: &[u8],
+                                          buf: &mut [u8])
+        -> Result<Tag<A::TagSize>, AeadError> {
+        self.encrypt_segment(false, aad, buf)
+    }
+
+    /// Encrypts the next segment in place and appends its tag
+    pub fn encrypt_next_in_place<B: Buffer + ?Sized>(&mut self, aad: &[u8],
+                                                     buf: &mut B)
+                                                     -> Result<(), AeadError> {
+        let tag = self.encrypt_segment(false, aad, buf.as_mut())?;
+        buf.extend_from_slice(&tag)
+    }
+
+    /// Encrypts the final segment in place and returns its tag
+    pub fn encrypt_last_in_place_detached(mut self, aad: &[u8],
+                                          buf: &mut [u8])
+        -> Result<Tag<A::TagSize>, AeadError> {
+        self.encrypt_segment(true, aad, buf)
+    }
+
+    /// Encrypts the final segment in place and appends its tag
+    pub fn encrypt_last_in_place<B: Buffer + ?Sized>(mut self, aad: &[u8],
+                                                     buf: &mut B)
+                                                     -> Result<(), AeadError> {
+        let tag = self.encrypt_segment(true, aad, buf.as_mut())?;
+        buf.extend_from_slice(&tag)
+    }
+}
+
+/// Decrypts a stream produced by `StreamEncryptor`. The position only
+/// advances when a segment authenticates, so a failure leaves the decryptor
+/// expecting the same segment again, and nothing is accepted after the final
+/// segment.
+///
+/// A stream is complete only once `decrypt_last` has succeeded. Segments
+/// released before that must be treated as possibly truncated.
+pub struct StreamDecryptor<A> {
+    aead: A,
+    nonces: Nonces,
+}
+
+impl<A: Aead> StreamDecryptor<A> {
+    /// Starts a stream with the nonce prefix given to the encryptor
+    pub fn new(aead: A, prefix: &[u8])
+               -> Result<StreamDecryptor<A>, AeadError> {
+        Ok(StreamDecryptor { aead: aead, nonces: Nonces::new(prefix)? })
+    }
+
+    fn decrypt_segment(&mut self, last: bool, aad: &[u8], buf: &mut [u8],
+                       tag: &[u8]) -> Result<(), AeadError> {
+        {
+            let nonce = self.nonces.current(last)?;
+            self.aead.decrypt_in_place_detached(nonce, aad, buf, tag)?;
+        }
+        self.nonces.advance(last);
+        Ok(())
+    }
+
+    /// Detaches the tag from the end of `buf` and decrypts the rest, the
+    /// tag is removed whether authentication succeeds or not
+    fn decrypt_segment_attached<B: Buffer + ?Sized>(&mut self, last: bool,
+                                                    aad: &[u8], buf: &mut B)
+                                                    -> Result<(), AeadError> {
+        let tag_size = A::TagSize::to_usize();
+        if buf.len() < tag_size {
+            return Err(AeadError::InvalidLength);
+        }
+        let msg_len = buf.len() - tag_size;
+        let result = {
+            let (msg, tag) = buf.as_mut().split_at_mut(msg_len);
+            self.decrypt_segment(last, aad, msg, tag)
+        };
+        buf.truncate(msg_len);
+        result
+    }
+
+    /// Checks `tag` and decrypts the next segment in place, a final segment
+    /// is rejected
+    pub fn decrypt_next_in_place_detached(&mut self, aad: &[u8],
+                                          buf: &mut [u8], tag: &[u8])
+                                          -> Result<(), AeadError> {
+        self.decrypt_segment(false, aad, buf, tag)
+    }
+
+    /// Decrypts a segment produced by `encrypt_next_in_place`
+    pub fn decrypt_next_in_place<B: Buffer + ?Sized>(&mut self, aad: &[u8],
+                                                     buf: &mut B)
+                                                     -> Result<(), AeadError> {
+        self.decrypt_segment_attached(false, aad, buf)
+    }
+
+    /// Checks `tag` and decrypts the final segment in place. Fails if the
+    /// segment was not marked as the last one, which happens when the
+    /// stream has been truncated.
+    pub fn decrypt_last_in_place_detached(&mut self, aad: &[u8],
+                                          buf: &mut [u8], tag: &[u8])
+                                          -> Result<(), AeadError> {
+        self.decrypt_segment(true, aad, buf, tag)
+    }
+
+    /// Decrypts a segment produced by `encrypt_last_in_place`
+    pub fn decrypt_last_in_place<B: Buffer + ?Sized>(&mut self, aad: &[u8],
+                                                     buf: &mut B)
+                                                     -> Result<(), AeadError> {
+        self.decrypt_segment_attached(true, aad, buf)
+    }
+}